@@ -0,0 +1,99 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Parse captured MI sessions: a stepping session, a deep `-stack-list-frames` and a
+//! `-var-update` storm. Run with `cargo bench --bench parser`. Record a baseline before
+//! changing the parser with `-- --save-baseline before`, then compare against it with
+//! `-- --baseline before`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const STEPPING: &[&str] = &[
+    "12^done,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"0x0000555555555131\",func=\"main\",file=\"main.c\",fullname=\"/home/me/main.c\",line=\"5\",thread-groups=[\"i1\"],times=\"0\",original-location=\"main\"}\n",
+    "13^running\n",
+    "*running,thread-id=\"all\"\n",
+    "=thread-group-started,id=\"i1\",pid=\"4242\"\n",
+    "=library-loaded,id=\"/lib/x86_64-linux-gnu/libc.so.6\",target-name=\"/lib/x86_64-linux-gnu/libc.so.6\",host-name=\"/lib/x86_64-linux-gnu/libc.so.6\",symbols-loaded=\"0\",thread-group=\"i1\"\n",
+    "~\"Breakpoint 1, main () at main.c:5\\n\"\n",
+    "*stopped,reason=\"breakpoint-hit\",disp=\"keep\",bkptno=\"1\",frame={addr=\"0x0000555555555131\",func=\"main\",args=[],file=\"main.c\",fullname=\"/home/me/main.c\",line=\"5\",arch=\"i386:x86-64\"},thread-id=\"1\",stopped-threads=\"all\",core=\"3\"\n",
+    "&\"warning: Error disabling address space randomization: Operation not permitted\\n\"\n",
+    "14^done,value=\"42\"\n",
+];
+
+/// `-stack-list-frames` of a deep recursion
+fn stack_list(depth: usize) -> String {
+    let frames: Vec<String> = (0..depth)
+        .map(|level| {
+            format!(
+                "frame={{level=\"{}\",addr=\"0x00005555555551{:02x}\",func=\"visit\",file=\"tree.cpp\",fullname=\"/home/me/src/tree.cpp\",line=\"{}\",arch=\"i386:x86-64\"}}",
+                level,
+                level % 256,
+                40 + level % 7
+            )
+        })
+        .collect();
+    format!("21^done,stack=[{}]\n", frames.join(","))
+}
+
+/// `-var-update --all-values *` with `count` changed varobjs, on one line
+fn var_update(count: usize) -> String {
+    let changes: Vec<String> = (0..count)
+        .map(|i| {
+            format!(
+                "{{name=\"var{}.data.[{}]\",value=\"{}\",in_scope=\"true\",type_changed=\"false\",has_more=\"0\"}}",
+                i / 100,
+                i % 100,
+                i * 3
+            )
+        })
+        .collect();
+    format!("35^done,changelist=[{}]\n", changes.join(","))
+}
+
+fn bench_lines(c: &mut Criterion, name: &str, lines: &[String]) {
+    let bytes: usize = lines.iter().map(String::len).sum();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(bytes as u64));
+    group.bench_function("parse_line", |b| {
+        b.iter(|| {
+            for line in lines {
+                black_box(gdb::parse_line(black_box(line)).unwrap());
+            }
+        })
+    });
+    group.bench_function("parse_line_borrowed", |b| {
+        b.iter(|| {
+            for line in lines {
+                black_box(gdb::parse_line_borrowed(black_box(line)).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn parser(c: &mut Criterion) {
+    let stepping: Vec<String> = STEPPING.iter().map(|line| line.to_string()).collect();
+    bench_lines(c, "stepping", &stepping);
+    bench_lines(c, "stack_list_frames", &[stack_list(1000)]);
+    bench_lines(c, "var_update", &[var_update(5000)]);
+    // a storm of small updates, one record per line
+    let updates: Vec<String> = (0..1000).map(|_| var_update(3)).collect();
+    bench_lines(c, "var_update_lines", &updates);
+}
+
+criterion_group!(benches, parser);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Feed arbitrary chunks to a `MiParser`. Run with `cargo fuzz run mi_parser`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|chunks: Vec<Vec<u8>>| {
+    let mut parser = gdb::MiParser::new();
+    for chunk in &chunks {
+        parser.feed(chunk);
+    }
+    parser.finish();
+});
@@ -0,0 +1,36 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Feed arbitrary lines to the `parse_line()` variants: they must return
+//! an error, never panic or overflow the stack. Run with `cargo fuzz run parse_line`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut line = String::from_utf8_lossy(data).into_owned();
+    line.push('\n');
+    let owned = gdb::parse_line(&line);
+    let borrowed = gdb::parse_line_borrowed(&line);
+    let lenient = gdb::parse_line_lenient(&line);
+    assert_eq!(owned.is_ok(), borrowed.is_ok());
+    // a record that parses is complete in lenient mode too
+    if owned.is_ok() {
+        assert!(matches!(lenient, Ok((_, None))));
+    }
+});
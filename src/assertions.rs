@@ -0,0 +1,72 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Assertions for tests that drive a real debug session. Each helper panics with a
+//! message describing what was received instead, and returns what it matched so tests
+//! can check more
+
+use crate::dbg::Debugger;
+use crate::events::StopEvent;
+use crate::msg::{self, MessageRecord, ResultClass};
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+
+/// Wait up to `timeout` for the target to stop, and check that it stopped at line `line`
+/// of `file`. `file` may be a file name or a path suffix, it is compared with the end of
+/// the full path reported by gdb
+pub async fn expect_stop_at(
+    dbg: &Debugger,
+    output_channel: &mut Receiver<msg::Record>,
+    file: &str,
+    line: u32,
+    timeout: Duration,
+) -> StopEvent {
+    let Ok(stop) = tokio::time::timeout(timeout, dbg.wait_for_stop(output_channel)).await else {
+        panic!(
+            "expected a stop at {}:{}, the target did not stop within {:?}",
+            file, line, timeout
+        );
+    };
+    let stop = stop.expect("gdb exited before the target stopped");
+    let frame = stop.frame.clone().unwrap_or_default();
+    let path = frame.fullname.or(frame.file).unwrap_or_default().replace('\\', "/");
+    let at_file = path == file || path.ends_with(&format!("/{}", file.trim_start_matches('/')));
+    assert!(
+        at_file && frame.line == Some(line),
+        "expected a stop at {}:{}, stopped at {}:{:?} (reason: {:?})",
+        file,
+        line,
+        path,
+        frame.line,
+        stop.reason
+    );
+    stop
+}
+
+/// Read the next result record and check its class
+pub async fn expect_result_class(
+    dbg: &Debugger,
+    output_channel: &mut Receiver<msg::Record>,
+    class: ResultClass,
+) -> MessageRecord<ResultClass> {
+    let record = dbg
+        .read_result_record(output_channel)
+        .await
+        .expect("gdb exited before printing a result");
+    assert_eq!(class, record.class, "unexpected result: {}", record);
+    record
+}
@@ -0,0 +1,133 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Result};
+use crate::events::Frame;
+use crate::msg::{self, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// Functions a complete backtrace may end with
+const OUTERMOST_FUNCTIONS: [&str; 4] = ["main", "_start", "__libc_start_main", "start_thread"];
+
+/// The stack of a thread, see `Debugger::backtrace()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backtrace {
+    /// Innermost first
+    pub frames: Vec<Frame>,
+    pub quality: BacktraceQuality,
+}
+
+/// How much a backtrace can be trusted, so frontends can explain bad stacks
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BacktraceQuality {
+    Complete,
+    /// The backtrace stops at `main`, as gdb does by default. `set backtrace past-main on`
+    /// shows the runtime frames calling it
+    EndsAtMain,
+    /// The backtrace ends early (a single frame, or an unknown outermost function),
+    /// unwinding probably failed. The hints tell why
+    Suspicious(Vec<BacktraceHint>),
+}
+
+/// A probable cause of a suspicious backtrace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BacktraceHint {
+    /// The outermost frame has no function name (`??`)
+    UnknownFunction,
+    /// The executable has neither `.eh_frame` nor `.debug_frame`: without call frame
+    /// information gdb relies on frame pointers, which optimized code often omits
+    NoUnwindInfo,
+    /// The executable has no `.debug_info`, it was probably stripped
+    Stripped,
+}
+
+fn is_unknown(func: Option<&str>) -> bool {
+    func.is_none_or(|func| func.is_empty() || func == "??")
+}
+
+/// Judge a backtrace from its frames alone. `Suspicious` hints that need the executable's
+/// sections are added by `Debugger::backtrace()`
+pub(crate) fn assess(frames: &[Frame]) -> BacktraceQuality {
+    let Some(outermost) = frames.last() else {
+        return BacktraceQuality::Suspicious(Vec::new());
+    };
+    let func = outermost.func.as_deref();
+    if func == Some("main") {
+        return BacktraceQuality::EndsAtMain;
+    }
+    if is_unknown(func) {
+        return BacktraceQuality::Suspicious(vec![BacktraceHint::UnknownFunction]);
+    }
+    if frames.len() == 1 && !func.is_some_and(|func| OUTERMOST_FUNCTIONS.contains(&func)) {
+        return BacktraceQuality::Suspicious(Vec::new());
+    }
+    BacktraceQuality::Complete
+}
+
+/// Parse `stack=[frame={...},frame={...}]`
+pub(crate) fn parse_frames(stack: Option<&Value>) -> Vec<Frame> {
+    match stack {
+        Some(Value::ResultList(vars) | Value::VariableList(vars)) => vars
+            .iter()
+            .filter_map(|var| Frame::from_value(&var.value))
+            .collect(),
+        Some(Value::ValueList(values)) => values.iter().filter_map(Frame::from_value).collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Debugger {
+    /// The stack of `thread`, or of the selected thread (`-stack-list-frames`). When it
+    /// looks truncated the executable's sections are checked for unwind and debug info,
+    /// see `BacktraceQuality`
+    pub async fn backtrace(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        thread: Option<&str>,
+    ) -> Result<Backtrace> {
+        let cmd = match thread {
+            Some(thread) => format!("-stack-list-frames --thread {}", thread),
+            None => "-stack-list-frames".to_string(),
+        };
+        let record = self.send_cmd(output_channel, &cmd).await?;
+        let frames = parse_frames(record.get("stack"));
+        let mut quality = assess(&frames);
+        if let BacktraceQuality::Suspicious(hints) = &mut quality {
+            // lists all the sections of the executable, debug ones included
+            match self.console(output_channel, "maint info sections").await {
+                Ok(sections) => hints.extend(section_hints(&sections)),
+                Err(e) => tracing::debug!("cannot list the sections: {}", e),
+            }
+        }
+        Ok(Backtrace { frames, quality })
+    }
+}
+
+/// Hints from the output of `maint info sections`
+pub(crate) fn section_hints(sections: &str) -> Vec<BacktraceHint> {
+    let has = |name: &str| sections.split_whitespace().any(|word| word == name);
+    let mut hints = Vec::new();
+    if !has(".eh_frame") && !has(".debug_frame") {
+        hints.push(BacktraceHint::NoUnwindInfo);
+    }
+    if !has(".debug_info") {
+        hints.push(BacktraceHint::Stripped);
+    }
+    hints
+}
@@ -0,0 +1,150 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Synchronous API, for programs that don't want to be async just to drive gdb.
+//! The gdb reader/writer tasks run on a dedicated runtime thread owned by the `Debugger`
+
+use crate::breakpoints::BreakpointTable;
+use crate::dbg::{self, Result};
+use crate::events::StopEvent;
+use crate::msg::{self, Record};
+use crate::session::SessionSnapshotView;
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::mpsc::Receiver;
+
+pub struct Debugger {
+    inner: dbg::Debugger,
+    output_channel: Receiver<Record>,
+    // dropped last: the inner debugger needs it to shut down
+    runtime: Runtime,
+}
+
+impl Debugger {
+    /// Start a new gdb process
+    pub fn start() -> Result<Self> {
+        Self::start_with_backend(dbg::GdbBackend::default())
+    }
+
+    /// Same as `start()`, talking to the process started by `backend`, e.g. a
+    /// `MockBackend` in tests
+    pub fn start_with_backend(backend: impl dbg::DebuggerBackend + 'static) -> Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("rust-gdb")
+            .enable_all()
+            .build()?;
+        let (inner, output_channel) =
+            runtime.block_on(dbg::Debugger::start_with_backend(backend))?;
+        Ok(Debugger {
+            inner,
+            output_channel,
+            runtime,
+        })
+    }
+
+    /// Run any async `Debugger` method to completion, e.g.
+    /// `dbg.run(async |dbg, rx| dbg.step_over(rx).await)`
+    pub fn run<F, T>(&mut self, f: F) -> T
+    where
+        F: AsyncFnOnce(&dbg::Debugger, &mut Receiver<Record>) -> T,
+    {
+        self.runtime
+            .block_on(f(&self.inner, &mut self.output_channel))
+    }
+
+    /// Send command to gdb
+    pub fn send_cmd_raw(&mut self, cmd: &str) -> Result<()> {
+        self.runtime.block_on(self.inner.send_cmd_raw(cmd))
+    }
+
+    /// Send command to gdb and wait for its result record
+    pub fn send_cmd(&mut self, cmd: &str) -> Result<msg::MessageRecord<msg::ResultClass>> {
+        self.runtime
+            .block_on(self.inner.send_cmd(&mut self.output_channel, cmd))
+    }
+
+    /// Run a CLI command and return what it printed on the console
+    pub fn console(&mut self, cmd: &str) -> Result<String> {
+        self.runtime
+            .block_on(self.inner.console(&mut self.output_channel, cmd))
+    }
+
+    /// Read the first result record, discarding everything before it
+    pub fn read_result_record(&mut self) -> Result<msg::MessageRecord<msg::ResultClass>> {
+        self.runtime
+            .block_on(self.inner.read_result_record(&mut self.output_channel))
+    }
+
+    /// Read the next record
+    pub fn read_message_record(&mut self) -> Result<Record> {
+        self.runtime
+            .block_on(self.inner.read_message_record(&mut self.output_channel))
+    }
+
+    /// Wait for the program to stop
+    pub fn wait_for_stop(&mut self) -> Result<StopEvent> {
+        self.runtime
+            .block_on(self.inner.wait_for_stop(&mut self.output_channel))
+    }
+
+    /// interrupt the running process
+    pub fn interrupt(&self) -> bool {
+        self.inner.interrupt()
+    }
+
+    /// Send `sig` to the debuggee
+    pub fn send_signal(&self, sig: crate::signals::DebugSignal) -> std::io::Result<()> {
+        self.inner.send_signal(sig)
+    }
+
+    /// Send `sig` to the debuggee's process group
+    pub fn send_signal_to_group(&self, sig: crate::signals::DebugSignal) -> std::io::Result<()> {
+        self.inner.send_signal_to_group(sig)
+    }
+
+    /// Choose how `interrupt()` stops the target
+    pub fn set_interrupt_strategy(&self, strategy: dbg::InterruptStrategy) {
+        self.inner.set_interrupt_strategy(strategy)
+    }
+
+    /// Is gdb still running?
+    pub fn is_alive(&self) -> bool {
+        self.inner.is_alive()
+    }
+
+    /// can we send commands to the debugger now?
+    pub fn can_send_commands(&self) -> bool {
+        self.inner.can_send_commands()
+    }
+
+    pub fn get_debuggee_pid(&self, inferior: Option<&str>) -> Option<usize> {
+        self.inner.get_debuggee_pid(inferior)
+    }
+
+    pub fn snapshot(&self) -> SessionSnapshotView {
+        self.inner.snapshot()
+    }
+
+    pub fn breakpoints(&self) -> BreakpointTable {
+        self.inner.breakpoints()
+    }
+
+    /// Access the underlying async debugger
+    pub fn inner(&self) -> &dbg::Debugger {
+        &self.inner
+    }
+}
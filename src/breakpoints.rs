@@ -0,0 +1,751 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg::{self, find_field, AsyncClass, MessageRecord, Value, Variable};
+use crate::session::BreakpointSummary;
+use crate::tracepoints::{is_tracepoint, TracepointOptions};
+use crate::version::Version;
+use crate::watchpoints::{WatchKind, WatchpointOptions};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+use std::path::Path;
+use tokio::sync::mpsc::Receiver;
+
+/// A breakpoint as reported by gdb (`bkpt={...}`)
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Breakpoint {
+    pub number: String,
+    /// The breakpoint type, e.g. `breakpoint` or `hw watchpoint`
+    pub kind: Option<String>,
+    /// Catchpoints: the event caught, e.g. `throw`, `syscall` or `fork`
+    pub catch_type: Option<String>,
+    /// `keep` or `del` (temporary breakpoints)
+    pub disposition: Option<String>,
+    pub enabled: bool,
+    pub addr: Option<String>,
+    pub func: Option<String>,
+    pub file: Option<String>,
+    pub fullname: Option<String>,
+    pub line: Option<u32>,
+    /// The location as it was requested by the user
+    pub original_location: Option<String>,
+    /// Pending breakpoints: the location gdb could not resolve yet, e.g. in a shared
+    /// library that is not loaded. `addr` is `<PENDING>` in that case
+    pub pending: Option<String>,
+    pub hit_count: u32,
+    /// The condition that must hold for the breakpoint to stop the program
+    pub condition: Option<String>,
+    /// Number of hits to ignore before stopping
+    pub ignore_count: u32,
+    /// The thread the breakpoint is restricted to
+    pub thread: Option<String>,
+    /// Tracepoints: the hits after which the trace experiment stops, 0 for never
+    pub pass_count: u32,
+    /// Commands executed when the breakpoint is hit, the actions of a tracepoint
+    pub script: Vec<String>,
+    /// The individual locations of a breakpoint that resolved to several addresses
+    /// (e.g. a template function), `addr` is `<MULTIPLE>` in that case
+    pub locations: Vec<BreakpointLocation>,
+}
+
+/// One location of a multi-location breakpoint, numbered `n.k`
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BreakpointLocation {
+    pub number: String,
+    pub enabled: bool,
+    pub addr: Option<String>,
+    pub func: Option<String>,
+    pub file: Option<String>,
+    pub fullname: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl BreakpointLocation {
+    pub fn from_value(value: &Value) -> Option<BreakpointLocation> {
+        let Value::VariableList(vars) = value else {
+            return None;
+        };
+        let text = |name| find_field(vars, name).and_then(Value::unquoted);
+        Some(BreakpointLocation {
+            number: text("number")?,
+            enabled: text("enabled").as_deref() == Some("y"),
+            addr: text("addr"),
+            func: text("func"),
+            file: text("file"),
+            fullname: text("fullname"),
+            line: text("line").and_then(|s| s.parse().ok()),
+        })
+    }
+}
+
+impl Breakpoint {
+    /// Build a breakpoint from the tuple value of a `bkpt=` field
+    pub fn from_value(value: &Value) -> Option<Breakpoint> {
+        match value {
+            Value::VariableList(vars) => Self::from_vars(vars),
+            _ => None,
+        }
+    }
+
+    /// Build a breakpoint from the fields of a record carrying `bkpt=`. MI2 appends the
+    /// locations of a multi-location breakpoint to the record as anonymous tuples:
+    /// `bkpt={...,addr="<MULTIPLE>"},{number="1.1",...},{number="1.2",...}`
+    pub fn from_results(vars: &[Variable]) -> Option<Breakpoint> {
+        let start = vars.iter().position(|var| var.name == "bkpt")?;
+        Self::all_from_results(&vars[start..]).into_iter().next()
+    }
+
+    /// All the `bkpt=` fields of `vars`, e.g. the body of `-break-list`'s
+    /// `BreakpointTable`, with the MI2 locations following a breakpoint moved into it
+    pub fn all_from_results(vars: &[Variable]) -> Vec<Breakpoint> {
+        let mut breakpoints: Vec<Breakpoint> = Vec::new();
+        // are anonymous tuples locations of the last breakpoint?
+        let mut in_breakpoint = false;
+        for var in vars {
+            if var.name == "bkpt" {
+                in_breakpoint = false;
+                if let Some(bkpt) = Self::from_value(&var.value) {
+                    in_breakpoint = bkpt.locations.is_empty();
+                    breakpoints.push(bkpt);
+                }
+            } else if var.name.is_empty() && in_breakpoint {
+                let bkpt = breakpoints.last_mut().expect("no breakpoint");
+                bkpt.locations.extend(BreakpointLocation::from_value(&var.value));
+            } else {
+                in_breakpoint = false;
+            }
+        }
+        breakpoints
+    }
+
+    pub(crate) fn from_vars(vars: &[Variable]) -> Option<Breakpoint> {
+        let text = |name| find_field(vars, name).and_then(Value::unquoted);
+        Some(Breakpoint {
+            number: text("number")?,
+            kind: text("type"),
+            catch_type: text("catch-type"),
+            disposition: text("disp"),
+            enabled: text("enabled").as_deref() == Some("y"),
+            addr: text("addr"),
+            func: text("func"),
+            file: text("file"),
+            fullname: text("fullname"),
+            line: text("line").and_then(|s| s.parse().ok()),
+            // watchpoints report the watched expression instead
+            original_location: text("original-location").or_else(|| text("exp")),
+            pending: text("pending"),
+            hit_count: text("times").and_then(|s| s.parse().ok()).unwrap_or(0),
+            condition: text("cond"),
+            ignore_count: text("ignore").and_then(|s| s.parse().ok()).unwrap_or(0),
+            thread: text("thread"),
+            pass_count: text("pass").and_then(|s| s.parse().ok()).unwrap_or(0),
+            script: match find_field(vars, "script") {
+                Some(Value::ValueList(commands)) => {
+                    commands.iter().filter_map(Value::unquoted).collect()
+                }
+                _ => Vec::new(),
+            },
+            // MI3 lists the locations in the tuple, see `from_results()` for MI2
+            locations: match find_field(vars, "locations") {
+                Some(Value::ValueList(locations)) => locations
+                    .iter()
+                    .filter_map(BreakpointLocation::from_value)
+                    .collect(),
+                _ => Vec::new(),
+            },
+        })
+    }
+
+    /// Is gdb waiting for a shared library to resolve the location? See
+    /// `BreakpointOptions::pending`
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some() || self.addr.as_deref() == Some("<PENDING>")
+    }
+
+    /// Location `number` (`n.k`) of this breakpoint
+    pub fn location_by_number(&self, number: &str) -> Option<&BreakpointLocation> {
+        self.locations.iter().find(|l| l.number == number)
+    }
+
+    /// The location shown to the user: the original location if known, `file:line` otherwise
+    pub fn location(&self) -> Option<String> {
+        self.original_location.clone().or_else(|| {
+            let file = self.file.as_ref()?;
+            Some(format!("{}:{}", file, self.line?))
+        })
+    }
+
+    /// Is this breakpoint set in source file `path`?
+    fn is_in_file(&self, path: &Path) -> bool {
+        self.fullname.as_deref().map(Path::new) == Some(path)
+            || self.file.as_deref().is_some_and(|file| path.ends_with(file))
+    }
+
+    pub fn summary(&self) -> BreakpointSummary {
+        BreakpointSummary {
+            number: self.number.clone(),
+            location: self.location(),
+            enabled: self.enabled,
+            hit_count: self.hit_count,
+        }
+    }
+}
+
+/// Options for `Debugger::insert_breakpoint()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BreakpointOptions {
+    /// Only stop when `condition` holds (`-c`)
+    pub condition: Option<String>,
+    /// Ignore the first `n` hits (`-i`)
+    pub ignore_count: Option<u32>,
+    /// Only stop in this thread (`-p`)
+    pub thread: Option<String>,
+    /// Delete the breakpoint once hit (`-t`)
+    pub temporary: bool,
+    /// Use a hardware breakpoint (`-h`)
+    pub hardware: bool,
+    /// Create the breakpoint disabled (`-d`)
+    pub disabled: bool,
+    /// Only match functions with this fully qualified name, not in every namespace or
+    /// class (`--qualified`). Requires gdb 8.1
+    pub qualified: bool,
+    /// Create a pending breakpoint if the location can't be resolved yet, e.g. a
+    /// function of a library the program loads later with `dlopen()` (`-f`). gdb binds
+    /// it once the library is loaded, see `DebuggerEvent::BreakpointBound`
+    pub pending: bool,
+    /// Commands to execute when the breakpoint is hit (set with `-break-commands`)
+    pub commands: Vec<String>,
+}
+
+impl BreakpointOptions {
+    /// Build the `-break-insert` command for `location`
+    pub(crate) fn insert_cmd(&self, location: &str) -> String {
+        self.command("-break-insert", location)
+    }
+
+    /// Build `command`, `-break-insert` or `-dprintf-insert`, for `location`
+    pub(crate) fn command(&self, command: &str, location: &str) -> String {
+        let mut cmd = command.to_string();
+        if self.temporary {
+            cmd.push_str(" -t");
+        }
+        if self.hardware {
+            cmd.push_str(" -h");
+        }
+        if self.disabled {
+            cmd.push_str(" -d");
+        }
+        if self.qualified {
+            cmd.push_str(" --qualified");
+        }
+        if self.pending {
+            cmd.push_str(" -f");
+        }
+        if let Some(condition) = &self.condition {
+            cmd.push_str(&format!(" -c {}", mi_quote(condition)));
+        }
+        if let Some(count) = self.ignore_count {
+            cmd.push_str(&format!(" -i {}", count));
+        }
+        if let Some(thread) = &self.thread {
+            cmd.push_str(&format!(" -p {}", thread));
+        }
+        cmd.push(' ');
+        cmd.push_str(&mi_quote(location));
+        cmd
+    }
+}
+
+impl From<&Breakpoint> for BreakpointOptions {
+    /// The options that re-create `bkpt`
+    fn from(bkpt: &Breakpoint) -> Self {
+        BreakpointOptions {
+            condition: bkpt.condition.clone(),
+            ignore_count: Some(bkpt.ignore_count).filter(|n| *n > 0),
+            thread: bkpt.thread.clone(),
+            temporary: bkpt.disposition.as_deref() == Some("del"),
+            hardware: bkpt.kind.as_deref() == Some("hw breakpoint"),
+            disabled: !bkpt.enabled,
+            qualified: false,
+            pending: bkpt.is_pending(),
+            commands: bkpt.script.clone(),
+        }
+    }
+}
+
+/// The debugger's breakpoints, kept in creation order
+#[derive(Debug, Clone, Default)]
+pub struct BreakpointTable {
+    entries: Vec<Breakpoint>,
+    /// Named groups of breakpoint numbers
+    groups: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl BreakpointTable {
+    /// Add `bkpt` to the table, replacing any breakpoint with the same number
+    pub fn insert(&mut self, bkpt: Breakpoint) {
+        match self.entries.iter_mut().find(|b| b.number == bkpt.number) {
+            Some(existing) => *existing = bkpt,
+            None => self.entries.push(bkpt),
+        }
+    }
+
+    /// Remove breakpoint `number` from the table
+    pub fn remove(&mut self, number: &str) -> Option<Breakpoint> {
+        let pos = self.entries.iter().position(|b| b.number == number)?;
+        for members in self.groups.values_mut() {
+            members.remove(number);
+        }
+        Some(self.entries.remove(pos))
+    }
+
+    pub fn get(&self, number: &str) -> Option<&Breakpoint> {
+        self.entries.iter().find(|b| b.number == number)
+    }
+
+    pub fn get_mut(&mut self, number: &str) -> Option<&mut Breakpoint> {
+        self.entries.iter_mut().find(|b| b.number == number)
+    }
+
+    /// Location `number` (`n.k`) of a multi-location breakpoint
+    pub fn get_location_mut(&mut self, number: &str) -> Option<&mut BreakpointLocation> {
+        let (parent, _) = number.split_once('.')?;
+        self.get_mut(parent)?
+            .locations
+            .iter_mut()
+            .find(|l| l.number == number)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Lines `edited` of source file `path` were replaced with `edited.len() + delta` lines.
+    /// Move the breakpoints of that file with the statements they are set on, so they are
+    /// inserted at the right line once re-created (e.g. with `restore_breakpoints()`).
+    /// A `delta` removing more lines than `edited` has removes the whole block
+    pub fn shift_lines(&mut self, path: &Path, edited: Range<u32>, delta: i32) {
+        // no line can move before the start of the edit
+        let delta = (delta as i64).max(-(edited.end.saturating_sub(edited.start) as i64));
+        // last line of the edited block after the edit
+        let new_end = (edited.end as i64 + delta).max(edited.start as i64 + 1) - 1;
+        for bkpt in self.entries.iter_mut().filter(|b| b.is_in_file(path)) {
+            let Some(line) = bkpt.line else {
+                continue;
+            };
+            let new_line = if line < edited.start {
+                continue;
+            } else if line >= edited.end {
+                (line as i64 + delta) as u32
+            } else {
+                // inside the edit, stay within what is left of it
+                line.min(new_end as u32)
+            };
+            // re-write `file:line` locations, keep function and address locations as is
+            let suffix = format!(":{}", line);
+            if let Some(location) = &bkpt.original_location {
+                if let Some(file) = location.strip_suffix(&suffix) {
+                    if path.ends_with(file) || Path::new(file).ends_with(path) {
+                        bkpt.original_location = Some(format!("{}:{}", file, new_line));
+                    }
+                }
+            }
+            bkpt.line = Some(new_line);
+        }
+    }
+
+    /// Add breakpoint `number` to `group`, creating the group if needed
+    pub fn add_to_group(&mut self, group: &str, number: &str) {
+        self.groups
+            .entry(group.to_string())
+            .or_default()
+            .insert(number.to_string());
+    }
+
+    /// Remove breakpoint `number` from `group`
+    pub fn remove_from_group(&mut self, group: &str, number: &str) {
+        if let Some(members) = self.groups.get_mut(group) {
+            members.remove(number);
+        }
+    }
+
+    /// The numbers of the breakpoints in `group`
+    pub fn group(&self, group: &str) -> Vec<String> {
+        self.groups
+            .get(group)
+            .map(|members| members.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// The names of the groups `number` belongs to
+    pub fn groups_of(&self, number: &str) -> Vec<String> {
+        self.groups
+            .iter()
+            .filter(|(_, members)| members.contains(number))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// All group names
+    pub fn group_names(&self) -> Vec<String> {
+        self.groups.keys().cloned().collect()
+    }
+}
+
+/// The pending breakpoint of `table` that a `=breakpoint-modified` record binds to an
+/// address, once the library defining its location was loaded
+pub(crate) fn bound_breakpoint(
+    table: &BreakpointTable,
+    record: &MessageRecord<AsyncClass>,
+) -> Option<Breakpoint> {
+    if record.class != AsyncClass::BreakpointModified {
+        return None;
+    }
+    let bkpt = Breakpoint::from_results(&record.content)?;
+    let was_pending = table.get(&bkpt.number).is_some_and(Breakpoint::is_pending);
+    (was_pending && !bkpt.is_pending()).then_some(bkpt)
+}
+
+impl Debugger {
+    /// Insert a breakpoint at `location` and return it as reported by gdb
+    pub async fn insert_breakpoint(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        location: &str,
+        options: &BreakpointOptions,
+    ) -> Result<Breakpoint> {
+        if options.qualified {
+            self.require_gdb(Version::QUALIFIED_BREAKPOINTS, "--qualified")?;
+        }
+        let record = self
+            .send_cmd(output_channel, &options.insert_cmd(location))
+            .await?;
+        let bkpt = Breakpoint::from_results(&record.content).ok_or(Error::UnexpectedResponse)?;
+        if options.commands.is_empty() {
+            return Ok(bkpt);
+        }
+        self.set_breakpoint_commands(output_channel, &bkpt.number, &options.commands)
+            .await?;
+        Ok(Breakpoint {
+            script: options.commands.clone(),
+            ..bkpt
+        })
+    }
+
+    /// Create a breakpoint with CLI command `cmd`, for the kinds MI has no command for
+    pub(crate) async fn insert_with_console(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        cmd: &str,
+    ) -> Result<Breakpoint> {
+        let before: Vec<String> = self.breakpoints().iter().map(|b| b.number.clone()).collect();
+        self.send_cmd(
+            output_channel,
+            &format!("-interpreter-exec console {}", mi_quote(cmd)),
+        )
+        .await?;
+        // console commands are reported with `=breakpoint-created`, which was processed
+        // before the result
+        self.breakpoints()
+            .iter()
+            .find(|b| !before.contains(&b.number))
+            .cloned()
+            .ok_or(Error::UnexpectedResponse)
+    }
+
+    /// List gdb's breakpoints (`-break-list`), multi-location breakpoints with their
+    /// locations. The breakpoint table is brought in sync with the list, e.g. the hit
+    /// counts are updated and breakpoints deleted behind our back are removed
+    pub async fn list_breakpoints(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<Vec<Breakpoint>> {
+        let record = self.send_cmd(output_channel, "-break-list").await?;
+        let table = record.get("BreakpointTable").ok_or(Error::UnexpectedResponse)?;
+        // `body=[]` without breakpoints
+        let breakpoints = match table.get("body") {
+            Some(Value::ResultList(vars)) => Breakpoint::all_from_results(vars),
+            _ => Vec::new(),
+        };
+
+        let mut session = self.session.lock().unwrap();
+        let deleted: Vec<_> = session
+            .breakpoints
+            .iter()
+            .filter(|old| !breakpoints.iter().any(|bkpt| bkpt.number == old.number))
+            .map(|old| old.number.clone())
+            .collect();
+        for number in deleted {
+            session.breakpoints.remove(&number);
+        }
+        for bkpt in &breakpoints {
+            session.breakpoints.insert(bkpt.clone());
+        }
+        Ok(breakpoints)
+    }
+
+    /// Re-create the breakpoints of `breakpoints` (e.g. a table saved with `breakpoints()`)
+    /// including their conditions, ignore counts and command scripts.
+    /// Return the outcome for each breakpoint, in table order
+    pub async fn restore_breakpoints(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        breakpoints: &BreakpointTable,
+    ) -> Vec<Result<Breakpoint>> {
+        let mut results = Vec::new();
+        for bkpt in breakpoints.iter() {
+            let Some(location) = bkpt.location() else {
+                results.push(Err(Error::UnexpectedResponse));
+                continue;
+            };
+            let kind = bkpt.kind.as_deref().unwrap_or_default();
+            let result = match WatchKind::from_breakpoint_type(kind) {
+                None if is_tracepoint(kind) => {
+                    let options = TracepointOptions::from(bkpt);
+                    self.insert_tracepoint(output_channel, &location, &options)
+                        .await
+                }
+                None if kind == "dprintf" => {
+                    let options = BreakpointOptions::from(bkpt);
+                    match bkpt.dprintf_format() {
+                        Some((format, args)) => {
+                            self.insert_dprintf(output_channel, &location, &format, &args, &options)
+                                .await
+                        }
+                        None => Err(Error::UnexpectedResponse),
+                    }
+                }
+                Some(kind) => {
+                    let options = WatchpointOptions {
+                        kind,
+                        thread: bkpt.thread.clone(),
+                        frame: None,
+                    };
+                    self.insert_watchpoint(output_channel, &location, &options)
+                        .await
+                }
+                None => {
+                    let options = BreakpointOptions::from(bkpt);
+                    self.insert_breakpoint(output_channel, &location, &options)
+                        .await
+                }
+            };
+            if let Ok(new_bkpt) = &result {
+                // gdb numbers the new breakpoint differently, carry the groups over
+                let mut session = self.session.lock().unwrap();
+                for group in breakpoints.groups_of(&bkpt.number) {
+                    session.breakpoints.add_to_group(&group, &new_bkpt.number);
+                }
+            }
+            results.push(result);
+        }
+        results
+    }
+
+    /// Replace the commands executed when breakpoint `number` is hit.
+    /// An empty list clears them
+    pub async fn set_breakpoint_commands(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: &str,
+        commands: &[String],
+    ) -> Result<()> {
+        let mut cmd = format!("-break-commands {}", number);
+        for command in commands {
+            cmd.push(' ');
+            cmd.push_str(&mi_quote(command));
+        }
+        self.send_cmd(output_channel, &cmd).await?;
+        if let Some(bkpt) = self.session.lock().unwrap().breakpoints.get_mut(number) {
+            bkpt.script = commands.to_vec();
+        }
+        Ok(())
+    }
+
+    /// Make breakpoint `number` conditional. An empty `condition` makes it unconditional
+    pub async fn set_breakpoint_condition(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: &str,
+        condition: &str,
+    ) -> Result<()> {
+        let cmd = match condition.is_empty() {
+            true => format!("-break-condition {}", number),
+            false => format!("-break-condition {} {}", number, mi_quote(condition)),
+        };
+        self.send_cmd(output_channel, &cmd).await?;
+        // gdb does not notify about changes made by MI commands
+        if let Some(bkpt) = self.session.lock().unwrap().breakpoints.get_mut(number) {
+            bkpt.condition = Some(condition.to_string()).filter(|c| !c.is_empty());
+        }
+        Ok(())
+    }
+
+    /// Ignore the next `count` hits of breakpoint `number`
+    pub async fn set_ignore_count(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: &str,
+        count: u32,
+    ) -> Result<()> {
+        self.send_cmd(output_channel, &format!("-break-after {} {}", number, count))
+            .await?;
+        if let Some(bkpt) = self.session.lock().unwrap().breakpoints.get_mut(number) {
+            bkpt.ignore_count = count;
+        }
+        Ok(())
+    }
+
+    /// Enable a single location `n.k` of a multi-location breakpoint
+    pub async fn enable_breakpoint_location(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: &str,
+    ) -> Result<()> {
+        self.set_location_enabled(output_channel, number, true).await
+    }
+
+    /// Disable a single location `n.k` of a multi-location breakpoint, the other
+    /// locations keep stopping the program
+    pub async fn disable_breakpoint_location(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: &str,
+    ) -> Result<()> {
+        self.set_location_enabled(output_channel, number, false).await
+    }
+
+    async fn set_location_enabled(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        let cmd = if enabled { "-break-enable" } else { "-break-disable" };
+        self.send_cmd(output_channel, &format!("{} {}", cmd, number)).await?;
+        let mut session = self.session.lock().unwrap();
+        if let Some(location) = session.breakpoints.get_location_mut(number) {
+            location.enabled = enabled;
+        }
+        Ok(())
+    }
+}
+
+/// Source edits
+impl Debugger {
+    /// Tell the debugger that lines `edited` of source file `path` were replaced with
+    /// `edited.len() + delta` lines. See `BreakpointTable::shift_lines()`
+    pub fn source_lines_shifted(&self, path: &Path, edited: Range<u32>, delta: i32) {
+        self.session
+            .lock()
+            .unwrap()
+            .breakpoints
+            .shift_lines(path, edited, delta);
+    }
+}
+
+/// Breakpoint groups: named sets of breakpoints that are enabled, disabled or deleted
+/// together, each with a single MI command
+impl Debugger {
+    /// Add breakpoint `number` to `group`
+    pub fn add_breakpoint_to_group(&self, group: &str, number: &str) {
+        self.session
+            .lock()
+            .unwrap()
+            .breakpoints
+            .add_to_group(group, number);
+    }
+
+    /// Remove breakpoint `number` from `group`
+    pub fn remove_breakpoint_from_group(&self, group: &str, number: &str) {
+        self.session
+            .lock()
+            .unwrap()
+            .breakpoints
+            .remove_from_group(group, number);
+    }
+
+    /// Enable all the breakpoints of `group`
+    pub async fn enable_group(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        group: &str,
+    ) -> Result<()> {
+        self.set_group_enabled(output_channel, group, true).await
+    }
+
+    /// Disable all the breakpoints of `group`
+    pub async fn disable_group(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        group: &str,
+    ) -> Result<()> {
+        self.set_group_enabled(output_channel, group, false).await
+    }
+
+    async fn set_group_enabled(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        group: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        let numbers = self.breakpoints().group(group);
+        if numbers.is_empty() {
+            return Ok(());
+        }
+        let cmd = if enabled { "-break-enable" } else { "-break-disable" };
+        self.send_cmd(output_channel, &format!("{} {}", cmd, numbers.join(" ")))
+            .await?;
+        let mut session = self.session.lock().unwrap();
+        for number in &numbers {
+            if let Some(bkpt) = session.breakpoints.get_mut(number) {
+                bkpt.enabled = enabled;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete all the breakpoints of `group`. The group itself is kept, empty
+    pub async fn delete_group(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        group: &str,
+    ) -> Result<()> {
+        let numbers = self.breakpoints().group(group);
+        if numbers.is_empty() {
+            return Ok(());
+        }
+        self.send_cmd(output_channel, &format!("-break-delete {}", numbers.join(" ")))
+            .await?;
+        let mut session = self.session.lock().unwrap();
+        for number in &numbers {
+            session.breakpoints.remove(number);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{is_reply, Debugger, Error, Result};
+use crate::msg::{self, MessageRecord, Record, ResultClass, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// What gdb supports, from `-list-features` and `-list-target-features`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The target can run in the background (`async`)
+    pub async_execution: bool,
+    /// The target can execute in reverse (`reverse`)
+    pub reverse: bool,
+    /// `-data-read-memory-bytes` is available
+    pub data_read_memory_bytes: bool,
+    /// Breakpoints changed with CLI commands are reported with `=breakpoint-*` records
+    pub breakpoint_notifications: bool,
+    /// `-exec-run --start` is available
+    pub exec_run_start_option: bool,
+    /// `-break-insert -f` creates pending breakpoints
+    pub pending_breakpoints: bool,
+    /// `-thread-info` is available
+    pub thread_info: bool,
+    /// gdb has Python support (`python`)
+    pub python: bool,
+    /// Every feature gdb listed, target features included
+    pub features: Vec<String>,
+}
+
+impl Capabilities {
+    /// Build from the feature names of `-list-features` and `-list-target-features`
+    pub fn from_features(features: &[String], target_features: &[String]) -> Self {
+        let has = |name: &str| features.iter().any(|f| f == name);
+        let target_has = |name: &str| target_features.iter().any(|f| f == name);
+        Capabilities {
+            async_execution: target_has("async"),
+            reverse: target_has("reverse"),
+            data_read_memory_bytes: has("data-read-memory-bytes"),
+            breakpoint_notifications: has("breakpoint-notifications"),
+            exec_run_start_option: has("exec-run-start-option"),
+            pending_breakpoints: has("pending-breakpoints"),
+            thread_info: has("thread-info"),
+            python: has("python"),
+            features: features.iter().chain(target_features).cloned().collect(),
+        }
+    }
+
+    /// Did gdb list `feature`?
+    pub fn has(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+/// The names in the `features=[...]` list of `record`
+fn feature_names(record: &MessageRecord<ResultClass>) -> Vec<String> {
+    match record.get("features") {
+        Some(Value::ValueList(names)) => names.iter().filter_map(Value::unquoted).collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Debugger {
+    /// Query what gdb and the current target support. Done on startup, run it again
+    /// after connecting to a target to update the target features. Fails on gdbs too old
+    /// for `-list-features`
+    pub async fn detect_capabilities(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<Capabilities> {
+        let features = match self.query(output_channel, "-list-features").await {
+            Ok(record) => feature_names(&record),
+            Err(e) => {
+                self.session.lock().unwrap().capabilities = None;
+                return Err(e);
+            }
+        };
+        // only failing queries count as missing target features
+        let target_features = match self.query(output_channel, "-list-target-features").await {
+            Ok(record) => feature_names(&record),
+            Err(_) => Vec::new(),
+        };
+        let capabilities = Capabilities::from_features(&features, &target_features);
+        tracing::debug!("capabilities: {:?}", capabilities);
+        self.session.lock().unwrap().capabilities = Some(capabilities.clone());
+        Ok(capabilities)
+    }
+
+    /// `send_cmd()`, setting the other records aside for `read_message_record()`: the
+    /// capabilities are detected on startup, before anyone reads the output channel
+    pub(crate) async fn query(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        cmd: &str,
+    ) -> Result<MessageRecord<ResultClass>> {
+        let (token, cmd) = self.tokens.tag(cmd);
+        let _guard = self.commands.lock().await;
+        self.send_cmd_raw(&cmd).await?;
+        let mut aside = Vec::new();
+        let record = loop {
+            match self.read_message_record(output_channel).await {
+                Ok(Record::Result(record)) if is_reply(&record, token) => break Ok(record),
+                Ok(record) => aside.push(record),
+                Err(e) => break Err(e),
+            }
+        };
+        let mut pending = self.pending.lock().unwrap();
+        for record in aside.into_iter().rev() {
+            pending.push_front(record);
+        }
+        let record = record?;
+        if record.class == ResultClass::Error {
+            let msg = record.get("msg").and_then(Value::unquoted);
+            return Err(Error::GdbError(msg.unwrap_or_default()));
+        }
+        Ok(record)
+    }
+
+    /// What gdb supports. `None` if it could not be detected
+    pub fn capabilities(&self) -> Option<Capabilities> {
+        self.session.lock().unwrap().capabilities.clone()
+    }
+
+    /// Does gdb support what `check` looks for? Assumed when the capabilities are
+    /// unknown, so the modern command forms are the default
+    pub(crate) fn supports(&self, check: impl Fn(&Capabilities) -> bool) -> bool {
+        self.session.lock().unwrap().capabilities.as_ref().is_none_or(check)
+    }
+}
@@ -0,0 +1,212 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::Breakpoint;
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::events::StopEvent;
+use crate::msg::{self, AsyncClass, MessageRecord, Value};
+use crate::version::Version;
+use tokio::sync::mpsc::Receiver;
+
+/// The event a catchpoint stops the program on, see `Debugger::insert_catchpoint()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatchEvent {
+    /// A C++ exception is thrown, optionally only the types matching a regex
+    Throw(Option<String>),
+    /// A C++ exception is caught
+    Catch(Option<String>),
+    /// A C++ exception is rethrown
+    Rethrow(Option<String>),
+    /// A system call is entered or returns. Names or numbers, every system call when
+    /// empty
+    Syscall(Vec<String>),
+    /// A signal is delivered. Names or numbers, e.g. `SIGUSR1`, or `all`. Every signal
+    /// but the ones used by the debugger (`SIGTRAP` and `SIGINT`) when empty
+    Signal(Vec<String>),
+    Fork,
+    Vfork,
+    /// The program calls `exec`
+    Exec,
+    /// A shared library is loaded, optionally only the ones matching a regex
+    Load(Option<String>),
+    /// A shared library is unloaded
+    Unload(Option<String>),
+}
+
+impl CatchEvent {
+    /// The `-catch-*` command, `None` for the events MI has no command for
+    fn mi_cmd(&self) -> Option<String> {
+        let regex = |flag: &str, regex: &Option<String>| match regex {
+            Some(regex) => format!(" {}{}", flag, mi_quote(regex)),
+            None => String::new(),
+        };
+        // the regex is required, an empty one matches every library
+        let library = |regex: &Option<String>| mi_quote(regex.as_deref().unwrap_or(""));
+        Some(match self {
+            CatchEvent::Throw(r) => format!("-catch-throw{}", regex("-r ", r)),
+            CatchEvent::Catch(r) => format!("-catch-catch{}", regex("-r ", r)),
+            CatchEvent::Rethrow(r) => format!("-catch-rethrow{}", regex("-r ", r)),
+            CatchEvent::Load(r) => format!("-catch-load {}", library(r)),
+            CatchEvent::Unload(r) => format!("-catch-unload {}", library(r)),
+            _ => return None,
+        })
+    }
+
+    /// The `catch` CLI command
+    fn console_cmd(&self) -> String {
+        let args = |command: &str, args: &[String]| {
+            args.iter().fold(command.to_string(), |cmd, arg| cmd + " " + arg)
+        };
+        let regex = |command: &str, regex: &Option<String>| match regex {
+            Some(regex) => format!("{} {}", command, regex),
+            None => command.to_string(),
+        };
+        match self {
+            CatchEvent::Throw(r) => regex("catch throw", r),
+            CatchEvent::Catch(r) => regex("catch catch", r),
+            CatchEvent::Rethrow(r) => regex("catch rethrow", r),
+            CatchEvent::Syscall(syscalls) => args("catch syscall", syscalls),
+            CatchEvent::Signal(signals) => args("catch signal", signals),
+            CatchEvent::Fork => "catch fork".to_string(),
+            CatchEvent::Vfork => "catch vfork".to_string(),
+            CatchEvent::Exec => "catch exec".to_string(),
+            CatchEvent::Load(r) => regex("catch load", r),
+            CatchEvent::Unload(r) => regex("catch unload", r),
+        }
+    }
+
+    fn is_exception(&self) -> bool {
+        matches!(
+            self,
+            CatchEvent::Throw(_) | CatchEvent::Catch(_) | CatchEvent::Rethrow(_)
+        )
+    }
+}
+
+/// Why a catchpoint stopped the program, see `StopEvent::catchpoint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatchpointHit {
+    /// A C++ exception was thrown, caught or rethrown: `throw`, `catch` or `rethrow`
+    Exception(String),
+    /// A system call was entered, or returned
+    Syscall {
+        returned: bool,
+        name: Option<String>,
+        number: Option<u32>,
+    },
+    Fork { child_pid: Option<u32> },
+    Vfork { child_pid: Option<u32> },
+    Exec { path: Option<String> },
+    /// A shared library was loaded or unloaded
+    Library,
+    /// A signal is about to be delivered
+    Signal { name: Option<String> },
+}
+
+impl CatchpointHit {
+    /// The catchpoint hits told apart by the stop reason of a `*stopped` record.
+    /// Exception and signal catchpoints need the catchpoint, see
+    /// `CatchpointHit::from_catchpoint()`
+    pub fn from_record(record: &MessageRecord<AsyncClass>) -> Option<CatchpointHit> {
+        let text = |name| record.get(name).and_then(Value::unquoted);
+        let number = |name| text(name).and_then(|s| s.parse().ok());
+        Some(match text("reason")?.as_str() {
+            reason @ ("syscall-entry" | "syscall-return") => CatchpointHit::Syscall {
+                returned: reason == "syscall-return",
+                name: text("syscall-name"),
+                number: number("syscall-number"),
+            },
+            "fork" => CatchpointHit::Fork {
+                child_pid: number("newpid"),
+            },
+            "vfork" => CatchpointHit::Vfork {
+                child_pid: number("newpid"),
+            },
+            "exec" => CatchpointHit::Exec {
+                path: text("new-exec"),
+            },
+            "solib-event" => CatchpointHit::Library,
+            _ => return None,
+        })
+    }
+
+    /// The hit of catchpoint `catchpoint`, stopping with a `breakpoint-hit` reason or
+    /// none at all
+    pub fn from_catchpoint(catchpoint: &Breakpoint) -> Option<CatchpointHit> {
+        if catchpoint.kind.as_deref() != Some("catchpoint") {
+            return None;
+        }
+        match catchpoint.catch_type.as_deref()? {
+            kind @ ("throw" | "catch" | "rethrow") => {
+                Some(CatchpointHit::Exception(kind.to_string()))
+            }
+            "signal" => Some(CatchpointHit::Signal { name: None }),
+            _ => None,
+        }
+    }
+}
+
+impl Debugger {
+    /// Stop the program when `event` happens. The hits are reported by `wait_for_stop()`
+    /// in `StopEvent::catchpoint`
+    pub async fn insert_catchpoint(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        event: &CatchEvent,
+    ) -> Result<Breakpoint> {
+        let too_old = matches!(self.gdb_version(), Some(v) if v < Version::CATCH_EXCEPTIONS);
+        let mi_cmd = event.mi_cmd().filter(|_| !(event.is_exception() && too_old));
+        let Some(cmd) = mi_cmd else {
+            return self
+                .insert_with_console(output_channel, &event.console_cmd())
+                .await;
+        };
+        let record = self.send_cmd(output_channel, &cmd).await?;
+        Breakpoint::from_results(&record.content).ok_or(Error::UnexpectedResponse)
+    }
+
+    /// Fill `StopEvent::catchpoint` for the catchpoints only known by their number.
+    /// Signal catchpoints are only reported on the console: `Catchpoint 2 (signal
+    /// SIGUSR1), `, `output` are the records received before the stop
+    pub(crate) fn resolve_catchpoint(&self, stop: &mut StopEvent, output: &[msg::Record]) {
+        if stop.catchpoint.is_some() {
+            return;
+        }
+        if let Some(number) = &stop.breakpoint {
+            let session = self.session.lock().unwrap();
+            stop.catchpoint = session
+                .breakpoints
+                .get(number)
+                .and_then(CatchpointHit::from_catchpoint);
+            return;
+        }
+        let signal = output.iter().rev().find_map(|record| match record {
+            msg::Record::Stream(msg::StreamRecord::Console(text)) => {
+                let text = msg::unquote(text);
+                let (_, rest) = text.split_once("atchpoint ")?;
+                let (number, rest) = rest.split_once(" (signal ")?;
+                let (name, _) = rest.split_once(')')?;
+                Some((number.to_string(), name.to_string()))
+            }
+            _ => None,
+        });
+        if let Some((number, name)) = signal {
+            stop.breakpoint = Some(number);
+            stop.catchpoint = Some(CatchpointHit::Signal { name: Some(name) });
+        }
+    }
+}
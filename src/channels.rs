@@ -0,0 +1,127 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Result};
+use crate::msg::{AsyncRecord, Record};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::{channel, unbounded_channel, Receiver, UnboundedSender};
+
+/// gdb output split by record kind. A receiver can be passed to the `Debugger` methods
+/// that take an output channel and only read its kind of records, e.g.
+/// `send_cmd(&mut channels.results, ...)` or `wait_for_stop(&mut channels.exec)`. Methods
+/// that read several kinds need the unsplit channel: `console()` collects stream records
+/// until the result, and `wait_for_stop()` reads the console output of signal catchpoints
+/// from the same channel as the stop, so `StopEvent::catchpoint` stays unset for them
+/// when waiting on `exec`
+pub struct DebuggerChannels {
+    /// Result records (`^done`, `^error`, ...)
+    pub results: Receiver<Record>,
+    /// Exec and status async records (`*stopped`, `*running`, `+download`)
+    pub exec: Receiver<Record>,
+    /// Notify async records (`=breakpoint-created`, `=library-loaded`, ...)
+    pub notify: Receiver<Record>,
+    /// Console, target and log stream records, lines that could not be parsed
+    /// (`Record::Raw`) and prompts, which end the output of a console command
+    pub stream: Receiver<Record>,
+    /// Events generated by the crate (`Record::Event`)
+    pub events: Receiver<Record>,
+}
+
+impl DebuggerChannels {
+    /// Split `output_channel` by record kind. A task forwards each record to its channel
+    /// until `output_channel` is closed. Records sent to a dropped receiver are discarded.
+    /// Each kind is queued separately, so a slow consumer of one channel does not hold
+    /// back the others
+    pub fn split(mut output_channel: Receiver<Record>) -> DebuggerChannels {
+        let (results_sender, results) = forward();
+        let (exec_sender, exec) = forward();
+        let (notify_sender, notify) = forward();
+        let (stream_sender, stream) = forward();
+        let (events_sender, events) = forward();
+        tokio::spawn(async move {
+            while let Some(record) = output_channel.recv().await {
+                let sender = match &record {
+                    Record::Result(_) => &results_sender,
+                    Record::Async(AsyncRecord::Exec(_) | AsyncRecord::Status(_)) => &exec_sender,
+                    Record::Async(AsyncRecord::Notify(_)) => &notify_sender,
+                    Record::Stream(_) | Record::Raw(_) | Record::Prompt => &stream_sender,
+                    Record::Event(_) => &events_sender,
+                };
+                let _ = sender.send(record);
+            }
+        });
+        DebuggerChannels {
+            results,
+            exec,
+            notify,
+            stream,
+            events,
+        }
+    }
+}
+
+/// A channel whose sending side never waits: records are queued until the receiver
+/// catches up
+fn forward() -> (UnboundedSender<Record>, Receiver<Record>) {
+    let (queue_sender, mut queue) = unbounded_channel::<Record>();
+    let (sender, receiver) = channel::<Record>(100);
+    tokio::spawn(async move {
+        while let Some(record) = queue.recv().await {
+            if sender.send(record).await.is_err() {
+                break;
+            }
+        }
+    });
+    (queue_sender, receiver)
+}
+
+/// Returned by `Debugger::suppress_streams()`, stream records are delivered again once
+/// dropped
+#[must_use = "streams are suppressed only while the guard is alive"]
+pub struct StreamSuppression {
+    counter: Arc<AtomicUsize>,
+}
+
+impl Drop for StreamSuppression {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Debugger {
+    /// Drop console, target and log stream records while the returned guard is alive,
+    /// e.g. to keep the frontend console clean while restoring dozens of breakpoints.
+    /// Result and async records are delivered as usual
+    pub fn suppress_streams(&self) -> StreamSuppression {
+        let counter = self.events.suppress_streams.clone();
+        counter.fetch_add(1, Ordering::Relaxed);
+        StreamSuppression { counter }
+    }
+
+    /// Same as `start()`, with gdb output split by record kind
+    pub async fn start_with_channels() -> Result<(Self, DebuggerChannels)> {
+        let (dbg, output_channel) = Self::start().await?;
+        Ok((dbg, DebuggerChannels::split(output_channel)))
+    }
+
+    /// Split the output channel returned by `start()` into one receiver per record kind,
+    /// see `DebuggerChannels`
+    pub fn split_channels(&self, output_channel: Receiver<Record>) -> DebuggerChannels {
+        DebuggerChannels::split(output_channel)
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::Debugger;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+/// Number of lines preceding a failure kept as context
+const CONTEXT_LINES: usize = 10;
+
+/// A line of gdb output the MI parser could not handle
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFailure {
+    pub line: String,
+    /// gdb's version banner, if it was seen
+    pub gdb_version: Option<String>,
+    /// The lines received before `line`, oldest first
+    pub context: Vec<String>,
+}
+
+/// Receives the lines of gdb output that failed to parse, see
+/// `Debugger::set_parse_error_sink()`
+pub trait ParseErrorSink: Send {
+    fn record(&mut self, failure: &ParseFailure);
+}
+
+impl<F: FnMut(&ParseFailure) + Send> ParseErrorSink for F {
+    fn record(&mut self, failure: &ParseFailure) {
+        self(failure)
+    }
+}
+
+/// A sink appending failures to a text file, ready to be turned into parser test cases
+pub struct ParseErrorFile {
+    pub path: PathBuf,
+}
+
+impl ParseErrorSink for ParseErrorFile {
+    fn record(&mut self, failure: &ParseFailure) {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path);
+        let result = file.and_then(|mut file| {
+            writeln!(
+                file,
+                "# gdb: {}",
+                failure.gdb_version.as_deref().unwrap_or("unknown")
+            )?;
+            for line in &failure.context {
+                writeln!(file, "  {}", line)?;
+            }
+            writeln!(file, "> {}", failure.line)?;
+            writeln!(file)
+        });
+        if let Err(e) = result {
+            tracing::debug!("failed to write to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+pub(crate) type SharedSink = Arc<Mutex<Option<Box<dyn ParseErrorSink>>>>;
+
+/// Owned by the reader task: remembers the recent output and reports failures to the sink
+pub(crate) struct ParseErrorCapture {
+    sink: SharedSink,
+    context: VecDeque<String>,
+    gdb_version: Option<String>,
+}
+
+impl ParseErrorCapture {
+    pub fn new(sink: SharedSink) -> Self {
+        ParseErrorCapture {
+            sink,
+            context: VecDeque::with_capacity(CONTEXT_LINES),
+            gdb_version: None,
+        }
+    }
+
+    /// Called for each line read from gdb
+    pub fn on_line(&mut self, line: &str, parsed: bool) {
+        if self.gdb_version.is_none() && line.starts_with("~\"GNU gdb") {
+            self.gdb_version = Some(crate::msg::unquote(&line[1..]).trim().to_string());
+        }
+        if !parsed && !line.trim().is_empty() {
+            if let Some(sink) = self.sink.lock().unwrap().as_mut() {
+                sink.record(&ParseFailure {
+                    line: line.to_string(),
+                    gdb_version: self.gdb_version.clone(),
+                    context: self.context.iter().cloned().collect(),
+                });
+            }
+        }
+        if self.context.len() == CONTEXT_LINES {
+            self.context.pop_front();
+        }
+        self.context.push_back(line.to_string());
+    }
+}
+
+impl Debugger {
+    /// Report the lines of gdb output that can't be parsed to `sink`, instead of
+    /// silently dropping them. Pass `None` to stop reporting
+    pub fn set_parse_error_sink(&self, sink: Option<Box<dyn ParseErrorSink>>) {
+        *self.parse_errors.lock().unwrap() = sink;
+    }
+
+    /// Keep the records that break off with the results parsed before the error (see
+    /// `parse_line_lenient()`), instead of passing the line on as `Record::Raw`
+    pub fn set_lenient_parsing(&self, lenient: bool) {
+        self.lenient_parsing.store(lenient, Ordering::Relaxed);
+    }
+
+    /// Limit the nesting of tuples and lists in the records read from gdb, instead of
+    /// `DEFAULT_MAX_NESTING_DEPTH`. Deeper records fail to parse like other broken lines
+    pub fn set_max_nesting_depth(&self, depth: usize) {
+        self.max_nesting_depth.store(depth, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,464 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! A Debug Adapter Protocol server, so DAP clients (e.g. VS Code) can drive gdb through
+//! the crate. DAP messages are JSON bodies framed by a `Content-Length` header
+
+use crate::breakpoints::BreakpointOptions;
+use crate::dbg::{Debugger, Error, Result};
+use crate::frames::FrameContext;
+use crate::launch::LaunchConfig;
+use crate::lifecycle::EndPolicy;
+use crate::msg::{self, AsyncClass, AsyncRecord, Record, StreamRecord, Value};
+use crate::variables::{variable_values, PrintValues};
+use serde_json::json;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite};
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::mpsc::{self, Receiver};
+
+/// Breakpoints set by a client for a source file are kept in this group, so a
+/// `setBreakpoints` request replaces them with a single `-break-delete`
+const SOURCE_GROUP_PREFIX: &str = "dap:";
+
+/// Read one DAP message. Return `None` once the client closed the stream
+pub(crate) async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+) -> Result<Option<serde_json::Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            let invalid = || Error::ParseError {
+                line: line.to_string(),
+                offset: line.len() - value.trim_start().len(),
+                expected: "a length",
+            };
+            length = Some(value.trim().parse::<usize>().map_err(|_| invalid())?);
+        }
+    }
+    let length = length.ok_or(Error::ParseError {
+        line: String::new(),
+        offset: 0,
+        expected: "a Content-Length header",
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|err| Error::ParseError {
+            line: String::from_utf8_lossy(&body).into_owned(),
+            offset: err.column().saturating_sub(1),
+            expected: "a JSON message",
+        })
+}
+
+/// Frame `message` for the wire
+pub(crate) fn encode_message(message: &serde_json::Value) -> Vec<u8> {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes()
+}
+
+/// The DAP `stopped` reason for a gdb stop reason
+fn stop_reason(record: &msg::MessageRecord<AsyncClass>) -> &'static str {
+    let text = |name| record.get(name).and_then(Value::unquoted);
+    match text("reason").as_deref() {
+        Some("breakpoint-hit") => "breakpoint",
+        Some("watchpoint-trigger" | "read-watchpoint-trigger" | "access-watchpoint-trigger") => {
+            "data breakpoint"
+        }
+        Some("end-stepping-range" | "function-finished" | "location-reached") => "step",
+        Some("signal-received") if text("signal-name").as_deref() != Some("SIGINT") => {
+            "exception"
+        }
+        _ => "pause",
+    }
+}
+
+/// Translate a gdb record into the DAP events to send to the client
+pub(crate) fn events_for(record: &Record) -> Vec<(&'static str, serde_json::Value)> {
+    match record {
+        Record::Async(AsyncRecord::Exec(record)) if record.class == AsyncClass::Stopped => {
+            let text = |name| record.get(name).and_then(Value::unquoted);
+            let reason = text("reason").unwrap_or_default();
+            if reason.starts_with("exited") {
+                let code = text("exit-code")
+                    .and_then(|code| i64::from_str_radix(&code, 8).ok())
+                    .unwrap_or(0);
+                return vec![
+                    ("exited", json!({ "exitCode": code })),
+                    ("terminated", json!({})),
+                ];
+            }
+            let mut body = json!({
+                "reason": stop_reason(record),
+                "allThreadsStopped": true,
+            });
+            if let Some(thread) = text("thread-id").and_then(|id| id.parse::<i64>().ok()) {
+                body["threadId"] = json!(thread);
+            }
+            vec![("stopped", body)]
+        }
+        Record::Stream(StreamRecord::Console(text)) => {
+            vec![("output", json!({ "category": "console", "output": text }))]
+        }
+        Record::Stream(StreamRecord::Target(text)) => {
+            vec![("output", json!({ "category": "stdout", "output": text }))]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Serves a single DAP client, translating its requests into gdb commands and gdb's
+/// records into DAP events. Supported requests: `initialize`, `launch`,
+/// `setBreakpoints`, `configurationDone`, `threads`, `stackTrace`, `scopes`,
+/// `variables`, `continue`, `next`, `stepIn`, `stepOut`, `pause` and `disconnect`
+pub struct DapServer {
+    dbg: Debugger,
+    output_channel: Receiver<Record>,
+    seq: i64,
+    /// The frames handed out by the last `stackTrace` requests, a frame id is an index
+    /// in this list plus one. Cleared when the program resumes
+    frames: Vec<FrameContext>,
+}
+
+impl DapServer {
+    pub fn new(dbg: Debugger, output_channel: Receiver<Record>) -> Self {
+        DapServer {
+            dbg,
+            output_channel,
+            seq: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Serve a client talking on our stdin and stdout, the way editors spawn adapters
+    pub async fn serve_stdio(self) -> Result<()> {
+        self.serve(tokio::io::stdin(), tokio::io::stdout()).await
+    }
+
+    /// Wait for a client to connect on `addr` and serve it
+    pub async fn serve_tcp<A: ToSocketAddrs>(self, addr: A) -> Result<()> {
+        let listener = TcpListener::bind(addr).await?;
+        let (stream, _) = listener.accept().await?;
+        let (reader, writer) = stream.into_split();
+        self.serve(reader, writer).await
+    }
+
+    /// Serve the client until it disconnects or closes `reader`
+    pub async fn serve<R, W>(mut self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Unpin,
+    {
+        // reading a message is not cancel safe, do it on its own task
+        let (requests_tx, mut requests) = mpsc::channel(16);
+        let reader_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(reader);
+            while let Ok(Some(request)) = read_message(&mut reader).await {
+                if requests_tx.send(request).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let result = loop {
+            tokio::select! {
+                request = requests.recv() => {
+                    let Some(request) = request else {
+                        break Ok(());
+                    };
+                    let done = request["command"] == "disconnect";
+                    let messages = self.handle(&request).await;
+                    if let Err(e) = self.write(&mut writer, messages).await {
+                        break Err(e);
+                    }
+                    if done {
+                        break Ok(());
+                    }
+                }
+                record = self.dbg.read_message_record(&mut self.output_channel) => {
+                    // gdb exited
+                    let Ok(record) = record else {
+                        break Ok(());
+                    };
+                    let events: Vec<_> = events_for(&record)
+                        .into_iter()
+                        .map(|(event, body)| self.event(event, body))
+                        .collect();
+                    if let Err(e) = self.write(&mut writer, events).await {
+                        break Err(e);
+                    }
+                }
+            }
+        };
+        reader_task.abort();
+        result
+    }
+
+    async fn write<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        messages: Vec<serde_json::Value>,
+    ) -> Result<()> {
+        for message in messages {
+            writer.write_all(&encode_message(&message)).await?;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+
+    fn next_seq(&mut self) -> i64 {
+        self.seq += 1;
+        self.seq
+    }
+
+    fn event(&mut self, event: &str, body: serde_json::Value) -> serde_json::Value {
+        json!({ "seq": self.next_seq(), "type": "event", "event": event, "body": body })
+    }
+
+    /// Handle `request`, return the response followed by the events it triggers
+    async fn handle(&mut self, request: &serde_json::Value) -> Vec<serde_json::Value> {
+        let command = request["command"].as_str().unwrap_or_default().to_string();
+        let arguments = &request["arguments"];
+        let result = match command.as_str() {
+            "initialize" => Ok(json!({ "supportsConfigurationDoneRequest": true })),
+            "launch" => self.launch(arguments).await,
+            "setBreakpoints" => self.set_breakpoints(arguments).await,
+            "configurationDone" => self.resume(command.as_str()).await,
+            "threads" => self.threads().await,
+            "stackTrace" => self.stack_trace(arguments).await,
+            "scopes" => Ok(self.scopes(arguments)),
+            "variables" => self.variables(arguments).await,
+            "continue" | "next" | "stepIn" | "stepOut" => self.resume(command.as_str()).await,
+            "pause" => match self.dbg.interrupt() {
+                true => Ok(json!({})),
+                false => Err(Error::InterruptFailed),
+            },
+            "disconnect" => self
+                .dbg
+                .stop_debugging(&mut self.output_channel, EndPolicy::Kill)
+                .await
+                .map(|_| json!({})),
+            _ => Err(Error::InvalidArgument(format!("unsupported request {}", command))),
+        };
+        let mut response = json!({
+            "seq": self.next_seq(),
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": command,
+            "success": result.is_ok(),
+        });
+        match result {
+            Ok(body) => response["body"] = body,
+            Err(e) => response["message"] = json!(e.to_string()),
+        }
+        let mut messages = vec![response];
+        if command == "initialize" {
+            messages.push(self.event("initialized", json!({})));
+        }
+        messages
+    }
+
+    /// `launch`: load `program` with `args` in `cwd`. It is run by `configurationDone`,
+    /// once the client sent its breakpoints
+    async fn launch(&mut self, arguments: &serde_json::Value) -> Result<serde_json::Value> {
+        let program = arguments["program"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidArgument("launch: missing program".to_string()))?;
+        let strings = |value: &serde_json::Value| -> Vec<String> {
+            let values = value.as_array().cloned().unwrap_or_default();
+            values.iter().filter_map(|v| v.as_str().map(str::to_string)).collect()
+        };
+        let config = LaunchConfig {
+            executable: Some(PathBuf::from(program)),
+            args: strings(&arguments["args"]),
+            cwd: arguments["cwd"].as_str().map(str::to_string),
+            env: match arguments["env"].as_object() {
+                Some(env) => env
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                    .collect(),
+                None => Vec::new(),
+            },
+            ..Default::default()
+        };
+        self.dbg
+            .configure_launch(&mut self.output_channel, &config)
+            .await?;
+        Ok(json!({}))
+    }
+
+    /// `setBreakpoints`: replace the breakpoints of a source file
+    async fn set_breakpoints(
+        &mut self,
+        arguments: &serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let path = arguments["source"]["path"]
+            .as_str()
+            .ok_or_else(|| Error::InvalidArgument("setBreakpoints: missing path".to_string()))?;
+        let group = format!("{}{}", SOURCE_GROUP_PREFIX, path);
+        let rx = &mut self.output_channel;
+        self.dbg.delete_group(rx, &group).await?;
+
+        let lines = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+        let mut breakpoints = Vec::new();
+        for line in lines.iter().filter_map(|bp| bp["line"].as_u64()) {
+            let location = format!("{}:{}", path, line);
+            let options = BreakpointOptions::default();
+            breakpoints.push(match self.dbg.insert_breakpoint(rx, &location, &options).await {
+                Ok(bkpt) => {
+                    self.dbg.add_breakpoint_to_group(&group, &bkpt.number);
+                    json!({
+                        "id": bkpt.number.parse::<i64>().ok(),
+                        "verified": true,
+                        "line": bkpt.line.map(u64::from).unwrap_or(line),
+                    })
+                }
+                Err(e) => json!({ "verified": false, "line": line, "message": e.to_string() }),
+            });
+        }
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+
+    /// `configurationDone`, `continue` and the stepping requests
+    async fn resume(&mut self, command: &str) -> Result<serde_json::Value> {
+        self.frames.clear();
+        let rx = &mut self.output_channel;
+        match command {
+            "configurationDone" => self.dbg.run(rx).await?,
+            "next" => self.dbg.step_over(rx).await?,
+            "stepIn" => self.dbg.step_into(rx).await?,
+            "stepOut" => self.dbg.step_out(rx).await?,
+            _ => {
+                self.dbg.continue_(rx).await?;
+                return Ok(json!({ "allThreadsContinued": true }));
+            }
+        }
+        Ok(json!({}))
+    }
+
+    async fn threads(&mut self) -> Result<serde_json::Value> {
+        let record = self
+            .dbg
+            .send_cmd(&mut self.output_channel, "-thread-info")
+            .await?;
+        let threads: Vec<_> = match record.get("threads") {
+            Some(Value::ValueList(threads)) => threads
+                .iter()
+                .filter_map(|thread| match thread {
+                    Value::VariableList(vars) => Some(vars),
+                    _ => None,
+                })
+                .filter_map(|vars| {
+                    let text = |name| msg::find_field(vars, name).and_then(Value::unquoted);
+                    let id = text("id")?.parse::<i64>().ok()?;
+                    let name = text("name").or_else(|| text("target-id"));
+                    Some(json!({
+                        "id": id,
+                        "name": name.unwrap_or_else(|| format!("Thread {}", id)),
+                    }))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(json!({ "threads": threads }))
+    }
+
+    async fn stack_trace(&mut self, arguments: &serde_json::Value) -> Result<serde_json::Value> {
+        let thread = arguments["threadId"].as_i64().map(|id| id.to_string());
+        let backtrace = self
+            .dbg
+            .backtrace(&mut self.output_channel, thread.as_deref())
+            .await?;
+        let mut frames = Vec::new();
+        for frame in backtrace.frames {
+            let level = frame.level.unwrap_or(frames.len() as u32);
+            self.frames.push(match &thread {
+                Some(thread) => FrameContext::thread_frame(thread, level),
+                None => FrameContext::frame(level),
+            });
+            let mut json = json!({
+                "id": self.frames.len(),
+                "name": frame.func.unwrap_or_else(|| frame.addr.clone().unwrap_or_default()),
+                "line": frame.line.unwrap_or(0),
+                "column": 0,
+            });
+            if let Some(path) = frame.fullname.or(frame.file) {
+                json["source"] = json!({ "path": path });
+            }
+            frames.push(json);
+        }
+        let total = frames.len();
+        Ok(json!({ "stackFrames": frames, "totalFrames": total }))
+    }
+
+    /// A single scope per frame, its variables reference is the frame id
+    fn scopes(&self, arguments: &serde_json::Value) -> serde_json::Value {
+        let frame = arguments["frameId"].as_i64().unwrap_or(0);
+        json!({
+            "scopes": [{
+                "name": "Locals",
+                "presentationHint": "locals",
+                "variablesReference": frame,
+                "expensive": false,
+            }]
+        })
+    }
+
+    /// The arguments and locals of a frame
+    async fn variables(&mut self, arguments: &serde_json::Value) -> Result<serde_json::Value> {
+        let reference = arguments["variablesReference"].as_u64().unwrap_or(0) as usize;
+        let context = reference
+            .checked_sub(1)
+            .and_then(|index| self.frames.get(index))
+            .cloned()
+            .ok_or_else(|| Error::InvalidArgument(format!("unknown frame {}", reference)))?;
+        let rx = &mut self.output_channel;
+        let options = self.dbg.context_options(rx, &context).await?;
+        let record = self
+            .dbg
+            .send_cmd(
+                rx,
+                &format!(
+                    "-stack-list-variables {} {}",
+                    options,
+                    PrintValues::All.as_option()
+                ),
+            )
+            .await?;
+        let variables: Vec<_> = variable_values(record.get("variables"), false)
+            .into_iter()
+            .map(|var| {
+                json!({
+                    "name": var.name,
+                    "value": var.value.unwrap_or_default(),
+                    "variablesReference": 0,
+                })
+            })
+            .collect();
+        Ok(json!({ "variables": variables }))
+    }
+}
@@ -15,39 +15,127 @@
  * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use crate::breakpoints::{bound_breakpoint, BreakpointTable};
+use crate::corpus::{ParseErrorCapture, SharedSink};
+use crate::debuginfod::parse_debuginfod_line;
+use crate::download::DownloadProgress;
+use crate::events::{DebuggerEvent, ExitStatus, OutputSource};
+use crate::metrics::{is_result_line, CommandTrace};
+use crate::modules::Module;
 use crate::msg;
 use crate::msg::{AsyncClass, AsyncRecord, Record, ResultClass, Value};
 use crate::parser;
+use crate::remote::{is_disconnect_message, KEEPALIVE_TOKEN};
+use crate::render::SharedRenderers;
+use crate::session::{SessionSnapshotView, SessionState};
+use crate::state::{loads_program, DebuggerState};
+use crate::transcript::SharedRecorder;
+use crate::version::Version;
+use crate::watchpoints::out_of_scope_watchpoint;
 use std::{
+    collections::VecDeque,
     convert::From,
     fmt,
     process::Stdio,
     result, str,
     sync::{
         atomic::Ordering,
-        atomic::{AtomicBool, AtomicUsize},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize},
+        Arc, Mutex,
     },
 };
 use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
-    sync::mpsc::{channel, Receiver, Sender},
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter},
+    sync::{broadcast, watch},
+    sync::mpsc::{channel, error::TryRecvError, Receiver, Sender},
+    time::{Duration, Instant},
 };
 
 #[derive(Debug)]
 pub enum Error {
     IOError(std::io::Error),
-    ParseError,
+    /// A line from gdb is not valid MI: at byte `offset` of `line`, the parser expected
+    /// `expected` (e.g. "a value" or "`,` or `}`")
+    ParseError {
+        line: String,
+        offset: usize,
+        expected: &'static str,
+    },
+    /// gdb's reply lacks what the command should have returned
+    UnexpectedResponse,
     IgnoredOutput,
+    /// gdb replied with `^error`
+    GdbError(String),
+    /// The command is not available while debugging a core file
+    PostMortem,
+    /// Not connected to a remote target
+    NotConnected,
+    /// The running target could not be interrupted
+    InterruptFailed,
+    /// `with_stopped()` was called from inside a `with_stopped()` section
+    NestedFence,
+    /// A record nests tuples and lists deeper than the parser allows
+    TooDeep,
+    /// gdb did not reply in time
+    Timeout,
+    /// An argument was rejected before sending anything to gdb
+    InvalidArgument(String),
+    /// The command is not valid in the current state, see `DebuggerState`
+    InvalidState {
+        operation: String,
+        state: DebuggerState,
+    },
+    /// No program was loaded (`load_executable()`) or attached to
+    NoExecutable,
+    /// The task writing commands to gdb is gone
+    ChannelClosed,
+    /// gdb exited before talking MI, with what it printed on stderr
+    StartupFailed(String),
+    /// The running gdb is too old for `feature`
+    Unsupported {
+        feature: String,
+        required: Version,
+        found: Version,
+    },
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            &Error::IOError(ref err) => write!(f, "{}", err.to_string()),
-            &Error::ParseError => write!(f, "cannot parse response from gdb"),
-            &Error::IgnoredOutput => write!(f, "ignored output"),
+            Error::IOError(err) => write!(f, "{}", err),
+            Error::ParseError {
+                line,
+                offset,
+                expected,
+            } => write!(
+                f,
+                "cannot parse response from gdb: expected {} at offset {} of `{}`",
+                expected, offset, line
+            ),
+            Error::UnexpectedResponse => write!(f, "unexpected response from gdb"),
+            Error::IgnoredOutput => write!(f, "ignored output"),
+            Error::GdbError(msg) => write!(f, "gdb error: {}", msg),
+            Error::PostMortem => write!(f, "not available in a post-mortem session"),
+            Error::NotConnected => write!(f, "not connected to a remote target"),
+            Error::InterruptFailed => write!(f, "failed to interrupt the target"),
+            Error::NestedFence => write!(f, "with_stopped() sections can't be nested"),
+            Error::TooDeep => write!(f, "response from gdb is nested too deeply"),
+            Error::Timeout => write!(f, "timed out waiting for gdb"),
+            Error::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
+            Error::InvalidState { operation, state } => {
+                write!(f, "{} is not valid while the debugger is {:?}", operation, state)
+            }
+            Error::NoExecutable => write!(f, "no executable loaded"),
+            Error::ChannelClosed => write!(f, "the channel to gdb is closed"),
+            Error::StartupFailed(stderr) => write!(f, "gdb exited during startup: {}", stderr),
+            Error::Unsupported {
+                feature,
+                required,
+                found,
+            } => write!(f, "{} requires gdb {} or later, found gdb {}", feature, required, found),
         }
     }
 }
@@ -60,21 +148,419 @@ impl From<std::io::Error> for Error {
     }
 }
 
+/// Delivers records to the output channel and to the `subscribe()` receivers
+#[derive(Clone)]
+pub(crate) struct RecordSender {
+    channel: Sender<msg::Record>,
+    broadcast: broadcast::Sender<msg::Record>,
+    /// Number of live `StreamSuppression` guards
+    pub suppress_streams: Arc<AtomicUsize>,
+    /// See `Debugger::set_recorder()`
+    pub recorder: SharedRecorder,
+    /// Set once the reader task read the last line of gdb's output, the records gdb
+    /// printed are all in the channel then
+    pub output_closed: Arc<watch::Sender<bool>>,
+}
+
+impl RecordSender {
+    /// Fails when the output channel is closed
+    pub async fn send(&self, record: msg::Record) -> result::Result<(), ()> {
+        if let Some(recorder) = self.recorder.lock().unwrap().as_mut() {
+            recorder.on_record(&record);
+        }
+        if matches!(record, Record::Stream(_)) && self.suppress_streams.load(Ordering::Relaxed) > 0
+        {
+            return Ok(());
+        }
+        // having no subscribers is not an error
+        let _ = self.broadcast.send(record.clone());
+        self.channel.send(record).await.map_err(|_| ())
+    }
+}
+
 pub struct Debugger {
     /// We write to gdb raw string commands
     pub stdin: Sender<String>,
     /// gdb process ID
     pub gdb_pid: Arc<AtomicUsize>,
-    /// The debugger state
-    pub can_interact: Arc<AtomicBool>,
+    /// The debugger state, see `state()` and `watch_state()`
+    pub(crate) state: Arc<watch::Sender<DebuggerState>>,
     /// The debugee pid
     pub debugee_pid: Arc<AtomicUsize>,
+    /// Are we debugging a core file?
+    pub post_mortem: Arc<AtomicBool>,
+    /// Session state tracked from gdb's async records
+    pub(crate) session: Arc<Mutex<SessionState>>,
+    /// Used to emit crate generated events on the output channel
+    pub(crate) events: RecordSender,
+    /// The remote target keepalive task
+    pub(crate) keepalive: Mutex<Option<tokio::task::JoinHandle<()>>>,
+    /// Records set aside by `wait_for_stop()`, returned first by `read_message_record()`
+    pub(crate) pending: Arc<Mutex<VecDeque<msg::Record>>>,
+    /// Serializes `with_stopped()` sections, and holds back the execution commands of
+    /// other tasks while one runs
+    pub(crate) fence: Arc<tokio::sync::Mutex<()>>,
+    /// Held from sending a command until its result is read, so commands sent from
+    /// different tasks don't get each other's results
+    pub(crate) commands: Arc<tokio::sync::Mutex<()>>,
+    /// Where lines that fail to parse are reported
+    pub(crate) parse_errors: SharedSink,
+    /// Tokens for the commands sent by `send_cmd()` and `console()`
+    pub(crate) tokens: TokenGenerator,
+    /// See `set_lenient_parsing()`
+    pub(crate) lenient_parsing: Arc<AtomicBool>,
+    /// See `set_max_nesting_depth()`
+    pub(crate) max_nesting_depth: Arc<AtomicUsize>,
+    /// Counters updated by the reader task
+    pub(crate) stats: Arc<StatsCounters>,
+    /// Custom value renderers, see `register_renderer()`
+    pub(crate) renderers: SharedRenderers,
+    /// The gdbserver started by `start_gdbserver()`
+    pub(crate) gdbserver: Mutex<Option<tokio::process::Child>>,
+    /// Spawns gdb again, see `restart_gdb()`
+    pub(crate) backend: Mutex<Box<dyn DebuggerBackend>>,
+    /// gdb's stdin, replaced when gdb is restarted
+    pub(crate) writer: SharedWriter,
+    /// See `set_auto_restart()`
+    pub(crate) auto_restart: AtomicBool,
+    /// Set while the supervisor mode restarts gdb
+    pub(crate) restarting: AtomicBool,
+    /// Set by the writer task when writing to gdb failed, returned by the next
+    /// `send_cmd_raw()`
+    pub(crate) write_error: Arc<Mutex<Option<std::io::Error>>>,
+}
+
+/// Hands out the tokens commands are sent with, so their result records can be told
+/// apart from the results of other commands
+#[derive(Debug)]
+pub struct TokenGenerator {
+    next: AtomicU64,
+}
+
+impl Default for TokenGenerator {
+    fn default() -> Self {
+        TokenGenerator {
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl TokenGenerator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token not handed out before. Never the token of the keepalive queries
+    pub fn next_token(&self) -> u64 {
+        loop {
+            let token = self.next.fetch_add(1, Ordering::Relaxed);
+            if token != 0 && token != KEEPALIVE_TOKEN {
+                return token;
+            }
+        }
+    }
+
+    /// Put a new token in front of `cmd`, unless it has one already. Returns the token
+    /// and the command
+    pub fn tag(&self, cmd: &str) -> (u64, String) {
+        let (token, command) = strip_token(cmd);
+        match token.parse() {
+            Ok(token) => (token, cmd.trim().to_string()),
+            Err(_) => {
+                let token = self.next_token();
+                (token, format!("{}{}", token, command))
+            }
+        }
+    }
+}
+
+/// Split `12-exec-run` into `("12", "-exec-run")`
+pub(crate) fn strip_token(command: &str) -> (&str, &str) {
+    let command = command.trim();
+    let digits = command.len() - command.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    command.split_at(digits)
+}
+
+/// Is `record` the result of the command sent with `token`? Results without a token are
+/// accepted too, for backends that don't echo tokens
+pub(crate) fn is_reply(record: &msg::MessageRecord<ResultClass>, token: u64) -> bool {
+    record.token.is_none_or(|t| t == token)
+}
+
+pub(crate) type SharedWriter =
+    Arc<tokio::sync::Mutex<BufWriter<Box<dyn AsyncWrite + Send + Unpin>>>>;
+
+/// What the gdb reader tasks share with the `Debugger`
+#[derive(Clone)]
+pub(crate) struct ReaderContext {
+    pub sender: RecordSender,
+    pub state: Arc<watch::Sender<DebuggerState>>,
+    pub debugee_pid: Arc<AtomicUsize>,
+    pub session: Arc<Mutex<SessionState>>,
+    pub parse_errors: SharedSink,
+    pub lenient_parsing: Arc<AtomicBool>,
+    pub max_nesting_depth: Arc<AtomicUsize>,
+    pub stats: Arc<StatsCounters>,
+}
+
+/// Counters about the gdb output, see `Debugger::stats()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DebuggerStats {
+    /// Lines read from gdb's stdout, prompts excluded
+    pub lines_read: u64,
+    /// Lines that could not be parsed
+    pub parse_failures: u64,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StatsCounters {
+    pub lines_read: AtomicU64,
+    pub parse_failures: AtomicU64,
+    /// See `Debugger::metrics()`
+    pub commands_sent: AtomicU64,
+    pub results_received: AtomicU64,
+    pub latency_total_us: AtomicU64,
+    pub latency_samples: AtomicU64,
+}
+
+/// How `Debugger::interrupt()` stops a running target
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InterruptStrategy {
+    /// Send `SIGINT` to the debuggee. Requires its pid, so this does not work for remote
+    /// targets or before gdb reports that the process started
+    #[default]
+    Debuggee,
+    /// Send `SIGINT` to gdb, which interrupts the target the same way Ctrl-C does in a
+    /// terminal. Works for remote targets
+    Gdb,
+    /// Send `-exec-interrupt`. gdb reads commands while the target runs only when
+    /// `mi-async` is on, so `-gdb-set mi-async on` must be sent before the target is started
+    ExecInterrupt,
+    /// Send `CTRL_BREAK_EVENT` to the debuggee's console process group. Windows only, for
+    /// console programs started in a process group of their own. Elsewhere it sends
+    /// `SIGINT` to the debuggee, like `Debuggee`
+    ConsoleCtrlEvent,
+}
+
+/// How long `start()` waits for gdb to talk MI before returning anyway
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many stderr lines printed during startup are kept for `Error::StartupFailed`
+const MAX_STARTUP_LINES: usize = 100;
+
+/// Reads lines like `AsyncBufReadExt::lines()`, without failing on invalid UTF-8: the
+/// target may print binary data. Invalid sequences are replaced with U+FFFD. Lines are
+/// not limited in length
+pub(crate) struct LossyLines<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> LossyLines<R> {
+    pub fn new(reader: R) -> Self {
+        LossyLines {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+        }
+    }
+
+    /// The next line without its line ending, `None` at the end of the input
+    pub async fn next_line(&mut self) -> std::io::Result<Option<String>> {
+        self.buf.clear();
+        if self.reader.read_until(b'\n', &mut self.buf).await? == 0 {
+            return Ok(None);
+        }
+        if self.buf.ends_with(b"\n") {
+            self.buf.pop();
+            if self.buf.ends_with(b"\r") {
+                self.buf.pop();
+            }
+        }
+        Ok(Some(String::from_utf8_lossy(&self.buf).into_owned()))
+    }
+}
+
+/// `line` as the c-string of a stream record, newline included
+fn stderr_text(line: &str) -> String {
+    let escaped = line.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\\n\"", escaped)
+}
+
+pub(crate) fn escape_command(cmd: &str) -> String {
+    cmd.replace("\r", "\\r").replace("\n", "\\n")
+}
+
+/// Wrap `s` with double quotes so it can be passed as an MI c-string argument
+pub(crate) fn mi_quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        // a line break would end the command
+        .replace('\n', "\\n")
+        .replace('\r', "\\r");
+    format!("\"{}\"", escaped)
+}
+
+/// Wrap `s` with double quotes for CLI commands splitting their arguments like a shell,
+/// e.g. `skip` or `set substitute-path`
+pub(crate) fn cli_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The I/O of a process speaking MI, returned by `DebuggerBackend::spawn()`
+pub struct BackendIo {
+    /// Where MI output is read from
+    pub stdout: Box<dyn AsyncRead + Send + Unpin>,
+    /// Where commands are written to
+    pub stdin: Box<dyn AsyncWrite + Send + Unpin>,
+    /// Diagnostics, reported as startup noise until MI output shows up
+    pub stderr: Option<Box<dyn AsyncRead + Send + Unpin>>,
+    /// The process ID, used by `terminate()` and `InterruptStrategy::Gdb`
+    pub pid: Option<u32>,
 }
 
-fn escape_command(cmd: &str) -> String {
-    let cmd = cmd.replace("\r", "\\r");
-    let cmd = cmd.replace("\n", "\\n");
-    cmd
+/// The flavour of MI spoken by a backend
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MiDialect {
+    #[default]
+    Gdb,
+    /// lldb-mi: always asynchronous, so `-exec-interrupt` is used to interrupt the target.
+    /// It does not report breakpoints created or deleted by console commands
+    LldbMi,
+}
+
+/// Starts the process the `Debugger` talks MI with, see `Debugger::start_with_backend()`
+pub trait DebuggerBackend: Send {
+    fn spawn(&mut self) -> Result<BackendIo>;
+
+    fn dialect(&self) -> MiDialect {
+        MiDialect::Gdb
+    }
+}
+
+/// The version of MI gdb is started with, `--interpreter=mi2` etc.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MiVersion {
+    /// Plain `mi`, the latest version the gdb binary speaks. It differs between
+    /// distributions shipping different gdbs
+    #[default]
+    Latest,
+    /// Multi-location breakpoints list their locations after the breakpoint
+    Mi2,
+    /// Multi-location breakpoints list their locations in a `locations` field
+    Mi3,
+    /// The `script` field of breakpoints is a list
+    Mi4,
+}
+
+impl MiVersion {
+    /// The `--interpreter` argument
+    pub fn interpreter(self) -> &'static str {
+        match self {
+            MiVersion::Latest => "--interpreter=mi",
+            MiVersion::Mi2 => "--interpreter=mi2",
+            MiVersion::Mi3 => "--interpreter=mi3",
+            MiVersion::Mi4 => "--interpreter=mi4",
+        }
+    }
+
+    /// The first gdb speaking this version, older gdbs exit at startup
+    pub fn required_gdb(self) -> Option<Version> {
+        match self {
+            MiVersion::Latest | MiVersion::Mi2 => None,
+            MiVersion::Mi3 => Some(Version::MI3),
+            MiVersion::Mi4 => Some(Version::MI4),
+        }
+    }
+}
+
+/// A gdb process, started with `--interpreter=mi`
+#[derive(Debug, Clone)]
+pub struct GdbBackend {
+    /// The gdb binary
+    pub program: String,
+    pub args: Vec<String>,
+    /// The MI version to speak, see `mi_version()`
+    pub mi_version: MiVersion,
+    /// Windows: put gdb in a Job Object that is killed when the host process exits, even
+    /// if it crashes. The debuggee is started by gdb so it belongs to the job too.
+    /// Ignored on other platforms
+    pub kill_on_host_exit: bool,
+}
+
+impl GdbBackend {
+    /// Kill gdb and the debuggee when the host process exits, see `kill_on_host_exit`
+    pub fn kill_on_host_exit(mut self, kill: bool) -> Self {
+        self.kill_on_host_exit = kill;
+        self
+    }
+
+    /// Start gdb with a specific MI version instead of the latest one. Breakpoints are
+    /// parsed the same way with every version
+    pub fn mi_version(mut self, version: MiVersion) -> Self {
+        self.mi_version = version;
+        self
+    }
+}
+
+impl Default for GdbBackend {
+    /// The binary named by the `GDB_BINARY` environment variable, or `gdb`
+    fn default() -> Self {
+        GdbBackend {
+            program: ::std::env::var("GDB_BINARY").unwrap_or("gdb".to_string()),
+            args: Vec::new(),
+            mi_version: MiVersion::Latest,
+            kill_on_host_exit: false,
+        }
+    }
+}
+
+impl DebuggerBackend for GdbBackend {
+    fn spawn(&mut self) -> Result<BackendIo> {
+        let mut command = Command::new(&self.program);
+        command.arg(self.mi_version.interpreter()).args(&self.args);
+        spawn_command(command, self.kill_on_host_exit)
+    }
+}
+
+/// Start `command` with its standard streams piped, for backends starting gdb in a
+/// process of their own. See `GdbBackend::kill_on_host_exit`
+pub(crate) fn spawn_command(mut command: Command, kill_on_host_exit: bool) -> Result<BackendIo> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    #[cfg(not(windows))]
+    let _ = kill_on_host_exit;
+    #[cfg(windows)]
+    if kill_on_host_exit {
+        if let Some(handle) = child.raw_handle() {
+            if let Err(e) = crate::job::kill_on_host_exit(handle as _) {
+                let _ = child.start_kill();
+                return Err(e.into());
+            }
+        }
+    }
+    let pid = child.id();
+    let stdout = child
+        .stdout
+        .take()
+        .expect("child did not have a handle to stdout");
+    let stdin = child
+        .stdin
+        .take()
+        .expect("child did not have a handle to stdin");
+    let stderr = child
+        .stderr
+        .take()
+        .expect("child did not have a handle to stderr");
+    Ok(BackendIo {
+        stdout: Box::new(stdout),
+        stdin: Box::new(stdin),
+        stderr: Some(Box::new(stderr)),
+        pid,
+    })
 }
 
 impl Debugger {
@@ -83,149 +569,432 @@ impl Debugger {
     /// * A `Debugger` instsance
     /// * The receiver end of the debugger's output channel
     pub async fn start() -> Result<(Self, Receiver<msg::Record>)> {
+        Self::start_with_backend(GdbBackend::default()).await
+    }
+
+    /// Same as `start()`, talking to the process started by `backend`, e.g. a
+    /// `MockBackend` in tests
+    pub async fn start_with_backend(
+        mut backend: impl DebuggerBackend + 'static,
+    ) -> Result<(Self, Receiver<msg::Record>)> {
         tracing::debug!("launching debugger");
-        let name = ::std::env::var("GDB_BINARY").unwrap_or("gdb".to_string());
-        let mut child = Command::new(name)
-            .args(&["--interpreter=mi"])
-            .stdout(Stdio::piped())
-            .stdin(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-
-        // =======================
-        // Handling stdout / Stdin
-        // =======================
-        let stdout = child
-            .stdout
-            .take()
-            .expect("child did not have a handle to stdout");
+        let dialect = backend.dialect();
+        let io = backend.spawn()?;
+        let pid = io.pid;
 
         // start a tasks here that always listens to gdb, parses the output and put it inside a channel
-        let (stdout_sender, output_channel) = channel::<msg::Record>(100);
+        let (stdout_sender, mut output_channel) = channel::<msg::Record>(100);
+        let (broadcast_sender, _) = broadcast::channel::<msg::Record>(100);
+        let stdout_sender = RecordSender {
+            channel: stdout_sender,
+            broadcast: broadcast_sender,
+            suppress_streams: Arc::new(AtomicUsize::new(0)),
+            recorder: Arc::new(Mutex::new(None)),
+            output_closed: Arc::new(watch::channel(false).0),
+        };
 
-        let stdin = child
-            .stdin
-            .take()
-            .expect("child did not have a handle to stdin");
         let (stdin_sender, mut stdin_receiver) = channel::<String>(100);
 
-        let can_interact = Arc::new(AtomicBool::new(true));
+        let state = Arc::new(watch::Sender::new(DebuggerState::Starting));
         let debugee_pid = Arc::new(AtomicUsize::new(usize::MAX));
 
-        let can_interact_clone = can_interact.clone();
-        let debugee_pid_clone = debugee_pid.clone();
+        let session = Arc::new(Mutex::new(SessionState {
+            dialect,
+            interrupt_strategy: match dialect {
+                MiDialect::Gdb => InterruptStrategy::Debuggee,
+                MiDialect::LldbMi => InterruptStrategy::ExecInterrupt,
+            },
+            ..Default::default()
+        }));
+
+        let readers = ReaderContext {
+            sender: stdout_sender.clone(),
+            state: state.clone(),
+            debugee_pid: debugee_pid.clone(),
+            session: session.clone(),
+            parse_errors: Arc::new(Mutex::new(None)),
+            lenient_parsing: Arc::new(AtomicBool::new(false)),
+            max_nesting_depth: Arc::new(AtomicUsize::new(parser::DEFAULT_MAX_NESTING_DEPTH)),
+            stats: Arc::new(StatsCounters::default()),
+        };
+        let writer: SharedWriter = Arc::new(tokio::sync::Mutex::new(BufWriter::new(io.stdin)));
+        let stderr_task = Self::spawn_readers(io.stdout, io.stderr, &readers);
+        Self::wait_for_startup(&state, stderr_task).await?;
+
+        let task_writer = writer.clone();
+        let write_error = Arc::new(Mutex::new(None));
+        let task_write_error = write_error.clone();
+        tracing::debug!("launching gdb writer task");
+        // start a task that reads lines from the input channel `stdin_receiver` and writes
+        // them to the gdb process
+        tokio::spawn(async move {
+            while let Some(line) = stdin_receiver.recv().await {
+                tracing::debug!("will send command to gdb: {}", escape_command(&line));
+                let buf = line.as_bytes();
+                let mut writer = task_writer.lock().await;
+                let written = match writer.write_all(buf).await {
+                    Ok(()) => writer.flush().await,
+                    Err(e) => Err(e),
+                };
+                match written {
+                    Ok(()) => tracing::debug!("command sent!"),
+                    Err(e) => {
+                        tracing::debug!("failed to write to gdb: {}", e);
+                        *task_write_error.lock().unwrap() = Some(e);
+                    }
+                }
+            }
+        });
+
+        tracing::debug!("gdb is up and running");
+        let debugger = Debugger {
+            stdin: stdin_sender,
+            gdb_pid: Arc::new(AtomicUsize::new(pid.map_or(usize::MAX, |pid| pid as usize))),
+            state,
+            debugee_pid,
+            post_mortem: Arc::new(AtomicBool::new(false)),
+            session,
+            events: stdout_sender,
+            keepalive: Mutex::new(None),
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+            fence: Arc::new(tokio::sync::Mutex::new(())),
+            commands: Arc::new(tokio::sync::Mutex::new(())),
+            parse_errors: readers.parse_errors,
+            tokens: TokenGenerator::new(),
+            lenient_parsing: readers.lenient_parsing,
+            max_nesting_depth: readers.max_nesting_depth,
+            stats: readers.stats,
+            renderers: Default::default(),
+            gdbserver: Mutex::new(None),
+            backend: Mutex::new(Box::new(backend)),
+            writer,
+            auto_restart: AtomicBool::new(false),
+            restarting: AtomicBool::new(false),
+            write_error,
+        };
+        if let Err(e) = debugger.detect_capabilities(&mut output_channel).await {
+            tracing::debug!("failed to detect gdb's capabilities: {}", e);
+        }
+        debugger.detect_gdb_version(&mut output_channel).await;
+        Ok((debugger, output_channel))
+    }
 
-        let mut reader = BufReader::new(stdout).lines();
+    /// Start the tasks reading gdb's stdout and stderr. The stdout reader moves the state
+    /// to `Dead` once gdb closes it. Lines printed on stderr once gdb talks MI are
+    /// passed on as `StreamRecord::Log` records. The stderr task returns the lines printed
+    /// before, for `StartupFailed` errors
+    pub(crate) fn spawn_readers(
+        stdout: Box<dyn AsyncRead + Send + Unpin>,
+        stderr: Option<Box<dyn AsyncRead + Send + Unpin>>,
+        context: &ReaderContext,
+    ) -> Option<JoinHandle<Vec<String>>> {
+        // Wrapper scripts may print banners before gdb starts talking MI. Until the first
+        // MI output shows up, unparsable lines on stdout and stderr are reported as
+        // `DebuggerEvent::StartupNoise`
+        let mi_started = Arc::new(AtomicBool::new(false));
+
+        let stderr_task = stderr.map(|stderr| {
+            let mut stderr_reader = LossyLines::new(stderr);
+            let stderr_sender = context.sender.clone();
+            let stderr_mi_started = mi_started.clone();
+            tracing::debug!("launching gdb stderr reader task");
+            tokio::spawn(async move {
+                let mut startup = Vec::new();
+                while let Ok(Some(line)) = stderr_reader.next_line().await {
+                    tracing::trace!("stderr: {}", escape_command(&line));
+                    if !stderr_mi_started.load(Ordering::Relaxed) {
+                        if startup.len() < MAX_STARTUP_LINES {
+                            startup.push(line.clone());
+                        }
+                        Self::emit_startup_noise(&stderr_sender, OutputSource::Stderr, line)
+                            .await;
+                    } else {
+                        let record = msg::StreamRecord::Log(stderr_text(&line));
+                        let _ = stderr_sender.send(Record::Stream(record)).await;
+                    }
+                }
+                startup
+            })
+        });
+
+        let ReaderContext {
+            sender: stdout_sender,
+            state: state_clone,
+            debugee_pid: debugee_pid_clone,
+            session: session_clone,
+            parse_errors,
+            lenient_parsing,
+            max_nesting_depth,
+            stats: reader_stats,
+        } = context.clone();
+        stdout_sender.output_closed.send_replace(false);
+        let mut capture = ParseErrorCapture::new(parse_errors);
+        let mut reader = LossyLines::new(stdout);
         tracing::debug!("launching gdb reader task");
-        tokio::task::spawn_local(async move {
+        tokio::spawn(async move {
             while let Ok(line) = reader.next_line().await {
                 if let Some(line) = line {
-                    // skip gdb prompt line
                     if line.starts_with("(gdb)") {
+                        mi_started.store(true, Ordering::Relaxed);
+                        // the first prompt means gdb is up
+                        let started = state_clone.send_if_modified(|state| {
+                            let started = *state == DebuggerState::Starting;
+                            if started {
+                                *state = DebuggerState::Idle;
+                            }
+                            started
+                        });
+                        if started {
+                            let mut session = session_clone.lock().unwrap();
+                            if let Some(timeline) = session.timeline.as_mut() {
+                                timeline.on_state(DebuggerState::Idle);
+                            }
+                        }
+                        let _ = stdout_sender.send(Record::Prompt).await;
                         continue;
                     }
                     tracing::trace!("{}", escape_command(&line));
-                    Self::process_line(
-                        line,
+                    reader_stats.lines_read.fetch_add(1, Ordering::Relaxed);
+                    if is_result_line(&line) {
+                        reader_stats.results_received.fetch_add(1, Ordering::Relaxed);
+                    }
+                    let parsed = Self::process_line(
+                        line.clone(),
                         &stdout_sender,
-                        can_interact_clone.clone(),
+                        &state_clone,
                         debugee_pid_clone.clone(),
+                        session_clone.clone(),
+                        lenient_parsing.load(Ordering::Relaxed),
+                        max_nesting_depth.load(Ordering::Relaxed),
                     )
                     .await;
+                    if mi_started.load(Ordering::Relaxed) || parsed {
+                        capture.on_line(&line, parsed);
+                    }
+                    if parsed {
+                        mi_started.store(true, Ordering::Relaxed);
+                        continue;
+                    }
+                    reader_stats.parse_failures.fetch_add(1, Ordering::Relaxed);
+                    if !mi_started.load(Ordering::Relaxed) {
+                        Self::emit_startup_noise(&stdout_sender, OutputSource::Stdout, line)
+                            .await;
+                    } else {
+                        // never lose output, pass it on as is
+                        tracing::debug!("failed to parse: {}", escape_command(&line));
+                        let _ = stdout_sender.send(Record::Raw(line)).await;
+                    }
+                } else {
+                    break;
                 }
             }
+            // end of output: gdb is gone
+            state_clone.send_replace(DebuggerState::Dead);
+            stdout_sender.output_closed.send_replace(true);
         });
+        stderr_task
+    }
 
-        let mut writer = BufWriter::new(stdin);
-        tracing::debug!("launching gdb writer task");
-        // start a task that reads lines from the input channel `stdin_receiver` and writes
-        // them to the gdb process
-        tokio::task::spawn_local(async move {
-            while let Some(line) = stdin_receiver.recv().await {
-                tracing::debug!("will send command to gdb: {}", escape_command(&line));
-                let buf = line.as_bytes();
-                let _ = writer.write(buf).await;
-                let _ = writer.flush().await;
-                tracing::debug!("command sent!");
+    /// Wait until gdb talks MI, up to `STARTUP_TIMEOUT`. If it exits first, fail with
+    /// what it printed on stderr. A gdb slow to start is not an error
+    pub(crate) async fn wait_for_startup(
+        state: &watch::Sender<DebuggerState>,
+        stderr_task: Option<JoinHandle<Vec<String>>>,
+    ) -> Result<()> {
+        let mut states = state.subscribe();
+        let started = states.wait_for(|state| *state != DebuggerState::Starting);
+        match tokio::time::timeout(STARTUP_TIMEOUT, started).await {
+            Ok(Ok(state)) if *state != DebuggerState::Dead => return Ok(()),
+            Err(_) => {
+                tracing::debug!("gdb did not talk MI within {:?}", STARTUP_TIMEOUT);
+                return Ok(());
             }
-        });
+            _ => {}
+        }
+        let stderr = match stderr_task {
+            // stderr ends with gdb, don't wait for processes it left behind
+            Some(task) => tokio::time::timeout(Duration::from_secs(1), task)
+                .await
+                .ok()
+                .and_then(|lines| lines.ok())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+        Err(Error::StartupFailed(stderr.join("\n")))
+    }
 
-        tracing::debug!("gdb is up and running");
-        Ok((
-            Debugger {
-                stdin: stdin_sender,
-                gdb_pid: Arc::new(AtomicUsize::new(usize::MAX)),
-                can_interact,
-                debugee_pid,
-            },
-            output_channel,
-        ))
+    /// Report a line printed before gdb started talking MI
+    async fn emit_startup_noise(sender: &RecordSender, source: OutputSource, line: String) {
+        tracing::debug!("startup noise on {:?}: {}", source, escape_command(&line));
+        let event = DebuggerEvent::StartupNoise { source, line };
+        let _ = sender.send(Record::Event(event)).await;
     }
 
-    /// Process gdb output line
+    /// Process gdb output line. Return `false` if the line could not be parsed
     async fn process_line(
         mut line: String,
-        sender: &Sender<msg::Record>,
-        can_interact: Arc<AtomicBool>,
+        sender: &RecordSender,
+        state: &watch::Sender<DebuggerState>,
         debugee_pid: Arc<AtomicUsize>,
-    ) {
+        session: Arc<Mutex<SessionState>>,
+        lenient: bool,
+        max_depth: usize,
+    ) -> bool {
         if !line.ends_with("\n") {
             line.push('\n');
         }
-        match parser::parse_line(line.as_str()) {
+        let parsed = match lenient {
+            true => parser::salvage_line(&line, max_depth).map(|(record, error)| {
+                if let Some(e) = error {
+                    tracing::debug!("salvaged part of a record: {}", e);
+                }
+                record
+            }),
+            false => parser::parse_record(&line, max_depth),
+        };
+        match parsed {
             Ok(resp) => {
+                // crate event that follows the record
+                let mut follow_up = None;
+                let changed = state.send_if_modified(|state| {
+                    let next = state.next(&resp);
+                    if next != *state {
+                        tracing::trace!("debugger state: {:?} -> {:?}", state, next);
+                    }
+                    std::mem::replace(state, next) != next
+                });
+                if changed {
+                    let next = *state.borrow();
+                    if let Ok(mut session) = session.lock() {
+                        if let Some(timeline) = session.timeline.as_mut() {
+                            timeline.on_state(next);
+                        }
+                    }
+                }
                 match &resp {
                     Record::Async(async_record) => {
+                        if let Ok(mut session) = session.lock() {
+                            let (AsyncRecord::Exec(s)
+                            | AsyncRecord::Status(s)
+                            | AsyncRecord::Notify(s)) = async_record;
+                            let bound = bound_breakpoint(&session.breakpoints, s).map(Box::new);
+                            follow_up = bound.map(DebuggerEvent::BreakpointBound);
+                            session.on_async(async_record);
+                        }
                         match async_record {
-                            // keep track of "*stopped" messages and mark the debugger as
-                            // "can_interact"
                             AsyncRecord::Exec(s) | AsyncRecord::Status(s) => {
                                 tracing::trace!("pushing response (AsyncRecord::Exec) to queue");
+                                let live =
+                                    session.lock().is_ok_and(|s| !s.inferior_pids.is_empty());
+                                if ExitStatus::from_record(s).is_some() && !live {
+                                    // a new run reports a new pid
+                                    debugee_pid.store(usize::MAX, Ordering::Relaxed);
+                                }
                                 if s.class == AsyncClass::Stopped {
-                                    tracing::trace!(
-                                        "debugger is stopped -> can_interact is set to TRUE"
-                                    );
-                                    can_interact.store(true, Ordering::Relaxed);
+                                    follow_up = out_of_scope_watchpoint(s)
+                                        .map(|number| DebuggerEvent::WatchpointOutOfScope { number });
+                                } else if s.class == AsyncClass::Download {
+                                    follow_up =
+                                        DownloadProgress::from_record(s).map(DebuggerEvent::Download);
                                 }
                             }
-                            AsyncRecord::Notify(s) => {
-                                // Looking for the process id
-                                if s.class == AsyncClass::Other
-                                    && debugee_pid.load(Ordering::Relaxed) == usize::MAX
-                                {
-                                    for var in &s.content {
-                                        if var.name.eq("pid") {
-                                            if let Value::String(str_value) = &var.value {
-                                                // found the pid
-                                                let stripped_value = str_value.replace("\"", "");
-                                                if let Ok(pid) = stripped_value.parse() {
-                                                    debugee_pid.store(pid, Ordering::Relaxed);
-                                                    tracing::debug!("debuggee PID is {}", pid);
-                                                    break;
-                                                }
-                                            }
+                            AsyncRecord::Notify(s) => match s.class {
+                                AsyncClass::ThreadGroupStarted => {
+                                    let pid = s.get("pid").and_then(Value::unquoted);
+                                    if let Some(pid) = pid.and_then(|pid| pid.parse().ok()) {
+                                        // the first process is the debuggee
+                                        if debugee_pid.load(Ordering::Relaxed) == usize::MAX {
+                                            debugee_pid.store(pid, Ordering::Relaxed);
+                                            tracing::debug!("debuggee PID is {}", pid);
                                         }
                                     }
                                 }
-                            }
+                                AsyncClass::ThreadGroupExited => {
+                                    // fall back to another live inferior, if any
+                                    let current = debugee_pid.load(Ordering::Relaxed);
+                                    if let Ok(session) = session.lock() {
+                                        let pids = &session.inferior_pids;
+                                        if !pids.values().any(|&pid| pid as usize == current) {
+                                            let next = pids.values().next().copied();
+                                            let next = next.map_or(usize::MAX, |pid| pid as usize);
+                                            debugee_pid.store(next, Ordering::Relaxed);
+                                        }
+                                    }
+                                }
+                                AsyncClass::LibraryLoaded => {
+                                    let module = Module::from_record(s);
+                                    follow_up = module.map(DebuggerEvent::LibraryLoaded);
+                                }
+                                AsyncClass::LibraryUnloaded => {
+                                    let module = Module::from_record(s);
+                                    follow_up = module.map(DebuggerEvent::LibraryUnloaded);
+                                }
+                                _ => {}
+                            },
+                        }
+                    }
+                    Record::Result(res) if res.token == Some(KEEPALIVE_TOKEN) => {
+                        // keepalive results are for internal use only
+                        let disconnected = session
+                            .lock()
+                            .map(|mut s| s.on_keepalive_result(res))
+                            .unwrap_or(false);
+                        if disconnected {
+                            Self::emit_disconnected(sender, "remote target does not respond").await;
                         }
+                        return true;
                     }
                     Record::Result(res) => {
-                        // keep track of records of type "*running"
-                        if res.class == ResultClass::Running {
-                            tracing::trace!("debugger is running -> can_interact is set to FALSE");
-                            can_interact.store(false, Ordering::Relaxed);
+                        if let Ok(mut session) = session.lock() {
+                            session.on_result(res);
+                        }
+                        if res.class == ResultClass::Error {
+                            if let Some(msg) = res.get("msg").and_then(Value::unquoted) {
+                                if is_disconnect_message(&msg) {
+                                    Self::emit_disconnected(sender, &msg).await;
+                                }
+                            }
+                        }
+                    }
+                    Record::Stream(msg::StreamRecord::Log(text)) if is_disconnect_message(text) => {
+                        Self::emit_disconnected(sender, &msg::unquote(text)).await;
+                    }
+                    Record::Stream(msg::StreamRecord::Console(text)) => {
+                        let text = msg::unquote(text);
+                        if let Some(version) = Version::from_banner(&text) {
+                            if let Ok(mut session) = session.lock() {
+                                session.gdb_version.get_or_insert(version);
+                            }
+                        }
+                        if let Some(event) = parse_debuginfod_line(&text) {
+                            if let Ok(mut session) = session.lock() {
+                                session.on_debuginfod(&event);
+                            }
+                            follow_up = Some(DebuggerEvent::Debuginfod(event));
                         }
                     }
                     _ => {}
                 }
                 let _ = sender.send(resp).await;
+                if let Some(event) = follow_up {
+                    let _ = sender.send(Record::Event(event)).await;
+                }
+                true
             }
-            Err(_) => {
-                //                tracing::trace!("error parsing line: `{}`", line.as_str());
-                //                return;
+            Err(e) => {
+                tracing::trace!("{}", e);
+                false
             }
+        }
+    }
+
+    /// Emit `DebuggerEvent::TargetDisconnected`
+    async fn emit_disconnected(sender: &RecordSender, reason: &str) {
+        tracing::debug!("remote target disconnected: {}", reason);
+        let event = DebuggerEvent::TargetDisconnected {
+            reason: reason.trim().to_string(),
         };
+        let _ = sender.send(Record::Event(event)).await;
     }
 
     /// Read the first `msg::ResultClass` from gdb output channel.
@@ -234,11 +1003,11 @@ impl Debugger {
     pub async fn read_result_record(
         &self,
         output_channel: &mut Receiver<msg::Record>,
-    ) -> msg::MessageRecord<msg::ResultClass> {
+    ) -> Result<msg::MessageRecord<msg::ResultClass>> {
         loop {
-            let record = self.read_message_record(output_channel).await;
+            let record = self.read_message_record(output_channel).await?;
             match record {
-                msg::Record::Result(msg) => return msg,
+                msg::Record::Result(msg) => return Ok(msg),
                 msg::Record::Stream(rec) => match rec {
                     msg::StreamRecord::Console(_msg)
                     | msg::StreamRecord::Target(_msg)
@@ -251,53 +1020,289 @@ impl Debugger {
         }
     }
 
-    /// Read the first `msg::ResultClass` from gdb output queue
-    pub async fn read_message_record(
+    /// Read the result record of the command sent with `token`. Results of other commands
+    /// (e.g. sent with `send_cmd_raw()` and never read) are discarded
+    pub(crate) async fn read_result_for(
         &self,
         output_channel: &mut Receiver<msg::Record>,
-    ) -> msg::Record {
+        token: u64,
+    ) -> Result<msg::MessageRecord<msg::ResultClass>> {
         loop {
-            if let Some(record) = &output_channel.recv().await {
-                match record {
-                    msg::Record::Result(message) => {
-                        tracing::trace!("< {:?}", message);
-                        return record.clone();
-                    }
-                    msg::Record::Stream(rec) => match rec {
-                        msg::StreamRecord::Console(message)
-                        | msg::StreamRecord::Target(message)
-                        | msg::StreamRecord::Log(message) => {
-                            tracing::trace!("< {}", escape_command(&message));
-                            return record.clone();
-                        }
-                    },
-                    msg::Record::Async(async_record) => {
-                        tracing::trace!("< {:?}", async_record);
-                        return record.clone();
+            let record = self.read_result_record(output_channel).await?;
+            if is_reply(&record, token) {
+                return Ok(record);
+            }
+            tracing::debug!("discarding the result of another command: {:?}", record.token);
+        }
+    }
+
+    /// Read the next record from gdb output queue. Fails with `Error::ChannelClosed` once
+    /// gdb exited and all it printed was read
+    pub async fn read_message_record(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<msg::Record> {
+        if let Some(record) = self.pending.lock().unwrap().pop_front() {
+            return Ok(record);
+        }
+        let mut closed = self.events.output_closed.subscribe();
+        let record = tokio::select! {
+            // what gdb printed before exiting is read first
+            biased;
+            record = output_channel.recv() => record,
+            _ = closed.wait_for(|closed| *closed) => None,
+        };
+        let Some(record) = record else {
+            return Err(Error::ChannelClosed);
+        };
+        match &record {
+            msg::Record::Result(message) => tracing::trace!("< {:?}", message),
+            msg::Record::Stream(rec) => match rec {
+                msg::StreamRecord::Console(message)
+                | msg::StreamRecord::Target(message)
+                | msg::StreamRecord::Log(message) => {
+                    tracing::trace!("< {}", escape_command(message))
+                }
+            },
+            msg::Record::Async(async_record) => tracing::trace!("< {:?}", async_record),
+            msg::Record::Event(event) => tracing::trace!("< {:?}", event),
+            msg::Record::Raw(line) => tracing::trace!("< (raw) {}", escape_command(line)),
+            msg::Record::Prompt => tracing::trace!("< (gdb)"),
+        }
+        // the record is moved out of the channel, not copied
+        Ok(record)
+    }
+
+    /// Counters about the gdb output read so far
+    pub fn stats(&self) -> DebuggerStats {
+        DebuggerStats {
+            lines_read: self.stats.lines_read.load(Ordering::Relaxed),
+            parse_failures: self.stats.parse_failures.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Subscribe to gdb output. Every subscriber gets a copy of each record sent to the
+    /// output channel, so several consumers (e.g. a UI and a logger) can observe the session
+    /// without stealing records from each other. A subscriber that falls behind by more
+    /// than 100 records gets `RecvError::Lagged`
+    pub fn subscribe(&self) -> broadcast::Receiver<msg::Record> {
+        self.events.broadcast.subscribe()
+    }
+
+    /// Collect up to `max` records from gdb output channel. Records already queued are
+    /// returned right away, then we keep waiting for more until `timeout` elapses
+    pub async fn drain_events(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        max: usize,
+        timeout: Duration,
+    ) -> Vec<msg::Record> {
+        let deadline = Instant::now() + timeout;
+        let mut records = Vec::new();
+        {
+            let mut pending = self.pending.lock().unwrap();
+            while records.len() < max {
+                match pending.pop_front() {
+                    Some(record) => records.push(record),
+                    None => break,
+                }
+            }
+        }
+        while records.len() < max {
+            match output_channel.try_recv() {
+                Ok(record) => records.push(record),
+                Err(TryRecvError::Disconnected) => break,
+                Err(TryRecvError::Empty) => {
+                    match tokio::time::timeout_at(deadline, output_channel.recv()).await {
+                        Ok(Some(record)) => records.push(record),
+                        Ok(None) | Err(_) => break,
                     }
                 }
             }
         }
+        records
     }
 
-    /// Send command to gdb
-    pub async fn send_cmd_raw(&mut self, cmd: &str) {
-        tracing::debug!("sending command: {} to gdb", escape_command(&cmd));
-        if cmd.ends_with("\n") {
-            let _ = self.stdin.send(cmd.to_string()).await;
-        } else {
-            let _ = self.stdin.send(cmd.to_string() + "\n").await;
+    /// Send command to gdb. Commands are written whole and in the order they are sent, so
+    /// this can be called from several tasks sharing the `Debugger` (e.g. behind an `Arc`).
+    /// Commands are written by a task: a failed write is returned by the next call, as an
+    /// `Error::IOError`. `Error::ChannelClosed` is returned if that task is gone
+    pub async fn send_cmd_raw(&self, cmd: &str) -> Result<()> {
+        if let Some(e) = self.write_error.lock().unwrap().take() {
+            return Err(Error::IOError(e));
+        }
+        tracing::debug!("sending command: {} to gdb", escape_command(cmd));
+        if let Some(timeline) = self.session.lock().unwrap().timeline.as_mut() {
+            timeline.on_command(cmd);
         }
+        if let Some(recorder) = self.events.recorder.lock().unwrap().as_mut() {
+            recorder.on_command(cmd);
+        }
+        if loads_program(cmd) {
+            self.session.lock().unwrap().program_loaded = true;
+        }
+        let line = match cmd.ends_with("\n") {
+            true => cmd.to_string(),
+            false => cmd.to_string() + "\n",
+        };
+        self.stdin
+            .send(line)
+            .await
+            .map_err(|_| Error::ChannelClosed)?;
+        self.stats.commands_sent.fetch_add(1, Ordering::Relaxed);
         tracing::debug!("done");
+        Ok(())
+    }
+
+    /// Send command to gdb and wait for its result record.
+    /// An `^error` result is converted into `Error::GdbError`.
+    /// Concurrent calls are serialized: each waits for the previous command's result
+    /// before sending its own
+    pub async fn send_cmd(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        cmd: &str,
+    ) -> Result<msg::MessageRecord<msg::ResultClass>> {
+        if self.is_post_mortem() && cmd.trim_start().starts_with("-exec-") {
+            return Err(Error::PostMortem);
+        }
+        if self.auto_restart() && !self.is_alive() {
+            self.supervise_restart(output_channel).await?;
+        }
+        let (token, cmd) = self.tokens.tag(cmd);
+        let trace = CommandTrace::new(&cmd);
+        let record = async {
+            let _guard = self.commands.lock().await;
+            self.send_cmd_raw(&cmd).await?;
+            self.read_result_for(output_channel, token).await
+        }
+        .instrument(trace.span.clone())
+        .await;
+        let record = match record {
+            // gdb died running the command: restart it, but don't send the command again
+            Err(Error::ChannelClosed) if self.auto_restart() => {
+                self.supervise_restart(output_channel).await?;
+                return Err(Error::ChannelClosed);
+            }
+            record => record?,
+        };
+        self.end_command(trace, &record);
+        if record.class == ResultClass::Error {
+            let msg = record
+                .get("msg")
+                .and_then(Value::unquoted)
+                .unwrap_or_default();
+            return Err(Error::GdbError(msg));
+        }
+        Ok(record)
     }
 
-    /// can we send commands to the debugger now?
+    /// Run the CLI command `cmd` (`-interpreter-exec console`) and return what it printed
+    /// on the console, unescaped. Console output is not captured while streams are
+    /// suppressed with `suppress_streams()`
+    pub async fn console(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        cmd: &str,
+    ) -> Result<String> {
+        let _guard = self.commands.lock().await;
+        let (token, cmd) = self
+            .tokens
+            .tag(&format!("-interpreter-exec console {}", mi_quote(cmd)));
+        let trace = CommandTrace::new(&cmd);
+        let (output, record) = async {
+            self.send_cmd_raw(&cmd).await?;
+            let mut output = String::new();
+            loop {
+                match self.read_message_record(output_channel).await? {
+                    Record::Stream(msg::StreamRecord::Console(text)) => {
+                        output.push_str(&msg::unquote(&text))
+                    }
+                    Record::Result(record) if is_reply(&record, token) => {
+                        return Ok::<_, Error>((output, record))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        .instrument(trace.span.clone())
+        .await?;
+        self.end_command(trace, &record);
+        if record.class == ResultClass::Error {
+            let text = record.get("msg").and_then(Value::unquoted);
+            return Err(Error::GdbError(text.unwrap_or_default()));
+        }
+        Ok(output)
+    }
+
+    /// Load `executable` and the core file `core_path` for post-mortem debugging.
+    /// The session is marked as post-mortem: execution commands (`-exec-*`) are refused,
+    /// while stack, variable and memory inspection keep working
+    pub async fn open_core(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        executable: &str,
+        core_path: &str,
+    ) -> Result<()> {
+        self.send_cmd(
+            output_channel,
+            &format!("-file-exec-and-symbols {}", mi_quote(executable)),
+        )
+        .await?;
+        self.send_cmd(
+            output_channel,
+            &format!(
+                "-interpreter-exec console {}",
+                mi_quote(&format!("core-file {}", cli_quote(core_path)))
+            ),
+        )
+        .await?;
+        self.post_mortem.store(true, Ordering::Relaxed);
+        self.set_state(DebuggerState::Stopped);
+        Ok(())
+    }
+
+    /// How the debuggee exited, `None` while it runs or before it ran
+    pub fn debuggee_exit_status(&self) -> Option<ExitStatus> {
+        self.session.lock().unwrap().exit_status.clone()
+    }
+
+    /// are we debugging a core file?
+    pub fn is_post_mortem(&self) -> bool {
+        self.post_mortem.load(Ordering::Relaxed)
+    }
+
+    /// can we send commands to the debugger now? While the target runs, only if some
+    /// threads were resumed individually (non-stop mode)
     pub fn can_send_commands(&self) -> bool {
-        self.can_interact.load(Ordering::Relaxed)
+        match self.state() {
+            DebuggerState::Dead => false,
+            DebuggerState::Running => self.session.lock().unwrap().running.is_partial(),
+            _ => true,
+        }
+    }
+
+    /// Was thread `thread_id` reported running by a `*running` record?
+    pub fn is_thread_running(&self, thread_id: &str) -> bool {
+        self.session.lock().unwrap().running.contains(thread_id)
+    }
+
+    /// The MI dialect of the backend the debugger was started with
+    pub fn dialect(&self) -> MiDialect {
+        self.session.lock().unwrap().dialect
+    }
+
+    /// Choose how `interrupt()` stops the target, see `InterruptStrategy`
+    pub fn set_interrupt_strategy(&self, strategy: InterruptStrategy) {
+        self.session.lock().unwrap().interrupt_strategy = strategy;
     }
 
-    /// interrupt the running process
-    /// If success, we should `can_send_commands()` returns `true`
+    pub fn interrupt_strategy(&self) -> InterruptStrategy {
+        self.session.lock().unwrap().interrupt_strategy
+    }
+
+    /// interrupt the running process, using the strategy set with `set_interrupt_strategy()`.
+    /// If success, we should `can_send_commands()` returns `true` once gdb reports the stop
     pub fn interrupt(&self) -> bool {
         if self.can_send_commands() {
             // nothing to be done more
@@ -307,14 +1312,80 @@ impl Debugger {
             return true;
         }
 
-        if self.debugee_pid.load(Ordering::Relaxed) == usize::MAX {
-            tracing::debug!("can not interrupt debugee process. I don't know its process id yet");
-            return false;
+        match self.interrupt_strategy() {
+            InterruptStrategy::Debuggee => {
+                if self.debugee_pid.load(Ordering::Relaxed) == usize::MAX {
+                    tracing::debug!(
+                        "can not interrupt debugee process. I don't know its process id yet"
+                    );
+                    return false;
+                }
+                signal(self.debugee_pid.load(Ordering::Relaxed), Signal::Interrupt).is_ok()
+            }
+            InterruptStrategy::ConsoleCtrlEvent => {
+                let pid = self.debugee_pid.load(Ordering::Relaxed);
+                if pid == usize::MAX {
+                    return false;
+                }
+                #[cfg(windows)]
+                let result = crate::console_ctrl_break(pid);
+                #[cfg(not(windows))]
+                let result = signal(pid, Signal::Interrupt);
+                result.is_ok()
+            }
+            InterruptStrategy::Gdb => {
+                if self.gdb_pid.load(Ordering::Relaxed) == usize::MAX {
+                    return false;
+                }
+                signal(self.gdb_pid.load(Ordering::Relaxed), Signal::Interrupt).is_ok()
+            }
+            InterruptStrategy::ExecInterrupt => {
+                // interrupt() is not async, the writer task picks the command up. The
+                // token keeps its result from passing for the reply of the next command
+                let cmd = format!("{}-exec-interrupt\n", self.tokens.next_token());
+                self.stdin.try_send(cmd).is_ok()
+            }
+        }
+    }
+
+    /// Return a copy of the current session state. This never waits on gdb, so it
+    /// is safe to call from render loops
+    pub fn snapshot(&self) -> SessionSnapshotView {
+        let session = self.session.lock().unwrap();
+        SessionSnapshotView {
+            state: self.state(),
+            post_mortem: self.is_post_mortem(),
+            selected_thread: session.selected_thread.clone(),
+            selected_frame: session.selected_frame,
+            debuggee_pid: self.get_debuggee_pid(None),
+            breakpoints: session.breakpoints.iter().map(|b| b.summary()).collect(),
+            breakpoint_groups: session
+                .breakpoints
+                .group_names()
+                .into_iter()
+                .map(|name| {
+                    let members = session.breakpoints.group(&name);
+                    (name, members)
+                })
+                .collect(),
+            last_stop: session.last_stop.clone(),
         }
-        signal(self.debugee_pid.load(Ordering::Relaxed), Signal::Interrupt)
     }
 
-    pub fn get_debuggee_pid(&self) -> Option<usize> {
+    /// Return the current breakpoints. The table is kept in sync from gdb's
+    /// breakpoint notifications and `-break-insert` results, no need to query `-break-list`
+    pub fn breakpoints(&self) -> BreakpointTable {
+        self.session.lock().unwrap().breakpoints.clone()
+    }
+
+    /// The pid of inferior `inferior` (e.g. `i2`, see `inferiors()`), as reported by
+    /// `=thread-group-started`. With `None`, the pid of the debuggee: the first process
+    /// started, or the one attached to
+    pub fn get_debuggee_pid(&self, inferior: Option<&str>) -> Option<usize> {
+        if let Some(inferior) = inferior {
+            let session = self.session.lock().unwrap();
+            return session.inferior_pids.get(inferior).map(|&pid| pid as usize);
+        }
         if self.debugee_pid.load(Ordering::Relaxed) != usize::MAX {
             Some(self.debugee_pid.load(Ordering::Relaxed))
         } else {
@@ -322,15 +1393,25 @@ impl Debugger {
         }
     }
 
+    /// Kill gdb and the debuggee right away. Exit hooks are not run: `terminate()` does
+    /// not wait for gdb, it also runs when the `Debugger` is dropped. Only
+    /// `stop_debugging()` and `close()` run them, use those for an orderly shutdown
     pub fn terminate(&self) {
         tracing::debug!("terminating gdb...");
+        // gdb is killed on purpose, don't bring it back
+        self.set_auto_restart(false);
         // terminate gdb + debugee
         if self.debugee_pid.load(Ordering::Relaxed) != usize::MAX {
-            signal(self.debugee_pid.load(Ordering::Relaxed), Signal::Kill);
+            if let Err(e) = signal(self.debugee_pid.load(Ordering::Relaxed), Signal::Kill) {
+                tracing::debug!("failed to kill the debuggee: {}", e);
+            }
         }
         if self.gdb_pid.load(Ordering::Relaxed) != usize::MAX {
-            signal(self.gdb_pid.load(Ordering::Relaxed), Signal::Kill);
+            if let Err(e) = signal(self.gdb_pid.load(Ordering::Relaxed), Signal::Kill) {
+                tracing::debug!("failed to kill gdb: {}", e);
+            }
         }
+        self.stop_gdbserver();
     }
 }
 
@@ -339,6 +1420,7 @@ use sysinfo::Signal;
 
 impl Drop for Debugger {
     fn drop(&mut self) {
+        self.disable_keepalive();
         self.terminate();
     }
 }
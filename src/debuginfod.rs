@@ -0,0 +1,193 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Result};
+use crate::msg;
+use crate::session::SessionState;
+use crate::signal;
+use std::sync::atomic::Ordering;
+use sysinfo::Signal;
+use tokio::sync::mpsc::Receiver;
+
+/// A file gdb fetches from a debuginfod server
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DebuginfodDownload {
+    /// What is downloaded, e.g. `separate debug info`, `executable` or `source file`
+    pub kind: String,
+    /// The objfile (or source file) it is downloaded for
+    pub target: String,
+}
+
+/// A debuginfod download report, parsed from gdb's console output
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebuginfodEvent {
+    Started(DebuginfodDownload),
+    /// gdb continues without the file
+    Failed {
+        download: DebuginfodDownload,
+        reason: String,
+    },
+    /// Cancelled with `Debugger::cancel_debuginfod_download()` (or Ctrl-C)
+    Cancelled(DebuginfodDownload),
+}
+
+/// Downloads tracked from the console output
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DebuginfodState {
+    /// The download in progress. gdb does not report completions, this is cleared by
+    /// the next report
+    pub active: Option<DebuginfodDownload>,
+    /// Failed and cancelled downloads, candidates for a retry
+    pub failed: Vec<DebuginfodDownload>,
+}
+
+const KINDS: [&str; 4] = [
+    "separate debug info",
+    "executable",
+    "source file",
+    "separate index",
+];
+
+/// Split `what for target` or `what target` (source files)
+fn parse_download(text: &str) -> Option<DebuginfodDownload> {
+    let text = text.trim_end().trim_end_matches('.');
+    let kind = KINDS.iter().find(|kind| text.contains(*kind))?;
+    let rest = &text[text.find(kind)? + kind.len()..];
+    let target = rest.strip_prefix(" for ").unwrap_or(rest).trim();
+    Some(DebuginfodDownload {
+        kind: kind.to_string(),
+        target: target.to_string(),
+    })
+}
+
+/// Parse an (unquoted) console line:
+///
+/// * `Downloading 1.20 MB separate debug info for /lib/libc.so.6...`
+/// * `Download failed: Connection timed out.  Continuing without separate debug info for X.`
+/// * `Cancelling download of separate debug info for /lib/libc.so.6...`
+pub(crate) fn parse_debuginfod_line(line: &str) -> Option<DebuginfodEvent> {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("Download failed: ") {
+        let (reason, rest) = rest.split_once(".  Continuing without ")?;
+        return Some(DebuginfodEvent::Failed {
+            download: parse_download(rest)?,
+            reason: reason.to_string(),
+        });
+    }
+    if let Some(rest) = line.strip_prefix("Cancelling download of ") {
+        return parse_download(rest).map(DebuginfodEvent::Cancelled);
+    }
+    // the line is reprinted with a progress bar on terminals, only the first one counts
+    let rest = line.strip_prefix("Downloading ")?;
+    parse_download(rest).map(DebuginfodEvent::Started)
+}
+
+impl SessionState {
+    pub(crate) fn on_debuginfod(&mut self, event: &DebuginfodEvent) {
+        let state = &mut self.debuginfod;
+        match event {
+            DebuginfodEvent::Started(download) => {
+                state.failed.retain(|failed| failed != download);
+                state.active = Some(download.clone());
+            }
+            DebuginfodEvent::Failed { download, .. } | DebuginfodEvent::Cancelled(download) => {
+                state.active = None;
+                if !state.failed.contains(download) {
+                    state.failed.push(download.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Debugger {
+    /// Enable or disable debuginfod downloads (`set debuginfod enabled`), e.g. after a
+    /// symbol server turned out to be unreachable
+    pub async fn set_debuginfod_enabled(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        enabled: bool,
+    ) -> Result<()> {
+        let cmd = format!(
+            "-gdb-set debuginfod enabled {}",
+            if enabled { "on" } else { "off" }
+        );
+        self.send_cmd(output_channel, &cmd).await?;
+        Ok(())
+    }
+
+    /// The download gdb reported last, if it is still in progress as far as we know
+    pub fn active_debuginfod_download(&self) -> Option<DebuginfodDownload> {
+        self.session.lock().unwrap().debuginfod.active.clone()
+    }
+
+    /// The failed and cancelled downloads that were not retried successfully
+    pub fn failed_debuginfod_downloads(&self) -> Vec<DebuginfodDownload> {
+        self.session.lock().unwrap().debuginfod.failed.clone()
+    }
+
+    /// Cancel the download in progress. gdb cancels downloads on `SIGINT`, like Ctrl-C
+    /// in a terminal, and carries on without the file. Return `false` if no download is
+    /// in progress or gdb could not be signalled
+    pub fn cancel_debuginfod_download(&self) -> bool {
+        let gdb_pid = self.gdb_pid.load(Ordering::Relaxed);
+        if self.active_debuginfod_download().is_none() || gdb_pid == usize::MAX {
+            return false;
+        }
+        signal(gdb_pid, Signal::Interrupt).is_ok()
+    }
+
+    /// Download the failed and cancelled files again, up to `max_attempts` times: the
+    /// executable's symbols are re-read (`-file-symbol-file`) and the shared libraries
+    /// reloaded. Source files are fetched again when they are next listed, they are not
+    /// retried here. Return the downloads that still failed
+    pub async fn retry_debuginfod_downloads(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        max_attempts: u32,
+    ) -> Result<Vec<DebuginfodDownload>> {
+        for attempt in 0..max_attempts {
+            let (failed, executable) = {
+                let session = self.session.lock().unwrap();
+                let executable = session.executable.as_ref().map(|e| e.path.clone());
+                (session.debuginfod.failed.clone(), executable)
+            };
+            let failed: Vec<_> = failed.iter().filter(|d| d.kind != "source file").collect();
+            if failed.is_empty() {
+                break;
+            }
+            tracing::debug!("debuginfod retry {}: {:?}", attempt + 1, failed);
+            let executable = executable.map(|path| path.to_string_lossy().to_string());
+            let mut libraries = false;
+            for download in failed {
+                if Some(&download.target) == executable.as_ref() {
+                    let cmd = format!("-file-symbol-file {}", mi_quote(&download.target));
+                    self.send_cmd(output_channel, &cmd).await?;
+                } else {
+                    libraries = true;
+                }
+            }
+            if libraries {
+                self.console(output_channel, "nosharedlibrary").await?;
+                self.console(output_channel, "sharedlibrary").await?;
+            }
+        }
+        Ok(self.failed_debuginfod_downloads())
+    }
+}
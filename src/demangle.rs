@@ -0,0 +1,79 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Result};
+use crate::events::Frame;
+use crate::msg;
+use tokio::sync::mpsc::Receiver;
+
+/// Does `name` look like a mangled Rust (legacy or v0) or Itanium C++ symbol?
+pub fn is_mangled(name: &str) -> bool {
+    name.starts_with("_Z") || name.starts_with("__Z") || name.starts_with("_R")
+}
+
+/// Demangle a Rust or C++ symbol on the client side.
+/// Without the `demangle` feature, or if `name` is not mangled, it is returned unchanged
+pub fn demangle(name: &str) -> String {
+    #[cfg(feature = "demangle")]
+    {
+        if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+            return format!("{:#}", demangled);
+        }
+        if let Ok(symbol) = cpp_demangle::Symbol::new(name) {
+            return symbol.to_string();
+        }
+    }
+    name.to_string()
+}
+
+impl Frame {
+    /// The function name as a mangled symbol. gdb only reports mangled names
+    /// when `print demangle` is off, see `Debugger::set_print_demangle()`
+    pub fn mangled_func(&self) -> Option<&str> {
+        self.func.as_deref().filter(|func| is_mangled(func))
+    }
+
+    /// The function name in human readable form
+    pub fn demangled_func(&self) -> Option<String> {
+        self.func.as_deref().map(demangle)
+    }
+}
+
+impl Debugger {
+    /// Control whether gdb demangles symbol names in its output (`set print demangle`).
+    /// Turn it off to get mangled names in frames and symbol queries
+    pub async fn set_print_demangle(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        on: bool,
+    ) -> Result<()> {
+        let cmd = format!("-gdb-set print demangle {}", if on { "on" } else { "off" });
+        self.send_cmd(output_channel, &cmd).await?;
+        Ok(())
+    }
+
+    /// Control whether gdb demangles symbol names in disassembly (`set print asm-demangle`)
+    pub async fn set_asm_demangle(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        on: bool,
+    ) -> Result<()> {
+        let cmd = format!("-gdb-set print asm-demangle {}", if on { "on" } else { "off" });
+        self.send_cmd(output_channel, &cmd).await?;
+        Ok(())
+    }
+}
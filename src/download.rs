@@ -0,0 +1,69 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::msg::{AsyncClass, MessageRecord, Value};
+
+/// Progress of `-target-download`, from a `+download` record. gdb first reports each
+/// section with its size only, then the bytes sent so far
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DownloadProgress {
+    pub section: String,
+    pub section_sent: u64,
+    pub section_size: u64,
+    pub total_sent: u64,
+    pub total_size: u64,
+}
+
+impl DownloadProgress {
+    pub fn from_record(record: &MessageRecord<AsyncClass>) -> Option<DownloadProgress> {
+        if record.class != AsyncClass::Download {
+            return None;
+        }
+        // the fields are in an anonymous tuple: `+download,{section=".text",...}`
+        let tuple = record
+            .content
+            .iter()
+            .find(|var| var.name.is_empty())
+            .map(|var| &var.value)?;
+        let number = |name| tuple.get(name).and_then(Value::as_u64).unwrap_or(0);
+        Some(DownloadProgress {
+            section: tuple.get("section").and_then(Value::unquoted)?,
+            section_sent: number("section-sent"),
+            section_size: number("section-size"),
+            total_sent: number("total-sent"),
+            total_size: number("total-size"),
+        })
+    }
+
+    /// Overall progress, from 0 to 100
+    pub fn percent(&self) -> f64 {
+        percent(self.total_sent, self.total_size)
+    }
+
+    /// Progress of the current section, from 0 to 100
+    pub fn section_percent(&self) -> f64 {
+        percent(self.section_sent, self.section_size)
+    }
+}
+
+fn percent(sent: u64, size: u64) -> f64 {
+    if size == 0 {
+        return 0.0;
+    }
+    (sent.min(size) as f64 * 100.0) / size as f64
+}
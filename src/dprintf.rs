@@ -0,0 +1,152 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::{Breakpoint, BreakpointOptions};
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg;
+use crate::version::Version;
+use tokio::sync::mpsc::Receiver;
+
+/// How dprintf breakpoints print, see `Debugger::set_dprintf_style()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DprintfStyle {
+    /// gdb prints on its console, reported as console stream records
+    #[default]
+    Gdb,
+    /// Call `function` (e.g. `printf` or `fprintf`) in the program. With a `channel`,
+    /// e.g. `stderr`, it is passed as the first argument
+    Call {
+        function: String,
+        channel: Option<String>,
+    },
+    /// The remote agent prints without stopping the program, for targets supporting it
+    Agent,
+}
+
+impl Breakpoint {
+    /// The format and arguments of a dprintf breakpoint, from its command:
+    /// `printf "x is %d\n",x`
+    pub fn dprintf_format(&self) -> Option<(String, Vec<String>)> {
+        if self.kind.as_deref() != Some("dprintf") {
+            return None;
+        }
+        let line = self.script.first()?;
+        let start = line.find('"')?;
+        // `call (void) printf ("x is %d\n",x)` with the call style
+        let line = match line.starts_with("call ") {
+            true => line[start..].strip_suffix(')')?,
+            false => &line[start..],
+        };
+        let mut escaped = false;
+        let end = line[1..].find(|c| {
+            let end = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            end
+        })? + 2;
+        let format = msg::unquote(&line[..end]);
+        Some((format, split_arguments(&line[end..])))
+    }
+}
+
+/// Split `,a,f(b, c)` into `a` and `f(b, c)`
+fn split_arguments(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            _ if in_string => {
+                in_string = c != '"' || escaped;
+                escaped = c == '\\' && !escaped;
+            }
+            '"' => in_string = true,
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                args.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(arg) = args.last_mut() {
+            arg.push(c);
+        }
+    }
+    args.iter().map(|arg| arg.trim().to_string()).collect()
+}
+
+impl Debugger {
+    /// Print `format` with `args` when `location` is reached and resume the program,
+    /// like a `printf` added to the source. `options` apply as for `insert_breakpoint()`,
+    /// except `hardware` and `commands`
+    pub async fn insert_dprintf(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        location: &str,
+        format: &str,
+        args: &[String],
+        options: &BreakpointOptions,
+    ) -> Result<Breakpoint> {
+        if options.qualified {
+            self.require_gdb(Version::QUALIFIED_BREAKPOINTS, "--qualified")?;
+        }
+        let options = BreakpointOptions {
+            hardware: false,
+            ..options.clone()
+        };
+        let mut cmd = options.command("-dprintf-insert", location);
+        cmd.push(' ');
+        cmd.push_str(&mi_quote(format));
+        for arg in args {
+            cmd.push(' ');
+            cmd.push_str(&mi_quote(arg));
+        }
+        let record = self.send_cmd(output_channel, &cmd).await?;
+        Breakpoint::from_results(&record.content).ok_or(Error::UnexpectedResponse)
+    }
+
+    /// Choose how dprintf breakpoints print. Applies to the dprintf breakpoints inserted
+    /// afterwards
+    pub async fn set_dprintf_style(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        style: &DprintfStyle,
+    ) -> Result<()> {
+        let name = match style {
+            DprintfStyle::Gdb => "gdb",
+            DprintfStyle::Call { function, channel } => {
+                self.send_cmd(
+                    output_channel,
+                    &format!("-gdb-set dprintf-function {}", function),
+                )
+                .await?;
+                let channel = channel.as_deref().unwrap_or_default();
+                self.send_cmd(
+                    output_channel,
+                    format!("-gdb-set dprintf-channel {}", channel).trim_end(),
+                )
+                .await?;
+                "call"
+            }
+            DprintfStyle::Agent => "agent",
+        };
+        self.send_cmd(output_channel, &format!("-gdb-set dprintf-style {}", name))
+            .await?;
+        Ok(())
+    }
+}
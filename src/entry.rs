@@ -0,0 +1,180 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::BreakpointOptions;
+use crate::dbg::{Debugger, Error, Result};
+use crate::events::StopEvent;
+use crate::msg;
+use tokio::sync::mpsc::Receiver;
+
+/// Symbols tried by `start_paused()` when the program has no `main`: the usual ELF
+/// entry symbol and the ARM Cortex-M reset vector
+pub const DEFAULT_ENTRY_SYMBOLS: [&str; 2] = ["_start", "Reset_Handler"];
+
+/// Where `start_paused()` stopped the program
+#[derive(Debug, Clone, PartialEq)]
+pub enum EntryPoint {
+    Main,
+    /// One of the fallback symbols
+    Symbol(String),
+    /// The entry point address from the executable's header
+    Address(String),
+}
+
+/// Outcome of `start_paused()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartReport {
+    pub entry: EntryPoint,
+    pub stop: StopEvent,
+}
+
+/// A section loaded in the target's memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecSection {
+    /// e.g. `.text`
+    pub name: String,
+    pub start: u64,
+    /// One past the last byte
+    pub end: u64,
+    /// The shared library the section belongs to, `None` for the executable
+    pub library: Option<String>,
+}
+
+impl ExecSection {
+    pub fn size(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        (self.start..self.end).contains(&address)
+    }
+}
+
+/// The entry point and sections of the program, see `Debugger::exec_sections()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecSections {
+    pub entry_point: Option<u64>,
+    pub sections: Vec<ExecSection>,
+}
+
+impl ExecSections {
+    /// The section containing `address`
+    pub fn section_at(&self, address: u64) -> Option<&ExecSection> {
+        self.sections.iter().find(|section| section.contains(address))
+    }
+}
+
+fn parse_address(text: &str) -> Option<u64> {
+    u64::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
+
+/// Parse the output of `info files`: the entry point and the
+/// `0x0000000000401000 - 0x0000000000401195 is .text [in /lib/libc.so.6]` lines
+pub(crate) fn parse_exec_sections(info_files: &str) -> ExecSections {
+    let sections = info_files
+        .lines()
+        .filter_map(|line| {
+            let (range, rest) = line.trim().split_once(" is ")?;
+            let (start, end) = range.split_once(" - ")?;
+            let (name, library) = match rest.split_once(" in ") {
+                Some((name, library)) => (name, Some(library.to_string())),
+                None => (rest, None),
+            };
+            Some(ExecSection {
+                name: name.to_string(),
+                start: parse_address(start.trim())?,
+                end: parse_address(end.trim())?,
+                library,
+            })
+        })
+        .collect();
+    ExecSections {
+        entry_point: parse_entry_point(info_files).as_deref().and_then(parse_address),
+        sections,
+    }
+}
+
+/// Extract the address from the `Entry point: 0x401000` line of `info files`
+pub(crate) fn parse_entry_point(info_files: &str) -> Option<String> {
+    info_files.lines().find_map(|line| {
+        let address = line.trim().strip_prefix("Entry point:")?.trim();
+        Some(address.to_string()).filter(|a| a.starts_with("0x"))
+    })
+}
+
+impl Debugger {
+    /// The entry point and the sections of the executable, and of the shared libraries
+    /// once the program runs. gdb does not implement `-file-list-exec-sections`, the
+    /// sections are parsed from `info files`
+    pub async fn exec_sections(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<ExecSections> {
+        let info = self.console(output_channel, "info files").await?;
+        Ok(parse_exec_sections(&info))
+    }
+
+    /// Start the program and stop it before it executes any user code: at `main`, or if
+    /// there is no `main` (embedded images, test harnesses...) at the first of
+    /// `fallback_symbols` that exists (see `DEFAULT_ENTRY_SYMBOLS`), or at the executable's
+    /// entry point address
+    pub async fn start_paused(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        fallback_symbols: &[&str],
+    ) -> Result<StartReport> {
+        let temporary = BreakpointOptions {
+            temporary: true,
+            ..Default::default()
+        };
+        let mut entry = None;
+        let candidates = std::iter::once(("main", EntryPoint::Main)).chain(
+            fallback_symbols
+                .iter()
+                .map(|symbol| (*symbol, EntryPoint::Symbol(symbol.to_string()))),
+        );
+        for (symbol, candidate) in candidates {
+            // fails when the symbol does not exist
+            if self
+                .insert_breakpoint(output_channel, symbol, &temporary)
+                .await
+                .is_ok()
+            {
+                entry = Some(candidate);
+                break;
+            }
+        }
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                let info = self.console(output_channel, "info files").await?;
+                let address = parse_entry_point(&info).ok_or_else(|| {
+                    Error::GdbError("cannot find the program's entry point".to_string())
+                })?;
+                self.insert_breakpoint(output_channel, &format!("*{}", address), &temporary)
+                    .await?;
+                EntryPoint::Address(address)
+            }
+        };
+        self.run(output_channel).await?;
+        let stop = self.wait_for_stop(output_channel).await?;
+        Ok(StartReport { entry, stop })
+    }
+}
+
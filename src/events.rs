@@ -0,0 +1,257 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::Breakpoint;
+use crate::catchpoints::CatchpointHit;
+use crate::debuginfod::DebuginfodEvent;
+use crate::download::DownloadProgress;
+use crate::lifecycle::{EndPolicy, ExitHookResult};
+use crate::modules::Module;
+use crate::msg::{find_field, AsyncClass, MessageRecord, Value, Variable};
+use serde_json::json;
+
+/// A stack frame as reported by gdb (`frame={...}`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frame {
+    pub level: Option<u32>,
+    pub addr: Option<String>,
+    pub func: Option<String>,
+    pub file: Option<String>,
+    pub fullname: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl Frame {
+    /// Build a frame from the tuple value of a `frame=` field
+    pub fn from_value(value: &Value) -> Option<Frame> {
+        match value {
+            Value::VariableList(vars) => Some(Self::from_vars(vars)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_vars(vars: &[Variable]) -> Frame {
+        let text = |name| find_field(vars, name).and_then(Value::unquoted);
+        Frame {
+            level: text("level").and_then(|s| s.parse().ok()),
+            addr: text("addr"),
+            func: text("func"),
+            file: text("file"),
+            fullname: text("fullname"),
+            line: text("line").and_then(|s| s.parse().ok()),
+        }
+    }
+}
+
+/// A typed `*stopped` record
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StopEvent {
+    /// The stop reason, e.g. `breakpoint-hit`. Missing when gdb does not report one
+    pub reason: Option<String>,
+    /// The thread that caused the stop
+    pub thread_id: Option<String>,
+    /// The frame in which the thread stopped
+    pub frame: Option<Frame>,
+    /// The breakpoint, watchpoint or catchpoint that was hit (`bkptno`)
+    pub breakpoint: Option<String>,
+    /// What the catchpoint that was hit caught
+    pub catchpoint: Option<CatchpointHit>,
+}
+
+impl StopEvent {
+    pub fn from_record(record: &MessageRecord<AsyncClass>) -> StopEvent {
+        StopEvent {
+            reason: record.get("reason").and_then(Value::unquoted),
+            thread_id: record.get("thread-id").and_then(Value::unquoted),
+            frame: record.get("frame").and_then(Frame::from_value),
+            breakpoint: record.get("bkptno").and_then(Value::unquoted),
+            catchpoint: CatchpointHit::from_record(record),
+        }
+    }
+}
+
+/// How the debuggee exited, see `Debugger::debuggee_exit_status()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExitStatus {
+    /// The program exited with this code
+    Exited(i32),
+    /// The program was killed by a signal, e.g. `SIGSEGV`
+    Signalled(String),
+}
+
+impl ExitStatus {
+    /// Parse `*stopped,reason="exited..."` or `=thread-group-exited,exit-code=...`.
+    /// `=thread-group-exited` carries no code when the program was signalled or killed
+    pub fn from_record(record: &MessageRecord<AsyncClass>) -> Option<ExitStatus> {
+        let text = |name| record.get(name).and_then(Value::unquoted);
+        // exit codes are reported in octal
+        let code = || text("exit-code").and_then(|code| i32::from_str_radix(&code, 8).ok());
+        match record.class {
+            AsyncClass::Stopped => match text("reason")?.as_str() {
+                "exited-normally" => Some(ExitStatus::Exited(0)),
+                "exited" => Some(ExitStatus::Exited(code().unwrap_or(0))),
+                "exited-signalled" => Some(ExitStatus::Signalled(
+                    text("signal-name").unwrap_or_default(),
+                )),
+                _ => None,
+            },
+            AsyncClass::ThreadGroupExited => code().map(ExitStatus::Exited),
+            _ => None,
+        }
+    }
+
+    /// The exit code, `None` if the program was signalled
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            ExitStatus::Exited(code) => Some(*code),
+            ExitStatus::Signalled(_) => None,
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.code() == Some(0)
+    }
+}
+
+/// Events generated by the crate itself (as opposed to records parsed from gdb's output).
+/// They are delivered on the output channel as `Record::Event`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebuggerEvent {
+    /// The connection to the remote target was lost
+    TargetDisconnected { reason: String },
+    /// A line gdb (or a wrapper script) printed before the MI session started
+    StartupNoise { source: OutputSource, line: String },
+    /// The debug session was ended with `Debugger::stop_debugging()` or `Debugger::close()`.
+    /// `hooks` holds the outcome of each exit hook, in the order they were run
+    SessionEnded {
+        policy: EndPolicy,
+        hooks: Vec<ExitHookResult>,
+    },
+    /// The frame of a watched local variable exited, gdb deleted watchpoint `number`
+    WatchpointOutOfScope { number: String },
+    /// `-target-download` progress, parsed from a `+download` record
+    Download(DownloadProgress),
+    /// `Debugger::attach()` attached to process `pid`, which gdb stopped
+    Attached { pid: u32 },
+    /// A debuginfod download started, failed or was cancelled
+    Debuginfod(DebuginfodEvent),
+    /// gdb was restarted by `Debugger::restart_gdb()`. `restored` breakpoints were
+    /// re-created, `unresolved` could not be
+    Restarted { restored: usize, unresolved: usize },
+    /// A pending breakpoint was bound to an address, the library defining its location
+    /// was loaded. Parsed from `=breakpoint-modified`
+    BreakpointBound(Box<Breakpoint>),
+    /// A shared library was loaded, parsed from `=library-loaded`
+    LibraryLoaded(Module),
+    /// A shared library was unloaded, parsed from `=library-unloaded`. Its addresses
+    /// are not reported
+    LibraryUnloaded(Module),
+}
+
+impl DebuggerEvent {
+    /// `{"event": "TargetDisconnected", ...fields}`, see `Record::to_json()`
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            DebuggerEvent::TargetDisconnected { reason } => {
+                json!({"event": "TargetDisconnected", "reason": reason})
+            }
+            DebuggerEvent::StartupNoise { source, line } => {
+                json!({"event": "StartupNoise", "source": format!("{:?}", source), "line": line})
+            }
+            DebuggerEvent::SessionEnded { policy, hooks } => {
+                let hooks: Vec<serde_json::Value> = hooks
+                    .iter()
+                    .map(|hook| json!({"command": hook.command, "error": hook.error}))
+                    .collect();
+                json!({"event": "SessionEnded", "policy": format!("{:?}", policy), "hooks": hooks})
+            }
+            DebuggerEvent::WatchpointOutOfScope { number } => {
+                json!({"event": "WatchpointOutOfScope", "number": number})
+            }
+            DebuggerEvent::Attached { pid } => json!({"event": "Attached", "pid": pid}),
+            DebuggerEvent::BreakpointBound(bkpt) => json!({
+                "event": "BreakpointBound",
+                "number": bkpt.number,
+                "location": bkpt.location(),
+                "addr": bkpt.addr,
+                "func": bkpt.func,
+                "file": bkpt.file,
+                "fullname": bkpt.fullname,
+                "line": bkpt.line,
+            }),
+            DebuggerEvent::LibraryLoaded(module) | DebuggerEvent::LibraryUnloaded(module) => {
+                let event = match self {
+                    DebuggerEvent::LibraryLoaded(_) => "LibraryLoaded",
+                    _ => "LibraryUnloaded",
+                };
+                let ranges: Vec<serde_json::Value> = module
+                    .ranges
+                    .iter()
+                    .map(|range| json!({"from": range.start, "to": range.end}))
+                    .collect();
+                json!({
+                    "event": event,
+                    "id": module.id,
+                    "target_name": module.target_name,
+                    "host_name": module.host_name,
+                    "symbols_loaded": module.symbols_loaded,
+                    "thread_group": module.thread_group,
+                    "ranges": ranges,
+                })
+            }
+            DebuggerEvent::Restarted {
+                restored,
+                unresolved,
+            } => json!({"event": "Restarted", "restored": restored, "unresolved": unresolved}),
+            DebuggerEvent::Debuginfod(event) => {
+                let (status, download, reason) = match event {
+                    DebuginfodEvent::Started(download) => ("started", download, None),
+                    DebuginfodEvent::Failed { download, reason } => {
+                        ("failed", download, Some(reason))
+                    }
+                    DebuginfodEvent::Cancelled(download) => ("cancelled", download, None),
+                };
+                json!({
+                    "event": "Debuginfod",
+                    "status": status,
+                    "kind": download.kind,
+                    "target": download.target,
+                    "reason": reason,
+                })
+            }
+            DebuggerEvent::Download(progress) => json!({
+                "event": "Download",
+                "section": progress.section,
+                "section_sent": progress.section_sent,
+                "section_size": progress.section_size,
+                "total_sent": progress.total_sent,
+                "total_size": progress.total_size,
+                "percent": progress.percent(),
+            }),
+        }
+    }
+}
+
+/// The gdb output stream a line was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputSource {
+    Stdout,
+    Stderr,
+}
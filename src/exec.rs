@@ -0,0 +1,217 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::BreakpointOptions;
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::events::StopEvent;
+use crate::msg::{self, AsyncClass, AsyncRecord};
+use tokio::sync::mpsc::Receiver;
+
+tokio::task_local! {
+    /// Set while a task runs a `with_stopped()` section
+    static FENCE_HELD: ();
+}
+
+/// Does the current task run a `with_stopped()` section?
+fn holds_fence() -> bool {
+    FENCE_HELD.try_with(|_| ()).is_ok()
+}
+
+/// Execution control. Each method returns once gdb acknowledged the command with
+/// `^running`; chain with `wait_for_stop()` to wait for the program to stop again
+impl Debugger {
+    /// Send an `-exec-*` command and wait for its result record. Commands that are not
+    /// valid in the current state are refused, see `DebuggerState`. The target is not
+    /// resumed while another task runs a `with_stopped()` section: the command waits for
+    /// the section to end
+    pub(crate) async fn exec(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        cmd: &str,
+    ) -> Result<()> {
+        let _fence = match holds_fence() {
+            true => None,
+            false => Some(self.fence.lock().await),
+        };
+        self.check_exec(cmd)?;
+        self.send_cmd(output_channel, cmd).await?;
+        Ok(())
+    }
+
+    /// Start the program (`-exec-run`)
+    pub async fn run(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.exec(output_channel, "-exec-run").await
+    }
+
+    /// Start the program and stop at the beginning of `main` (`-exec-run --start`). gdbs
+    /// without the `--start` option get a temporary breakpoint on `main` instead
+    pub async fn run_start(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        if self.supports(|c| c.exec_run_start_option) {
+            return self.exec(output_channel, "-exec-run --start").await;
+        }
+        self.check_exec("-exec-run")?;
+        let temporary = BreakpointOptions {
+            temporary: true,
+            ..Default::default()
+        };
+        self.insert_breakpoint(output_channel, "main", &temporary)
+            .await?;
+        self.run(output_channel).await
+    }
+
+    /// Kill the program and start it again. The program must be stopped. Over an
+    /// extended-remote connection the server stays up and runs the program again
+    pub async fn restart(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        if let Err(e) = self
+            .send_cmd(output_channel, "-interpreter-exec console \"kill\"")
+            .await
+        {
+            // the program may have exited already
+            tracing::debug!("kill failed: {}", e);
+        }
+        self.reset_session();
+        self.run(output_channel).await
+    }
+
+    /// Resume the program (`-exec-continue`)
+    pub async fn continue_(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.exec(output_channel, "-exec-continue").await
+    }
+
+    /// Step into the next source line (`-exec-step`)
+    pub async fn step_into(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.exec(output_channel, "-exec-step").await
+    }
+
+    /// Step over the next source line (`-exec-next`)
+    pub async fn step_over(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.exec(output_channel, "-exec-next").await
+    }
+
+    /// Run until the current function returns (`-exec-finish`)
+    pub async fn step_out(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.exec(output_channel, "-exec-finish").await
+    }
+
+    /// Same as `step_out()`
+    pub async fn finish(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.step_out(output_channel).await
+    }
+
+    /// Execute one machine instruction, stepping into calls (`-exec-step-instruction`)
+    pub async fn step_instruction(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        self.exec(output_channel, "-exec-step-instruction").await
+    }
+
+    /// Execute one machine instruction, stepping over calls (`-exec-next-instruction`)
+    pub async fn next_instruction(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        self.exec(output_channel, "-exec-next-instruction").await
+    }
+
+    /// Run until `location` is reached or the current frame returns (`-exec-until`)
+    pub async fn until(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        location: &str,
+    ) -> Result<()> {
+        self.exec(output_channel, &format!("-exec-until {}", mi_quote(location)))
+            .await
+    }
+
+    /// Resume execution at `location` (`-exec-jump`)
+    pub async fn jump(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        location: &str,
+    ) -> Result<()> {
+        self.exec(output_channel, &format!("-exec-jump {}", mi_quote(location)))
+            .await
+    }
+
+    /// Wait for the next `*stopped` record and return it as a `StopEvent`.
+    /// Records received in the meantime are buffered, and returned by the next
+    /// calls to `read_message_record()`. Fails with `Error::ChannelClosed` if gdb exits
+    pub async fn wait_for_stop(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<StopEvent> {
+        let mut skipped = Vec::new();
+        let stop = loop {
+            match self.read_message_record(output_channel).await {
+                Ok(msg::Record::Async(AsyncRecord::Exec(record)))
+                    if record.class == AsyncClass::Stopped =>
+                {
+                    let mut stop = StopEvent::from_record(&record);
+                    self.resolve_catchpoint(&mut stop, &skipped);
+                    break Ok(stop);
+                }
+                Ok(record) => skipped.push(record),
+                Err(e) => break Err(e),
+            }
+        };
+        // buffered records that were re-read above are put back in front, in order
+        let mut pending = self.pending.lock().unwrap();
+        for record in skipped.into_iter().rev() {
+            pending.push_front(record);
+        }
+        stop
+    }
+
+    /// Run `f` while the target is stopped. If the target is running it is interrupted
+    /// first and resumed once `f` completes. Fenced sections are serialized, and execution
+    /// commands sent by other tasks wait for the section to end, so queries made inside
+    /// `f` never race a resume. `f` itself may resume the target.
+    ///
+    /// Not reentrant: calling `with_stopped()` from `f` fails with `Error::NestedFence`
+    pub async fn with_stopped<F, T>(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        f: F,
+    ) -> Result<T>
+    where
+        F: AsyncFnOnce(&Debugger, &mut Receiver<msg::Record>) -> T,
+    {
+        if holds_fence() {
+            return Err(Error::NestedFence);
+        }
+        let fence = self.fence.clone();
+        let _guard = fence.lock().await;
+
+        FENCE_HELD
+            .scope((), async {
+                let was_running = !self.can_send_commands();
+                if was_running {
+                    if !self.interrupt() {
+                        return Err(Error::InterruptFailed);
+                    }
+                    self.wait_for_stop(output_channel).await?;
+                }
+                let result = f(self, output_channel).await;
+                if was_running {
+                    self.continue_(output_channel).await?;
+                }
+                Ok(result)
+            })
+            .await
+    }
+}
@@ -0,0 +1,169 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg::{self, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// The thread and frame a command applies to, passed to gdb as `--thread`/`--frame`
+/// so the command does not change the selected thread and frame. Missing fields
+/// default to the current selection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameContext {
+    pub thread: Option<String>,
+    /// 0 is the innermost frame
+    pub frame: Option<u32>,
+}
+
+impl FrameContext {
+    /// Frame `level` of the selected thread
+    pub fn frame(level: u32) -> FrameContext {
+        FrameContext {
+            thread: None,
+            frame: Some(level),
+        }
+    }
+
+    /// Frame `level` of thread `thread`
+    pub fn thread_frame(thread: &str, level: u32) -> FrameContext {
+        FrameContext {
+            thread: Some(thread.to_string()),
+            frame: Some(level),
+        }
+    }
+}
+
+/// A register and its value, see `Debugger::registers()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisterValue {
+    pub number: u32,
+    pub name: String,
+    pub value: String,
+}
+
+impl Debugger {
+    /// Select frame `level` of the selected thread (`-stack-select-frame`)
+    pub async fn select_frame(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        level: u32,
+    ) -> Result<()> {
+        self.send_cmd(output_channel, &format!("-stack-select-frame {}", level))
+            .await?;
+        // gdb does not notify about selections made with MI commands
+        self.session.lock().unwrap().selected_frame = Some(level);
+        Ok(())
+    }
+
+    /// Evaluate `expression` in the frame described by `context`
+    /// (`-data-evaluate-expression`). When renderers are registered, the type of the
+    /// result is looked up first with a varobj, see `register_renderer()`
+    pub async fn evaluate(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        expression: &str,
+        context: &FrameContext,
+    ) -> Result<String> {
+        let options = self.context_options(output_channel, context).await?;
+        let renderers = self.renderers();
+        if !renderers.is_empty() {
+            let rendered = self
+                .render_expression(output_channel, &renderers, &options, expression)
+                .await?;
+            if let Some(rendered) = rendered {
+                return Ok(rendered);
+            }
+        }
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-data-evaluate-expression {} {}", options, mi_quote(expression)),
+            )
+            .await?;
+        record
+            .get("value")
+            .and_then(Value::unquoted)
+            .ok_or(Error::UnexpectedResponse)
+    }
+
+    /// The general purpose registers values in the frame described by `context`, in
+    /// gdb's natural format (`-data-list-register-values`)
+    pub async fn registers(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        context: &FrameContext,
+    ) -> Result<Vec<RegisterValue>> {
+        let record = self
+            .send_cmd(output_channel, "-data-list-register-names")
+            .await?;
+        let names: Vec<String> = match record.get("register-names") {
+            Some(Value::ValueList(names)) => names.iter().filter_map(Value::unquoted).collect(),
+            _ => Vec::new(),
+        };
+        let options = self.context_options(output_channel, context).await?;
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-data-list-register-values {} --skip-unavailable N", options),
+            )
+            .await?;
+        Ok(register_values(record.get("register-values"), &names))
+    }
+
+    /// Options for `context`. `--frame` requires `--thread`: when no thread is given, the
+    /// selected one is used, and if that is not known yet the frame is selected instead
+    pub(crate) async fn context_options(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        context: &FrameContext,
+    ) -> Result<String> {
+        let thread = context
+            .thread
+            .clone()
+            .or_else(|| context.frame.and(self.snapshot().selected_thread));
+        match (thread, context.frame) {
+            (Some(thread), Some(frame)) => Ok(format!("--thread {} --frame {}", thread, frame)),
+            (Some(thread), None) => Ok(format!("--thread {}", thread)),
+            (None, Some(frame)) => {
+                self.select_frame(output_channel, frame).await?;
+                Ok(String::new())
+            }
+            (None, None) => Ok(String::new()),
+        }
+    }
+}
+
+/// Parse `[{number="0",value="0x1"},...]`, `names` are indexed by register number.
+/// Registers without a name are not real registers and are skipped
+pub(crate) fn register_values(list: Option<&Value>, names: &[String]) -> Vec<RegisterValue> {
+    let Some(Value::ValueList(values)) = list else {
+        return Vec::new();
+    };
+    values
+        .iter()
+        .filter_map(|value| {
+            let number: u32 = value.get("number")?.unquoted()?.parse().ok()?;
+            let name = names.get(number as usize).filter(|name| !name.is_empty())?;
+            Some(RegisterValue {
+                number,
+                name: name.clone(),
+                value: value.get("value")?.unquoted()?,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Error, LossyLines, Result};
+use crate::msg;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::mpsc::Receiver;
+
+/// What gdbserver debugs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GdbServerTarget {
+    /// Start `program` with `args`
+    Launch { program: String, args: Vec<String> },
+    /// Attach to a running process (`--attach`)
+    Attach(u32),
+}
+
+/// A local gdbserver to debug through, see `Debugger::start_gdbserver()`
+#[derive(Debug, Clone)]
+pub struct GdbServer {
+    /// The gdbserver binary
+    pub program: String,
+    /// The TCP port gdbserver listens on
+    pub port: u16,
+    pub target: GdbServerTarget,
+    /// How long gdbserver may take to start listening
+    pub startup_timeout: Duration,
+}
+
+impl GdbServer {
+    /// gdbserver from the `GDBSERVER_BINARY` environment variable, or `gdbserver`
+    pub fn new(target: GdbServerTarget, port: u16) -> Self {
+        GdbServer {
+            program: ::std::env::var("GDBSERVER_BINARY").unwrap_or("gdbserver".to_string()),
+            port,
+            target,
+            startup_timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// The address gdb connects to
+    pub fn address(&self) -> String {
+        format!("localhost:{}", self.port)
+    }
+
+    pub(crate) fn args(&self) -> Vec<String> {
+        let comm = format!(":{}", self.port);
+        match &self.target {
+            GdbServerTarget::Launch { program, args } => {
+                let mut all = vec![comm, program.clone()];
+                all.extend(args.iter().cloned());
+                all
+            }
+            GdbServerTarget::Attach(pid) => vec!["--attach".to_string(), comm, pid.to_string()],
+        }
+    }
+}
+
+impl Debugger {
+    /// Spawn `server`, wait until it listens, and connect to it with `target remote`.
+    /// gdbserver is killed when the session ends (`stop_debugging()`, `close()`) or when
+    /// the debugger is dropped. The output of a launched program is discarded
+    pub async fn start_gdbserver(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        server: &GdbServer,
+    ) -> Result<()> {
+        self.stop_gdbserver();
+        let mut child = Command::new(&server.program)
+            .args(server.args())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stderr = child
+            .stderr
+            .take()
+            .expect("child did not have a handle to stderr");
+
+        // gdbserver announces it is ready on stderr, no need to probe the port. The
+        // debuggee it launched prints there too
+        let mut lines = LossyLines::new(stderr);
+        let listening = async {
+            let mut last = String::new();
+            while let Some(line) = lines.next_line().await? {
+                tracing::debug!("gdbserver: {}", line);
+                if line.starts_with("Listening on port") {
+                    return Ok(());
+                }
+                last = line;
+            }
+            Err(Error::GdbError(format!("gdbserver exited: {}", last)))
+        };
+        tokio::time::timeout(server.startup_timeout, listening)
+            .await
+            .unwrap_or(Err(Error::Timeout))?;
+        // keep reading, gdbserver blocks once the pipe is full
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("gdbserver: {}", line);
+            }
+        });
+
+        self.connect_remote(output_channel, &server.address()).await?;
+        *self.gdbserver.lock().unwrap() = Some(child);
+        Ok(())
+    }
+
+    /// Kill the gdbserver started by `start_gdbserver()`, if any
+    pub fn stop_gdbserver(&self) {
+        if let Some(mut child) = self.gdbserver.lock().unwrap().take() {
+            if let Err(e) = child.start_kill() {
+                tracing::debug!("failed to kill gdbserver: {}", e);
+            }
+        }
+    }
+}
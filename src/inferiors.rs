@@ -0,0 +1,154 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg::{self, find_field, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// Which process gdb debugs after a fork (`set follow-fork-mode`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FollowForkMode {
+    #[default]
+    Parent,
+    Child,
+}
+
+/// A gdb inferior: a process being debugged, or a slot for one (`-list-thread-groups`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Inferior {
+    /// The thread group id, e.g. `i1`
+    pub id: String,
+    /// Missing until the inferior runs
+    pub pid: Option<u32>,
+    pub executable: Option<String>,
+    /// Set once the process exited
+    pub exit_code: Option<i32>,
+}
+
+impl Inferior {
+    /// The inferior number used by CLI commands: `2` for `i2`
+    pub fn number(&self) -> &str {
+        inferior_number(&self.id)
+    }
+}
+
+fn inferior_number(id: &str) -> &str {
+    id.strip_prefix('i').unwrap_or(id)
+}
+
+/// Parse the `groups` list of `-list-thread-groups`
+pub(crate) fn parse_inferiors(groups: Option<&Value>) -> Vec<Inferior> {
+    let Some(Value::ValueList(groups)) = groups else {
+        return Vec::new();
+    };
+    groups
+        .iter()
+        .filter_map(|group| match group {
+            Value::VariableList(vars) => Some(vars),
+            _ => None,
+        })
+        .filter_map(|vars| {
+            let text = |name| find_field(vars, name).and_then(Value::unquoted);
+            Some(Inferior {
+                id: text("id")?,
+                pid: text("pid").and_then(|pid| pid.parse().ok()),
+                executable: text("executable"),
+                // reported in octal, like the exit codes of `*stopped`
+                exit_code: text("exit-code").and_then(|code| i32::from_str_radix(&code, 8).ok()),
+            })
+        })
+        .collect()
+}
+
+impl Debugger {
+    /// Choose the process gdb keeps debugging after a fork (`set follow-fork-mode`)
+    pub async fn set_follow_fork_mode(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        mode: FollowForkMode,
+    ) -> Result<()> {
+        let mode = match mode {
+            FollowForkMode::Parent => "parent",
+            FollowForkMode::Child => "child",
+        };
+        self.send_cmd(output_channel, &format!("-gdb-set follow-fork-mode {}", mode))
+            .await?;
+        Ok(())
+    }
+
+    /// With `false`, both processes of a fork stay under gdb's control, the one not
+    /// followed becomes a new inferior (`set detach-on-fork`)
+    pub async fn set_detach_on_fork(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        detach: bool,
+    ) -> Result<()> {
+        let cmd = format!("-gdb-set detach-on-fork {}", if detach { "on" } else { "off" });
+        self.send_cmd(output_channel, &cmd).await?;
+        Ok(())
+    }
+
+    /// All the inferiors (`-list-thread-groups`)
+    pub async fn inferiors(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<Vec<Inferior>> {
+        let record = self.send_cmd(output_channel, "-list-thread-groups").await?;
+        Ok(parse_inferiors(record.get("groups")))
+    }
+
+    /// Add an empty inferior (`-add-inferior`) and return its id
+    pub async fn add_inferior(&self, output_channel: &mut Receiver<msg::Record>) -> Result<String> {
+        let record = self.send_cmd(output_channel, "-add-inferior").await?;
+        record
+            .get("inferior")
+            .and_then(Value::unquoted)
+            .ok_or(Error::UnexpectedResponse)
+    }
+
+    /// Make inferior `id` (e.g. `i2`) the current one: the following commands apply to it
+    pub async fn select_inferior(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        id: &str,
+    ) -> Result<()> {
+        // MI has no command for it, `--thread-group` only applies to a single command
+        let cmd = format!("inferior {}", inferior_number(id));
+        self.send_cmd(
+            output_channel,
+            &format!("-interpreter-exec console {}", mi_quote(&cmd)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Kill the process of inferior `id`. The inferior itself remains, empty
+    pub async fn kill_inferior(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        id: &str,
+    ) -> Result<()> {
+        let cmd = format!("kill inferiors {}", inferior_number(id));
+        self.send_cmd(
+            output_channel,
+            &format!("-interpreter-exec console {}", mi_quote(&cmd)),
+        )
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Windows Job Object holding the gdb processes, so they don't outlive the host process
+
+use std::io;
+use std::sync::OnceLock;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::jobapi2::{AssignProcessToJobObject, CreateJobObjectW, SetInformationJobObject};
+use winapi::um::winnt::{
+    JobObjectExtendedLimitInformation, HANDLE, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+};
+
+/// The job handle, as an integer since `HANDLE` is not `Sync`. It is never closed: Windows
+/// closes it when the host exits, which kills every process in the job
+static HOST_JOB: OnceLock<usize> = OnceLock::new();
+
+fn create_job() -> io::Result<usize> {
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null_mut(), std::ptr::null());
+        if job.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        let ok = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &mut info as *mut _ as *mut _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        );
+        if ok == 0 {
+            let e = io::Error::last_os_error();
+            CloseHandle(job);
+            return Err(e);
+        }
+        Ok(job as usize)
+    }
+}
+
+/// Put process `process` in the host's kill-on-close job. Processes it starts (the
+/// debuggee) join the job too
+pub(crate) fn kill_on_host_exit(process: HANDLE) -> io::Result<()> {
+    let job = match HOST_JOB.get() {
+        Some(job) => *job,
+        None => {
+            let job = create_job()?;
+            match HOST_JOB.set(job) {
+                Ok(()) => job,
+                Err(_) => {
+                    // another thread created it first
+                    unsafe { CloseHandle(job as HANDLE) };
+                    *HOST_JOB.get().unwrap()
+                }
+            }
+        }
+    };
+    if unsafe { AssignProcessToJobObject(job as HANDLE, process) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
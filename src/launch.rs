@@ -0,0 +1,153 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::BreakpointOptions;
+use crate::dbg::{mi_quote, Debugger, Error, MiDialect, Result};
+use crate::events::{DebuggerEvent, Frame, StopEvent};
+use crate::msg::{self, Record};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use tokio::sync::mpsc::Receiver;
+
+/// How the debuggee is started or attached to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchConfig {
+    /// The program to load. `None` keeps the one already loaded
+    pub executable: Option<PathBuf>,
+    /// The program's arguments
+    pub args: Vec<String>,
+    /// The program's working directory
+    pub cwd: Option<String>,
+    /// Variables added to the program's environment, `(name, value)`
+    pub env: Vec<(String, String)>,
+    /// Stop at the beginning of `main` (`-exec-run --start`)
+    pub stop_at_main: bool,
+    /// gdb stops the process it attaches to. Resume it once attached, so attaching does
+    /// not interrupt the program for longer than needed
+    pub continue_after_attach: bool,
+}
+
+/// Quote `arg` for the shell gdb starts the program with
+fn shell_quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// `-exec-arguments` for `args`, passed to the program verbatim
+pub(crate) fn exec_arguments_cmd(args: &[String]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| mi_quote(&shell_quote(arg))).collect();
+    format!("-exec-arguments {}", args.join(" "))
+}
+
+impl Debugger {
+    /// Load the executable and set the arguments, working directory and environment of
+    /// `config`, without starting the program
+    pub async fn configure_launch(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        config: &LaunchConfig,
+    ) -> Result<()> {
+        if let Some(executable) = &config.executable {
+            self.load_executable(output_channel, executable).await?;
+        }
+        if !config.args.is_empty() {
+            self.send_cmd(output_channel, &exec_arguments_cmd(&config.args))
+                .await?;
+        }
+        if let Some(cwd) = &config.cwd {
+            self.set_working_directory(output_channel, cwd).await?;
+        }
+        for (name, value) in &config.env {
+            let cmd = format!("set environment {}={}", name, value);
+            self.send_cmd(
+                output_channel,
+                &format!("-interpreter-exec console {}", mi_quote(&cmd)),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Configure the launch (see `configure_launch()`) and start the program. Returns once
+    /// it runs, chain with `wait_for_stop()` to wait for it to stop (at `main` with
+    /// `config.stop_at_main`)
+    pub async fn launch(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        config: &LaunchConfig,
+    ) -> Result<()> {
+        self.configure_launch(output_channel, config).await?;
+        if config.stop_at_main {
+            self.run_start(output_channel).await
+        } else {
+            self.run(output_channel).await
+        }
+    }
+
+    /// Start the program and run it to the beginning of `main`. Return the frame it
+    /// stopped in. gdb stops there with `-exec-run --start`, lldb-mi is given a temporary
+    /// breakpoint instead. See `start_paused()` for programs without a `main`
+    pub async fn run_to_main(&self, output_channel: &mut Receiver<msg::Record>) -> Result<Frame> {
+        match self.dialect() {
+            MiDialect::Gdb => self.run_start(output_channel).await?,
+            MiDialect::LldbMi => {
+                let temporary = BreakpointOptions {
+                    temporary: true,
+                    ..Default::default()
+                };
+                self.insert_breakpoint(output_channel, "main", &temporary)
+                    .await?;
+                self.run(output_channel).await?;
+            }
+        }
+        let stop = self.wait_for_stop(output_channel).await?;
+        if stop.reason.as_deref().is_some_and(|r| r.starts_with("exited")) {
+            return Err(Error::GdbError(
+                "the program exited before reaching main".to_string(),
+            ));
+        }
+        stop.frame.ok_or(Error::UnexpectedResponse)
+    }
+
+    /// Attach to the running process `pid` (`-target-attach`). The stop gdb reports once
+    /// attached is returned, and emitted as `DebuggerEvent::Attached` instead of a
+    /// `*stopped` record. The breakpoints are inserted in the process by then, the
+    /// program is resumed if `config.continue_after_attach` is set
+    pub async fn attach(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        pid: u32,
+        config: &LaunchConfig,
+    ) -> Result<StopEvent> {
+        self.send_cmd(output_channel, &format!("-target-attach {}", pid))
+            .await?;
+        self.debugee_pid.store(pid as usize, Ordering::Relaxed);
+        let stop = self.wait_for_stop(output_channel).await?;
+        if config.continue_after_attach {
+            self.continue_(output_channel).await?;
+        }
+        // sent last, commands discard the records preceding their result
+        let _ = self
+            .events
+            .send(Record::Event(DebuggerEvent::Attached { pid }))
+            .await;
+        Ok(stop)
+    }
+}
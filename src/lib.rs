@@ -17,64 +17,305 @@
 
 extern crate regex;
 
+pub mod assertions;
+mod backtrace;
+pub mod blocking;
+mod breakpoints;
+mod capabilities;
+mod catchpoints;
+mod channels;
+mod corpus;
+#[cfg(feature = "dap")]
+mod dap;
 mod dbg;
+mod debuginfod;
+mod demangle;
+mod download;
+mod dprintf;
+mod entry;
+mod events;
+mod exec;
+mod frames;
+mod gdbserver;
+mod inferiors;
+#[cfg(windows)]
+mod job;
+mod launch;
+mod lifecycle;
+mod metrics;
+#[cfg(feature = "lldb")]
+mod lldb;
+mod mock;
+mod modules;
 mod msg;
 mod parser;
+mod paths;
+mod reload;
+mod remote;
+mod render;
+mod reverse;
+mod rr;
+mod script;
+mod session;
+mod signals;
+mod skip;
+mod state;
+mod stop_bundle;
+mod stream;
+mod supervisor;
+mod symbols;
+mod timeline;
+mod tracepoints;
+mod transcript;
+mod variables;
+mod version;
+mod watchpoints;
 use std::future::Future;
 
 use sysinfo::Signal;
-#[cfg(not(windows))]
-use sysinfo::{Pid, ProcessExt, System, SystemExt};
-use tokio::runtime::Runtime;
+use tokio::runtime::{Handle, Runtime};
 
-/// Helper function to bridge between the async <-> sync code
-pub fn run_async(future: impl Future) {
+/// Helper function to bridge between the async <-> sync code.
+/// Return the future's output
+pub fn run_async<F: Future>(future: F) -> F::Output {
     let rt = Runtime::new().unwrap();
-    let local = tokio::task::LocalSet::new();
-    local.block_on(&rt, future);
+    rt.block_on(future)
 }
 
-#[cfg(target_os = "windows")]
-use winapi::um::winbase::DebugBreakProcess;
+/// Same as `run_async`, using an existing runtime instead of creating a new one.
+/// Like `Handle::block_on`, this panics when called from within an async context
+pub fn run_async_on<F: Future>(handle: &Handle, future: F) -> F::Output {
+    handle.block_on(future)
+}
 
-/// Send `sigid` to process with ID `pid`
-/// Return true on success
+/// Open process `pid` with `access` rights, run `f` on its handle and close it.
+/// `f` returns a Win32 `BOOL`, the error is read with `GetLastError()` on failure
 #[cfg(target_os = "windows")]
-pub fn signal(pid: usize, sigid: Signal) -> bool {
+fn with_process<F>(pid: usize, access: u32, f: F) -> std::io::Result<()>
+where
+    F: FnOnce(winapi::um::winnt::HANDLE) -> winapi::shared::minwindef::BOOL,
+{
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::OpenProcess;
     unsafe {
-        match sigid {
-            Signal::Interrupt => {
-                let open_mode: u32 = 2097151; // PROCESS_ALL_ACCESS
-                let process_handle =
-                    winapi::um::processthreadsapi::OpenProcess(open_mode, 0, pid as u32);
-                DebugBreakProcess(process_handle) == 1
-            }
-            Signal::Kill => {
-                let open_mode: u32 = 2097151; // PROCESS_ALL_ACCESS
-                let process_handle =
-                    winapi::um::processthreadsapi::OpenProcess(open_mode, 0, pid as u32);
-                winapi::um::processthreadsapi::TerminateProcess(process_handle, 0);
-                true
-            }
-            _ => false,
+        let handle = OpenProcess(access, 0, pid as u32);
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error());
         }
+        let result = if f(handle) != 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        };
+        CloseHandle(handle);
+        result
+    }
+}
+
+/// Send `CTRL_BREAK_EVENT` to the console process group `pid`. Only reaches console
+/// programs started in their own process group (`CREATE_NEW_PROCESS_GROUP`)
+#[cfg(target_os = "windows")]
+pub(crate) fn console_ctrl_break(pid: usize) -> std::io::Result<()> {
+    use winapi::um::wincon::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+    if unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid as u32) } != 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Send `sigid` to process with ID `pid`. Windows has no signals:
+///
+/// * `Interrupt` breaks into the debugger (`DebugBreakProcess`)
+/// * `Term` asks a console program to stop with `CTRL_BREAK_EVENT`, and terminates the
+///   process if it can't be delivered
+/// * `Kill` terminates the process (`TerminateProcess`)
+///
+/// Other signals fail with `ErrorKind::Unsupported`
+#[cfg(target_os = "windows")]
+pub(crate) fn signal(pid: usize, sigid: Signal) -> std::io::Result<()> {
+    use winapi::um::processthreadsapi::TerminateProcess;
+    use winapi::um::winbase::DebugBreakProcess;
+    use winapi::um::winnt::{PROCESS_ALL_ACCESS, PROCESS_TERMINATE};
+    match sigid {
+        Signal::Interrupt => with_process(pid, PROCESS_ALL_ACCESS, |handle| unsafe {
+            DebugBreakProcess(handle)
+        }),
+        Signal::Term => console_ctrl_break(pid).or_else(|e| {
+            tracing::debug!("cannot send CTRL_BREAK_EVENT to {}: {}", pid, e);
+            with_process(pid, PROCESS_TERMINATE, |handle| unsafe {
+                TerminateProcess(handle, 1)
+            })
+        }),
+        Signal::Kill => with_process(pid, PROCESS_TERMINATE, |handle| unsafe {
+            TerminateProcess(handle, 1)
+        }),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("{} is not supported on Windows", sigid),
+        )),
+    }
+}
+
+/// Send `CTRL_BREAK_EVENT` to the console process group `pgid` for `Interrupt` and
+/// `Term`, other signals fail with `ErrorKind::Unsupported`. A process started with
+/// `CREATE_NEW_PROCESS_GROUP` leads a group whose ID is its pid
+#[cfg(target_os = "windows")]
+pub(crate) fn signal_process_group(pgid: usize, sigid: Signal) -> std::io::Result<()> {
+    match sigid {
+        Signal::Interrupt | Signal::Term => console_ctrl_break(pgid),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("{} is not supported for process groups on Windows", sigid),
+        )),
     }
 }
 
+/// The process group of `pid`
+#[cfg(target_os = "windows")]
+pub(crate) fn process_group(pid: usize) -> std::io::Result<usize> {
+    Ok(pid)
+}
+
+/// The `libc` number of `sigid`
 #[cfg(not(windows))]
-pub fn signal(pid: usize, sigid: Signal) -> bool {
-    let mut s = System::new();
-    s.refresh_processes();
-    if let Some(process) = s.process(Pid::from(pid)) {
-        tracing::debug!("sending signal {} to process {}", sigid, pid);
-        if let Some(res) = process.kill_with(sigid) {
-            tracing::debug!("success");
-            return res;
+fn signal_number(sigid: Signal) -> std::io::Result<libc::c_int> {
+    let number = match sigid {
+        Signal::Hangup => libc::SIGHUP,
+        Signal::Interrupt => libc::SIGINT,
+        Signal::Quit => libc::SIGQUIT,
+        Signal::Illegal => libc::SIGILL,
+        Signal::Trap => libc::SIGTRAP,
+        Signal::Abort | Signal::IOT => libc::SIGABRT,
+        Signal::Bus => libc::SIGBUS,
+        Signal::FloatingPointException => libc::SIGFPE,
+        Signal::Kill => libc::SIGKILL,
+        Signal::User1 => libc::SIGUSR1,
+        Signal::Segv => libc::SIGSEGV,
+        Signal::User2 => libc::SIGUSR2,
+        Signal::Pipe => libc::SIGPIPE,
+        Signal::Alarm => libc::SIGALRM,
+        Signal::Term => libc::SIGTERM,
+        Signal::Child => libc::SIGCHLD,
+        Signal::Continue => libc::SIGCONT,
+        Signal::Stop => libc::SIGSTOP,
+        Signal::TSTP => libc::SIGTSTP,
+        Signal::TTIN => libc::SIGTTIN,
+        Signal::TTOU => libc::SIGTTOU,
+        Signal::Urgent => libc::SIGURG,
+        Signal::XCPU => libc::SIGXCPU,
+        Signal::XFSZ => libc::SIGXFSZ,
+        Signal::VirtualAlarm => libc::SIGVTALRM,
+        Signal::Profiling => libc::SIGPROF,
+        Signal::Winch => libc::SIGWINCH,
+        Signal::IO => libc::SIGIO,
+        Signal::Sys => libc::SIGSYS,
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("{} is not supported", sigid),
+            ))
         }
+    };
+    Ok(number)
+}
+
+/// `kill(2)`: a positive `pid` is a process, a negative one a process group
+#[cfg(not(windows))]
+fn kill(pid: libc::pid_t, sigid: Signal) -> std::io::Result<()> {
+    let number = signal_number(sigid)?;
+    if unsafe { libc::kill(pid, number) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// A pid as a `pid_t`. 0 and values out of range would signal more than one process
+#[cfg(not(windows))]
+fn to_pid(pid: usize) -> std::io::Result<libc::pid_t> {
+    match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => Ok(pid),
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid process id {}", pid),
+        )),
+    }
+}
+
+/// Send `sigid` to process with ID `pid`
+#[cfg(not(windows))]
+pub(crate) fn signal(pid: usize, sigid: Signal) -> std::io::Result<()> {
+    tracing::debug!("sending signal {} to process {}", sigid, pid);
+    kill(to_pid(pid)?, sigid)
+}
+
+/// Send `sigid` to every process of process group `pgid`
+#[cfg(not(windows))]
+pub(crate) fn signal_process_group(pgid: usize, sigid: Signal) -> std::io::Result<()> {
+    tracing::debug!("sending signal {} to process group {}", sigid, pgid);
+    kill(-to_pid(pgid)?, sigid)
+}
+
+/// The process group of `pid`
+#[cfg(not(windows))]
+pub(crate) fn process_group(pid: usize) -> std::io::Result<usize> {
+    let pgid = unsafe { libc::getpgid(to_pid(pid)?) };
+    if pgid < 0 {
+        return Err(std::io::Error::last_os_error());
     }
-    return false;
+    Ok(pgid as usize)
 }
 
+pub use backtrace::*;
+pub use breakpoints::*;
+pub use capabilities::*;
+pub use catchpoints::*;
+pub use channels::*;
+pub use corpus::*;
+#[cfg(feature = "dap")]
+pub use dap::*;
+pub use dbg::*;
+pub use debuginfod::*;
+pub use demangle::*;
+pub use download::*;
+pub use dprintf::*;
+pub use entry::*;
+pub use events::*;
+pub use frames::*;
+pub use gdbserver::*;
+pub use inferiors::*;
+pub use launch::*;
+pub use lifecycle::*;
+pub use metrics::*;
+#[cfg(feature = "lldb")]
+pub use lldb::*;
+pub use mock::*;
+pub use modules::*;
+pub use msg::*;
+pub use parser::{
+    parse_line, parse_line_borrowed, parse_line_lenient, MiParser, DEFAULT_MAX_NESTING_DEPTH,
+};
+pub use reload::*;
+pub use remote::*;
+pub use render::*;
+pub use reverse::*;
+pub use rr::*;
+pub use script::*;
+pub use session::*;
+pub use signals::*;
+pub use skip::*;
+pub use state::*;
+pub use stop_bundle::*;
+pub use stream::*;
+pub use supervisor::*;
+pub use symbols::*;
+pub use timeline::*;
+pub use tracepoints::*;
+pub use transcript::*;
+pub use variables::*;
+pub use version::*;
+pub use watchpoints::*;
+
 #[cfg(test)]
 mod tests {
     use super::run_async;
@@ -84,23 +325,19 @@ mod tests {
     fn test_debug_session() {
         tracing_subscriber::fmt::init();
         run_async(async move {
-            let (mut dbg, mut rx) = dbg::Debugger::start().await.unwrap();
+            let (dbg, mut rx) = dbg::Debugger::start().await.unwrap();
             assert!(dbg.can_send_commands());
 
             if let Ok(test_exe) = std::env::var("TEST_EXE") {
                 // load the executable
                 let test_exe = test_exe.replace("\\", "/");
                 dbg.send_cmd_raw(&format!(r#"-file-exec-and-symbols "{test_exe}""#))
-                    .await;
-
-                let resp = dbg.read_result_record(&mut rx).await;
-                assert_eq!(msg::ResultClass::Done, resp.class);
+                    .await
+                    .unwrap();
+                assertions::expect_result_class(&dbg, &mut rx, msg::ResultClass::Done).await;
 
-                dbg.send_cmd_raw("-exec-run").await;
-                let resp = dbg.read_result_record(&mut rx).await;
-
-                tracing::debug!("{:?}", resp);
-                assert_eq!(msg::ResultClass::Running, resp.class);
+                dbg.send_cmd_raw("-exec-run").await.unwrap();
+                assertions::expect_result_class(&dbg, &mut rx, msg::ResultClass::Running).await;
 
                 // let the process a chance to start
                 tokio::time::sleep(std::time::Duration::from_millis(100)).await;
@@ -109,7 +346,7 @@ mod tests {
                 assert!(!dbg.can_send_commands());
 
                 // we should have the debugiee process id by now
-                assert!(dbg.get_debuggee_pid().is_some(), "no debuggee process id");
+                assert!(dbg.get_debuggee_pid(None).is_some(), "no debuggee process id");
 
                 // interrupt the process
                 assert!(dbg.interrupt());
@@ -148,7 +385,2494 @@ mod tests {
             _ => panic!("wrong type :("),
         };
     }
-}
 
-pub use dbg::*;
-pub use msg::*;
+    #[test]
+    fn parse_error_position() {
+        let failure = |line: &str| match parser::parse_line(line) {
+            Err(Error::ParseError {
+                line: text,
+                offset,
+                expected,
+            }) => {
+                assert_eq!(line.trim_end(), text);
+                Some((offset, expected))
+            }
+            _ => None,
+        };
+        assert_eq!(Some((0, "a record")), failure("garbage\n"));
+        assert_eq!(Some((3, "a result class")), failure("12^42\n"));
+        assert_eq!(Some((1, "an async class")), failure("*123\n"));
+        assert_eq!(Some((12, "a value")), failure("^done,frame=<>\n"));
+        assert_eq!(Some((20, "`,` or `}`")), failure("^done,v={a=\"1\",b=\"2\"]\n"));
+        assert_eq!(Some((5, "`,` or the end of the line")), failure("^done x\n"));
+    }
+
+    #[test]
+    fn parse_lenient() {
+        let line = "*stopped,reason=\"breakpoint-hit\",thread-id=\"1\",frame={addr=<>}\n";
+        assert!(parser::parse_line(line).is_err());
+        let (record, error) = parser::parse_line_lenient(line).unwrap();
+        let Record::Async(AsyncRecord::Exec(stopped)) = record else {
+            panic!("wrong type :(");
+        };
+        assert_eq!(2, stopped.content.len());
+        assert_eq!(Some("1"), stopped.get("thread-id").and_then(Value::unquoted).as_deref());
+        assert!(matches!(error, Some(Error::ParseError { offset: 59, .. })));
+
+        let (_, error) = parser::parse_line_lenient("^done,value=\"1\"\n").unwrap();
+        assert!(error.is_none());
+        assert!(parser::parse_line_lenient("garbage\n").is_err());
+    }
+
+    #[test]
+    fn unknown_classes() {
+        let line = "7^accepted,id=\"1\"\n";
+        let Ok(Record::Result(result)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        assert_eq!(ResultClass::Unknown("accepted".to_string()), result.class);
+        assert_eq!(line.trim_end(), result.to_string());
+        assert_eq!("accepted", Record::Result(result).to_json()["class"]);
+
+        let line = "=cmd-param-changed,param=\"print pretty\",value=\"on\"\n";
+        let Ok(Record::Async(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let AsyncRecord::Notify(notify) = &record else {
+            panic!("wrong type :(");
+        };
+        assert_eq!(AsyncClass::Unknown("cmd-param-changed".to_string()), notify.class);
+        assert_eq!(line.trim_end(), record.to_string());
+    }
+
+    #[test]
+    fn nested_values() {
+        let line = "^done,stack=[frame={level=\"0\",args=[]},frame={level=\"1\",args=[{name=\"x\",value=\"{1, 2}\"}]}]\n";
+        let Ok(msg::Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let Some(Value::ResultList(frames)) = record.get("stack") else {
+            panic!("stack is not a tuple list");
+        };
+        assert_eq!(2, frames.len());
+        assert_eq!(Some(0), record.lookup("stack.frame.level").and_then(Value::as_u64));
+        assert_eq!(2, record.get("stack").unwrap().iter_tuples().count());
+        assert_eq!(line.trim_end(), record.to_string());
+        let json = Record::Result(record).to_json();
+        assert_eq!("done", json["class"]);
+        assert_eq!("{1, 2}", json["results"]["stack"]["frame"][1]["args"][0]["value"]);
+
+        let nested = |depth| format!("^done,v={}{}\n", "[".repeat(depth), "]".repeat(depth));
+        assert!(parser::parse_line(&nested(100)).is_ok());
+        assert!(matches!(
+            parser::parse_line(&nested(DEFAULT_MAX_NESTING_DEPTH + 1)),
+            Err(Error::TooDeep)
+        ));
+    }
+
+    /// MI values built from the grammar, with their text
+    fn mi_value() -> impl proptest::strategy::Strategy<Value = String> {
+        use proptest::prelude::*;
+        let name = "[a-z][a-z0-9_-]{0,8}";
+        let leaf = "[a-zA-Z0-9 ,.={}]{0,12}".prop_map(|s| format!("\"{}\"", s));
+        leaf.prop_recursive(8, 64, 6, move |inner| {
+            prop_oneof![
+                prop::collection::vec((name, inner.clone()), 0..6).prop_map(|vars| {
+                    let vars: Vec<_> = vars.iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+                    format!("{{{}}}", vars.join(","))
+                }),
+                prop::collection::vec(inner, 0..6)
+                    .prop_map(|values| format!("[{}]", values.join(","))),
+            ]
+        })
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn parse_arbitrary_bytes(
+            data in proptest::collection::vec(proptest::num::u8::ANY, 0..256),
+        ) {
+            let mut line = String::from_utf8_lossy(&data).into_owned();
+            line.push('\n');
+            let _ = parser::parse_line(&line);
+            let _ = parser::parse_line_borrowed(&line);
+            MiParser::new().feed(&data);
+        }
+
+        #[test]
+        fn parse_generated_records(
+            prefix in "[0-9]{0,4}(\\^done|\\^error|\\*stopped|=thread-created|\\+download)",
+            vars in proptest::collection::vec(("[a-z][a-z0-9_-]{0,8}", mi_value()), 0..4),
+        ) {
+            let mut line = prefix;
+            for (name, value) in &vars {
+                line.push_str(&format!(",{}={}", name, value));
+            }
+            let record = parser::parse_line(&format!("{}\n", line));
+            proptest::prop_assert!(record.is_ok(), "failed to parse {}", line);
+        }
+
+        #[test]
+        fn parse_mutated_records(
+            value in mi_value(),
+            cut in proptest::num::usize::ANY,
+            insert in "[\\[\\]{}\",=\\\\a]{0,3}",
+        ) {
+            // break the grammar at a random spot
+            let mut line = format!("^done,v={}", value);
+            let mut at = cut % (line.len() + 1);
+            while !line.is_char_boundary(at) {
+                at -= 1;
+            }
+            line.insert_str(at, &insert);
+            line.push('\n');
+            let _ = parser::parse_line(&line);
+        }
+
+        #[test]
+        fn parse_deep_nesting(depth in 0usize..4096, open in "[\\[{]") {
+            let close = if open == "[" { "]" } else { "}" };
+            let line = format!("^done,v={}{}\n", open.repeat(depth), close.repeat(depth));
+            let record = parser::parse_line(&line);
+            if depth > DEFAULT_MAX_NESTING_DEPTH {
+                proptest::prop_assert!(matches!(record, Err(Error::TooDeep)));
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_and_anonymous_fields() {
+        let line = "^done,BreakpointTable={body=[bkpt={number=\"1\",addr=\"<MULTIPLE>\"},{number=\"1.1\"},{number=\"1.2\"},bkpt={number=\"2\"}]}\n";
+        let Ok(msg::Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        assert_eq!(line.trim_end(), record.to_string());
+        let body = record.lookup("BreakpointTable.body").unwrap();
+        let numbers = |name| {
+            body.get_all(name)
+                .filter_map(|bkpt| bkpt.get("number").and_then(Value::unquoted))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["1", "2"], numbers("bkpt"));
+        assert_eq!(vec!["1.1", "1.2"], numbers(""));
+        assert_eq!(4, body.iter_tuples().count());
+
+        // a list starting with anonymous values
+        let line = "^done,v=[{a=\"1\"},x={a=\"2\"},x=\"3\"]\n";
+        let Ok(msg::Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        assert_eq!(line.trim_end(), record.to_string());
+        assert_eq!(2, record.get("v").unwrap().get_all("x").count());
+        let line = "=thread-group-added,id=\"i1\",id=\"i2\"\n";
+        let Ok(msg::Record::Async(AsyncRecord::Notify(record))) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        assert_eq!(2, record.get_all("id").count());
+    }
+
+    #[test]
+    fn parse_error_capture() {
+        let failures = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collected = failures.clone();
+        let sink: Box<dyn ParseErrorSink> =
+            Box::new(move |f: &ParseFailure| collected.lock().unwrap().push(f.clone()));
+        let mut capture = corpus::ParseErrorCapture::new(std::sync::Arc::new(
+            std::sync::Mutex::new(Some(sink)),
+        ));
+        capture.on_line("~\"GNU gdb (GDB) 13.1\\n\"", true);
+        capture.on_line("^done", true);
+        capture.on_line("^done,weird=<>", false);
+
+        let failures = failures.lock().unwrap();
+        assert_eq!(1, failures.len());
+        assert_eq!(Some("GNU gdb (GDB) 13.1"), failures[0].gdb_version.as_deref());
+        assert_eq!(2, failures[0].context.len());
+    }
+
+    #[test]
+    fn split_channels_independent() {
+        run_async(async {
+            let (sender, rx) = tokio::sync::mpsc::channel(100);
+            let mut channels = DebuggerChannels::split(rx);
+            tokio::spawn(async move {
+                // nobody reads the stream channel, the result must get through anyway
+                for _ in 0..300 {
+                    let line = parser::parse_line("~\"noise\"\n").unwrap();
+                    sender.send(line).await.unwrap();
+                }
+                sender.send(parser::parse_line("^done\n").unwrap()).await.unwrap();
+            });
+            let record = tokio::time::timeout(
+                std::time::Duration::from_secs(5),
+                channels.results.recv(),
+            )
+            .await;
+            assert!(matches!(record, Ok(Some(Record::Result(_)))));
+        });
+    }
+
+    #[test]
+    fn download_progress() {
+        let line = "+download,{section=\".text\",section-sent=\"512\",section-size=\"6668\",total-sent=\"2470\",total-size=\"9880\"}\n";
+        let Ok(Record::Async(AsyncRecord::Status(record))) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let progress = DownloadProgress::from_record(&record).unwrap();
+        assert_eq!(".text", progress.section);
+        assert_eq!(25.0, progress.percent());
+        assert_eq!(line.trim_end(), AsyncRecord::Status(record).to_string());
+    }
+
+    #[test]
+    fn frame_variables() {
+        let line = "^done,variables=[{name=\"argc\",arg=\"1\",type=\"int\",value=\"1\"},{name=\"p\",type=\"struct point\"},{name=\"i\",type=\"int\",value=\"42\"}]\n";
+        let Ok(Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let locals = variables::variable_values(record.get("variables"), true);
+        assert_eq!(2, locals.len());
+        assert_eq!("p", locals[0].name);
+        assert_eq!(Some("struct point".to_string()), locals[0].type_name);
+        assert_eq!(None, locals[0].value);
+        assert_eq!(Some("42".to_string()), locals[1].value);
+
+        let line = "^done,stack-args=[frame={level=\"0\",args=[name=\"argc\",name=\"argv\"]}]\n";
+        let Ok(Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let args = variables::variable_values(record.lookup("stack-args.frame.args"), false);
+        let names: Vec<_> = args.iter().map(|arg| arg.name.as_str()).collect();
+        assert_eq!(vec!["argc", "argv"], names);
+    }
+
+    #[test]
+    fn register_values() {
+        let names: Vec<String> = ["rax", "rbx", ""].iter().map(|s| s.to_string()).collect();
+        let line = "^done,register-values=[{number=\"0\",value=\"4195638\"},{number=\"1\",value=\"0\"},{number=\"2\",value=\"1\"}]\n";
+        let Ok(Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let registers = frames::register_values(record.get("register-values"), &names);
+        assert_eq!(2, registers.len());
+        assert_eq!("rax", registers[0].name);
+        assert_eq!("4195638", registers[0].value);
+        assert_eq!(1, registers[1].number);
+    }
+
+    #[test]
+    fn timeline_trace() {
+        let mut timeline = Timeline::new(DebuggerState::Idle);
+        timeline.on_command("-exec-run\n");
+        let parse = |line| parser::parse_line(line).unwrap();
+        let Record::Result(result) = parse("^running\n") else {
+            panic!("wrong type :(");
+        };
+        timeline.on_result(&result);
+        timeline.on_state(DebuggerState::Running);
+        let Record::Async(AsyncRecord::Exec(stop)) =
+            parse("*stopped,reason=\"breakpoint-hit\",thread-id=\"1\"\n")
+        else {
+            panic!("wrong type :(");
+        };
+        timeline.on_async(&stop);
+        timeline.on_state(DebuggerState::Stopped);
+
+        let names: Vec<_> = timeline.events().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(vec!["-exec-run", "Idle", "stopped", "Running"], names);
+
+        let trace = timeline.to_chrome_trace();
+        let events = trace["traceEvents"].as_array().unwrap();
+        // 3 track names, 4 events and the current state
+        assert_eq!(8, events.len());
+        assert_eq!("running", events[3]["args"]["result"]);
+        assert_eq!("i", events[5]["ph"]);
+        assert_eq!("breakpoint-hit", events[5]["args"]["reason"]);
+        assert_eq!("Stopped", events[7]["name"]);
+    }
+
+    #[test]
+    fn symbol_info() {
+        let line = "^done,symbols={debug=[{filename=\"main.c\",fullname=\"/src/main.c\",symbols=[{line=\"12\",name=\"main\",type=\"int (void)\",description=\"int main(void);\"}]}],nondebug=[{address=\"0x0000000000001000\",name=\"_init\"}]}\n";
+        let Ok(Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let symbols = symbols::parse_symbols(record.get("symbols"));
+        assert_eq!(2, symbols.len());
+        assert_eq!("main", symbols[0].name);
+        assert_eq!(Some(12), symbols[0].line);
+        assert_eq!(Some("/src/main.c".to_string()), symbols[0].fullname);
+        assert_eq!(Some("0x0000000000001000".to_string()), symbols[1].address);
+        assert_eq!(None, symbols[1].file);
+    }
+
+    #[test]
+    fn transcript_ring_buffer() {
+        let mut recorder = SessionRecorder::new(2);
+        recorder.on_command("-exec-run\n");
+        recorder.on_record(&parser::parse_line("^running\n").unwrap());
+        recorder.on_record(&Record::Prompt);
+        let entries = recorder.entries();
+        assert_eq!(2, entries.len());
+        let line = entries[0].to_line();
+        assert!(line.ends_with(" < ^running"), "{}", line);
+        assert!(entries[1].to_line().ends_with(" < (gdb) "));
+    }
+
+    #[test]
+    fn mock_backend() {
+        let backend = MockBackend::new()
+            .on("-break-insert \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\",enabled=\"y\",original-location=\"main\"}"])
+            .on("-exec-run", &["^running", "*running,thread-id=\"all\""])
+            .on("-file-exec-and-symbols \"/bin/true\"", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let bkpt = dbg
+                .insert_breakpoint(&mut rx, "main", &BreakpointOptions::default())
+                .await
+                .unwrap();
+            assert_eq!("1", bkpt.number);
+            assert_eq!(1, dbg.breakpoints().len());
+            assert!(matches!(dbg.run(&mut rx).await, Err(Error::NoExecutable)));
+            assert!(matches!(
+                dbg.continue_(&mut rx).await,
+                Err(Error::InvalidState { .. })
+            ));
+            dbg.load_executable(&mut rx, std::path::Path::new("/bin/true"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            assert!(!dbg.can_send_commands());
+            let Err(Error::GdbError(msg)) = dbg.send_cmd(&mut rx, "-foo").await else {
+                panic!("expected an error");
+            };
+            assert_eq!("mock: unexpected command -foo", msg);
+        });
+
+        let transcript = "1700000000.000 > -interpreter-exec console \"show version\"\n1700000000.001 < ~\"GNU gdb 14.2\\n\"\n1700000000.001 < ^done\n1700000000.001 < (gdb) \n";
+        let backend = MockBackend::from_transcript(transcript);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let version = dbg.console(&mut rx, "show version").await.unwrap();
+            assert_eq!("GNU gdb 14.2\n", version);
+            dbg.send_cmd_raw("7-interpreter-exec console \"show version\"")
+                .await
+                .unwrap();
+            let result = dbg.read_result_record(&mut rx).await.unwrap();
+            assert_eq!(Some(7), result.token);
+        });
+    }
+
+    #[test]
+    fn backtrace_quality() {
+        let line = "^done,stack=[frame={level=\"0\",addr=\"0x401136\",func=\"foo\",file=\"a.c\",line=\"3\"},frame={level=\"1\",addr=\"0x401150\",func=\"main\",file=\"a.c\",line=\"8\"}]\n";
+        let Ok(Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let frames = backtrace::parse_frames(record.get("stack"));
+        assert_eq!(2, frames.len());
+        assert_eq!(BacktraceQuality::EndsAtMain, backtrace::assess(&frames));
+
+        let line = "^done,stack=[frame={level=\"0\",addr=\"0x401136\",func=\"foo\"},frame={level=\"1\",addr=\"0x0000000000001234\",func=\"??\"}]\n";
+        let Ok(Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let frames = backtrace::parse_frames(record.get("stack"));
+        assert_eq!(
+            BacktraceQuality::Suspicious(vec![BacktraceHint::UnknownFunction]),
+            backtrace::assess(&frames)
+        );
+        assert_eq!(
+            vec![BacktraceHint::NoUnwindInfo, BacktraceHint::Stripped],
+            backtrace::section_hints(" [0] 0x1000->0x1100 at 0x1000: .text ALLOC LOAD CODE\n")
+        );
+    }
+
+    #[test]
+    fn attach_event() {
+        let backend = MockBackend::new().on(
+            "-target-attach 4242",
+            &["^done", "*stopped,frame={addr=\"0x7f01\",func=\"poll\"},thread-id=\"1\""],
+        );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let stop = dbg
+                .attach(&mut rx, 4242, &LaunchConfig::default())
+                .await
+                .unwrap();
+            assert_eq!(Some("poll"), stop.frame.and_then(|f| f.func).as_deref());
+            assert_eq!(Some(4242), dbg.get_debuggee_pid(None));
+            let attached = loop {
+                if let Record::Event(event) = dbg.read_message_record(&mut rx).await.unwrap() {
+                    break event;
+                }
+            };
+            assert_eq!(DebuggerEvent::Attached { pid: 4242 }, attached);
+        });
+    }
+
+    #[test]
+    fn quote_roundtrip() {
+        let path = r#"C:\Users\me\"core" dump"#;
+        assert_eq!(path, msg::unquote(&dbg::mi_quote(path)));
+    }
+
+    #[test]
+    fn open_core() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"/bin/app\"", &["^done"])
+            .on(
+                "-interpreter-exec console \"core-file \\\"/tmp/my \\\\\\\"core\\\\\\\"\\\"\"",
+                &["^done"],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.open_core(&mut rx, "/bin/app", "/tmp/my \"core\"").await.unwrap();
+            assert!(dbg.is_post_mortem());
+        });
+    }
+
+    #[test]
+    fn unquote_escapes() {
+        assert_eq!("café \x1b[0m", msg::unquote(r#""caf\303\251 \033[0m""#));
+        assert_eq!("\x1b\x07\x08\x0c\x0b\0", msg::unquote(r#""\e\a\b\f\v\0""#));
+        assert_eq!("say \"hi\" \\ 8", msg::unquote(r#""say \"hi\" \\ \70""#));
+        // not UTF-8
+        assert_eq!("\u{fffd}!", msg::unquote(r#""\377!""#));
+    }
+
+    #[test]
+    fn session_state_tracking() {
+        let mut state = session::SessionState::default();
+        for line in [
+            "=breakpoint-created,bkpt={number=\"1\",enabled=\"y\",file=\"main.c\",line=\"5\",times=\"0\"}\n",
+            "*stopped,reason=\"breakpoint-hit\",thread-id=\"2\",frame={level=\"0\",func=\"main\",line=\"5\"}\n",
+        ] {
+            if let msg::Record::Async(record) = parser::parse_line(line).unwrap() {
+                state.on_async(&record);
+            }
+        }
+        assert_eq!(Some("2".to_string()), state.selected_thread);
+        assert_eq!(Some(0), state.selected_frame);
+        let stop = state.last_stop.unwrap();
+        assert_eq!(Some("breakpoint-hit".to_string()), stop.reason);
+        assert_eq!(Some(5), stop.frame.unwrap().line);
+        assert_eq!(Some("main.c:5".to_string()), state.breakpoints.get("1").unwrap().location());
+    }
+
+    #[test]
+    fn watchpoint_scope() {
+        let mut state = session::SessionState::default();
+        for line in [
+            "=breakpoint-created,bkpt={number=\"3\",type=\"hw watchpoint\",disp=\"keep\",enabled=\"y\",exp=\"total\",times=\"0\"}\n",
+            "*stopped,reason=\"watchpoint-scope\",wpnum=\"3\",frame={func=\"main\"},thread-id=\"1\"\n",
+        ] {
+            let Ok(msg::Record::Async(record)) = parser::parse_line(line) else {
+                panic!("wrong type :(");
+            };
+            if state.breakpoints.get("3").is_some() {
+                let (AsyncRecord::Exec(stop) | AsyncRecord::Status(stop) | AsyncRecord::Notify(stop)) =
+                    &record;
+                assert_eq!(Some("3".to_string()), watchpoints::out_of_scope_watchpoint(stop));
+                assert_eq!(Some("total".to_string()), state.breakpoints.get("3").unwrap().location());
+            }
+            state.on_async(&record);
+        }
+        assert!(state.breakpoints.is_empty());
+    }
+
+    #[test]
+    fn state_transitions() {
+        let mut state = DebuggerState::Starting;
+        let mut states = Vec::new();
+        for line in [
+            "=thread-group-added,id=\"i1\"\n",
+            "^running\n",
+            "*stopped,reason=\"breakpoint-hit\",thread-id=\"1\"\n",
+            "*running,thread-id=\"all\"\n",
+            "*stopped,reason=\"exited-normally\"\n",
+            "^exit\n",
+        ] {
+            state = state.next(&parser::parse_line(line).unwrap());
+            states.push(state);
+        }
+        use DebuggerState::*;
+        assert_eq!(vec![Idle, Running, Stopped, Running, Exited, Dead], states);
+    }
+
+    #[test]
+    fn running_threads() {
+        let mut session = session::SessionState::default();
+        let mut feed = |line| {
+            let Ok(Record::Async(record)) = parser::parse_line(line) else {
+                panic!("wrong type :(");
+            };
+            session.on_async(&record);
+            session.running.clone()
+        };
+        // all-stop
+        let running = feed("*running,thread-id=\"all\"\n");
+        assert!(running.contains("1") && !running.is_partial());
+        let running = feed("*stopped,reason=\"end-stepping-range\",thread-id=\"1\"\n");
+        assert!(!running.contains("1"));
+        // non-stop: threads resume and stop individually
+        let running = feed("*running,thread-id=\"2\"\n");
+        assert!(running.contains("2") && !running.contains("1") && running.is_partial());
+        let running = feed("*stopped,thread-id=\"2\",stopped-threads=[\"2\"]\n");
+        assert!(!running.contains("2") && !running.is_partial());
+        feed("*running,thread-id=\"all\"\n");
+        let running = feed("*stopped,thread-id=\"3\",stopped-threads=[\"3\"]\n");
+        assert!(running.contains("1") && !running.contains("3"));
+    }
+
+    #[test]
+    fn breakpoint_cond_and_script() {
+        let line = "=breakpoint-modified,bkpt={number=\"2\",type=\"breakpoint\",disp=\"del\",enabled=\"y\",cond=\"x > 5\",ignore=\"3\",script={\"silent\",\"print x\"},original-location=\"foo.c:12\"}\n";
+        let Ok(msg::Record::Async(AsyncRecord::Notify(record))) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let bkpt = record.get("bkpt").and_then(Breakpoint::from_value).unwrap();
+        assert_eq!(Some("x > 5".to_string()), bkpt.condition);
+        assert_eq!(3, bkpt.ignore_count);
+        assert_eq!(vec!["silent", "print x"], bkpt.script);
+
+        let options = BreakpointOptions::from(&bkpt);
+        assert_eq!(
+            r#"-break-insert -t -c "x > 5" -i 3 "foo.c:12""#,
+            options.insert_cmd("foo.c:12")
+        );
+    }
+
+    #[test]
+    fn breakpoint_locations() {
+        let line = "^done,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"<MULTIPLE>\",times=\"0\",original-location=\"max\",locations=[{number=\"1.1\",enabled=\"y\",addr=\"0x1139\",func=\"max<int>(int, int)\",file=\"t.cpp\",line=\"3\"},{number=\"1.2\",enabled=\"n\",addr=\"0x1151\",func=\"max<long>(long, long)\",file=\"t.cpp\",line=\"3\"}]}\n";
+        let Ok(msg::Record::Result(record)) = parser::parse_line(line) else {
+            panic!("wrong type :(");
+        };
+        let mut table = BreakpointTable::default();
+        table.insert(
+            record
+                .get("bkpt")
+                .and_then(Breakpoint::from_value)
+                .unwrap(),
+        );
+        let bkpt = table.get("1").unwrap();
+        assert_eq!(2, bkpt.locations.len());
+        assert_eq!(Some(3), bkpt.locations[0].line);
+        assert!(!bkpt.location_by_number("1.2").unwrap().enabled);
+
+        table.get_location_mut("1.2").unwrap().enabled = true;
+        assert!(table.get("1").unwrap().locations[1].enabled);
+        assert!(table.get_location_mut("1.3").is_none());
+    }
+
+    #[test]
+    fn mi_versions() {
+        let backend = GdbBackend::default().mi_version(MiVersion::Mi2);
+        assert_eq!("--interpreter=mi2", backend.mi_version.interpreter());
+        assert_eq!("--interpreter=mi", MiVersion::default().interpreter());
+        assert_eq!(Some(Version::MI3), MiVersion::Mi3.required_gdb());
+
+        // the same breakpoint in MI2 and MI3
+        let mi2 = "^done,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"<MULTIPLE>\",times=\"0\",original-location=\"max\"},{number=\"1.1\",enabled=\"y\",addr=\"0x1139\",func=\"max<int>(int, int)\",file=\"t.cpp\",line=\"3\"},{number=\"1.2\",enabled=\"n\",addr=\"0x1151\",func=\"max<long>(long, long)\",file=\"t.cpp\",line=\"3\"}\n";
+        let mi3 = "^done,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"<MULTIPLE>\",times=\"0\",original-location=\"max\",locations=[{number=\"1.1\",enabled=\"y\",addr=\"0x1139\",func=\"max<int>(int, int)\",file=\"t.cpp\",line=\"3\"},{number=\"1.2\",enabled=\"n\",addr=\"0x1151\",func=\"max<long>(long, long)\",file=\"t.cpp\",line=\"3\"}]}\n";
+        let bkpt = |line| {
+            let Ok(msg::Record::Result(record)) = parser::parse_line(line) else {
+                panic!("wrong type :(");
+            };
+            Breakpoint::from_results(&record.content).unwrap()
+        };
+        assert_eq!(2, bkpt(mi2).locations.len());
+        assert_eq!(bkpt(mi3), bkpt(mi2));
+
+        // MI4 made the script a list
+        let mi4 = "^done,bkpt={number=\"2\",script=[\"silent\",\"print x\"]}\n";
+        assert_eq!(vec!["silent", "print x"], bkpt(mi4).script);
+
+        let backend = MockBackend::new().on("-break-insert \"max\"", &[mi2.trim_end()]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let bkpt = dbg
+                .insert_breakpoint(&mut rx, "max", &BreakpointOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(2, bkpt.locations.len());
+            let table = dbg.breakpoints();
+            assert_eq!(Some(3), table.get("1").unwrap().locations[1].line);
+        });
+    }
+
+    #[test]
+    fn list_breakpoints() {
+        let hdr = "hdr=[{width=\"7\",alignment=\"-1\",col_name=\"number\",colhdr=\"Num\"},{width=\"14\",alignment=\"-1\",col_name=\"type\",colhdr=\"Type\"},{width=\"4\",alignment=\"-1\",col_name=\"disp\",colhdr=\"Disp\"},{width=\"3\",alignment=\"-1\",col_name=\"enabled\",colhdr=\"Enb\"},{width=\"18\",alignment=\"-1\",col_name=\"addr\",colhdr=\"Address\"},{width=\"40\",alignment=\"2\",col_name=\"what\",colhdr=\"What\"}]";
+        let mi2 = format!("^done,BreakpointTable={{nr_rows=\"2\",nr_cols=\"6\",{},body=[bkpt={{number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"<MULTIPLE>\",times=\"2\",original-location=\"max\"}},{{number=\"1.1\",enabled=\"y\",addr=\"0x1139\",func=\"max<int>(int, int)\",file=\"t.cpp\",line=\"3\"}},{{number=\"1.2\",enabled=\"n\",addr=\"0x1151\",func=\"max<long>(long, long)\",file=\"t.cpp\",line=\"3\"}},bkpt={{number=\"2\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"0x1160\",func=\"main\",file=\"t.cpp\",line=\"9\",times=\"1\",original-location=\"main\"}}]}}", hdr);
+        let mi3 = format!("^done,BreakpointTable={{nr_rows=\"2\",nr_cols=\"6\",{},body=[bkpt={{number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"<MULTIPLE>\",times=\"2\",original-location=\"max\",locations=[{{number=\"1.1\",enabled=\"y\",addr=\"0x1139\",func=\"max<int>(int, int)\",file=\"t.cpp\",line=\"3\"}},{{number=\"1.2\",enabled=\"n\",addr=\"0x1151\",func=\"max<long>(long, long)\",file=\"t.cpp\",line=\"3\"}}]}},bkpt={{number=\"2\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"0x1160\",func=\"main\",file=\"t.cpp\",line=\"9\",times=\"1\",original-location=\"main\"}}]}}", hdr);
+        let empty = format!("^done,BreakpointTable={{nr_rows=\"0\",nr_cols=\"6\",{},body=[]}}", hdr);
+
+        for table in [mi2, mi3] {
+            let backend = MockBackend::new()
+                .on("-break-insert \"foo\"", &["^done,bkpt={number=\"3\",type=\"breakpoint\"}"])
+                .on("-break-list", &[table.as_str()]);
+            run_async(async move {
+                let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+                dbg.insert_breakpoint(&mut rx, "foo", &BreakpointOptions::default())
+                    .await
+                    .unwrap();
+                let breakpoints = dbg.list_breakpoints(&mut rx).await.unwrap();
+                let numbers: Vec<_> = breakpoints.iter().map(|b| b.number.as_str()).collect();
+                assert_eq!(vec!["1", "2"], numbers);
+                assert_eq!(Some("<MULTIPLE>"), breakpoints[0].addr.as_deref());
+                assert_eq!(2, breakpoints[0].hit_count);
+                let locations = &breakpoints[0].locations;
+                let numbers: Vec<_> = locations.iter().map(|l| l.number.as_str()).collect();
+                assert_eq!(vec!["1.1", "1.2"], numbers);
+                assert!(!locations[1].enabled);
+                assert!(breakpoints[1].locations.is_empty());
+                assert_eq!(Some(9), breakpoints[1].line);
+
+                // breakpoint 3 was deleted behind our back
+                let table = dbg.breakpoints();
+                assert!(table.get("3").is_none());
+                assert_eq!(2, table.get("1").unwrap().locations.len());
+            });
+        }
+
+        let backend = MockBackend::new().on("-break-list", &[empty.as_str()]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            assert!(dbg.list_breakpoints(&mut rx).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn tracepoints() {
+        let options = TracepointOptions {
+            fast: true,
+            condition: Some("n > 3".to_string()),
+            pass_count: Some(10),
+            actions: vec!["collect $regs".to_string(), "teval $hits++".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            "-break-insert -a -h -c \"n > 3\" \"foo.c:12\"",
+            options.insert_cmd("foo.c:12")
+        );
+        let backend = MockBackend::new()
+            .on("-trace-define-variable $hits 0", &["^done"])
+            .on(
+                "-break-insert -a -h -c \"n > 3\" \"foo.c:12\"",
+                &["^done,bkpt={number=\"1\",type=\"fast tracepoint\",disp=\"keep\",enabled=\"y\",addr=\"0x1139\",func=\"foo\",file=\"foo.c\",line=\"12\",cond=\"n > 3\",times=\"0\",pass=\"0\",original-location=\"foo.c:12\"}"],
+            )
+            .on("-break-passcount 1 10", &["^done"])
+            .on("-break-commands 1 \"collect $regs\" \"teval $hits++\"", &["^done"])
+            .on("-trace-start", &["^done"])
+            .on(
+                "-trace-status",
+                &["^done,supported=\"1\",running=\"1\",frames=\"2\",frames-created=\"2\",buffer-size=\"5242880\",buffer-free=\"5242000\",disconnected=\"0\",circular=\"0\""],
+            )
+            .on(
+                "-trace-stop",
+                &["^done,supported=\"1\",running=\"0\",stop-reason=\"request\",frames=\"2\",frames-created=\"2\",circular=\"0\",disconnected=\"0\""],
+            )
+            .on(
+                "-trace-find frame-number 0",
+                &["^done,found=\"1\",tracepoint=\"1\",traceframe=\"0\",frame={level=\"0\",addr=\"0x1139\",func=\"foo\",args=[],file=\"foo.c\",fullname=\"/src/foo.c\",line=\"12\",arch=\"i386:x86-64\"}"],
+            )
+            .on("-trace-find tracepoint-number 1", &["^done,found=\"0\""])
+            .on("-trace-find none", &["^done,found=\"0\""]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.define_trace_variable(&mut rx, "hits", Some(0)).await.unwrap();
+            let tpt = dbg
+                .insert_tracepoint(&mut rx, "foo.c:12", &options)
+                .await
+                .unwrap();
+            assert_eq!(Some("fast tracepoint"), tpt.kind.as_deref());
+            assert_eq!(10, tpt.pass_count);
+            assert_eq!(tpt, *dbg.breakpoints().get("1").unwrap());
+            assert_eq!(options, TracepointOptions::from(&tpt));
+
+            dbg.trace_start(&mut rx).await.unwrap();
+            let status = dbg.trace_status(&mut rx).await.unwrap();
+            assert!(status.supported && status.running);
+            assert_eq!(2, status.frames);
+            assert_eq!(Some(5242000), status.buffer_free);
+            let status = dbg.trace_stop(&mut rx).await.unwrap();
+            assert!(!status.running);
+            assert_eq!(Some("request"), status.stop_reason.as_deref());
+
+            let frame = dbg
+                .trace_find(&mut rx, &TraceFind::Frame(0))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(0, frame.number);
+            assert_eq!("1", frame.tracepoint);
+            assert_eq!(Some(12), frame.frame.unwrap().line);
+            let find = TraceFind::Tracepoint("1".to_string());
+            assert_eq!(None, dbg.trace_find(&mut rx, &find).await.unwrap());
+            assert_eq!(None, dbg.trace_find(&mut rx, &TraceFind::None).await.unwrap());
+        });
+    }
+
+    #[test]
+    fn reverse_debugging() {
+        let line = "2\t=> 0x00000000004004ed <main+11>:\tjmp    0x4004f6 <main+20>";
+        let insn = RecordedInstruction::from_line(line).unwrap();
+        assert_eq!(2, insn.number);
+        assert!(insn.current);
+        assert_eq!("0x00000000004004ed", insn.addr);
+        assert_eq!(Some("main+11"), insn.symbol.as_deref());
+        assert_eq!("jmp    0x4004f6 <main+20>", insn.instruction);
+
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"/bin/true\"", &["^done"])
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "*stopped,reason=\"breakpoint-hit\",thread-id=\"1\"",
+                ],
+            )
+            .on("-list-features", &["^done,features=[\"exec-run-start-option\"]"])
+            .on("-list-target-features", &["^done,features=[\"async\"]"])
+            .on("-list-target-features", &["^done,features=[\"async\",\"reverse\"]"])
+            .on("-interpreter-exec console \"record btrace\"", &["^done"])
+            .on(
+                "-exec-continue --reverse",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "*stopped,reason=\"no-history\",thread-id=\"1\"",
+                ],
+            )
+            .on(
+                "-interpreter-exec console \"record instruction-history 1,2\"",
+                &[
+                    "~\"1\\t   0x00000000004004e6 <main+4>:\\tmovl   $0x0,-0x4(%rbp)\\n\"",
+                    "~\"2\\t=> 0x00000000004004ed <main+11>:\\tjmp    0x4004f6 <main+20>\\n\"",
+                    "^done",
+                ],
+            )
+            .on(
+                "-interpreter-exec console \"record function-call-history /l\"",
+                &["~\"1\\tmain\\tat t.c:3,5\\n\"", "~\"2\\tfoo\\tat t.c:9\\n\"", "^done"],
+            )
+            .on("-interpreter-exec console \"record stop\"", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            assert!(!dbg.capabilities().unwrap().reverse);
+            dbg.load_executable(&mut rx, std::path::Path::new("/bin/true"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            dbg.wait_for_stop(&mut rx).await.unwrap();
+            dbg.record_start(&mut rx, RecordMethod::Btrace).await.unwrap();
+            assert!(dbg.capabilities().unwrap().reverse);
+
+            dbg.reverse_continue(&mut rx).await.unwrap();
+            let stop = dbg.wait_for_stop(&mut rx).await.unwrap();
+            assert_eq!(Some("no-history"), stop.reason.as_deref());
+
+            let history = dbg.instruction_history(&mut rx, Some(1..=2)).await.unwrap();
+            assert_eq!(2, history.len());
+            assert!(!history[0].current && history[1].current);
+            let calls = dbg.function_call_history(&mut rx, None).await.unwrap();
+            assert_eq!(
+                vec![
+                    RecordedCall {
+                        number: 1,
+                        function: "main".to_string(),
+                        source: Some("t.c:3,5".to_string()),
+                    },
+                    RecordedCall {
+                        number: 2,
+                        function: "foo".to_string(),
+                        source: Some("t.c:9".to_string()),
+                    },
+                ],
+                calls
+            );
+            dbg.record_stop(&mut rx).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn catchpoints() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"/bin/true\"", &["^done"])
+            .on(
+                "-catch-throw -r \"std::.*\"",
+                &["^done,bkpt={number=\"1\",type=\"catchpoint\",disp=\"keep\",enabled=\"y\",what=\"exception throw\",catch-type=\"throw\",regexp=\"std::.*\",times=\"0\"}"],
+            )
+            .on(
+                "-interpreter-exec console \"catch syscall openat 1\"",
+                &[
+                    "=breakpoint-created,bkpt={number=\"2\",type=\"catchpoint\",disp=\"keep\",enabled=\"y\",what=\"syscalls \\\"openat, write\\\"\",catch-type=\"syscall\",times=\"0\"}",
+                    "^done",
+                ],
+            )
+            .on(
+                "-interpreter-exec console \"catch signal SIGUSR1\"",
+                &[
+                    "=breakpoint-created,bkpt={number=\"3\",type=\"catchpoint\",disp=\"keep\",enabled=\"y\",what=\"SIGUSR1\",catch-type=\"signal\",times=\"0\"}",
+                    "^done",
+                ],
+            )
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "~\"\\nCatchpoint 1 (exception thrown), \"",
+                    "*stopped,reason=\"breakpoint-hit\",disp=\"keep\",bkptno=\"1\",frame={level=\"0\",func=\"__cxa_throw\"},thread-id=\"1\"",
+                ],
+            )
+            .on(
+                "-exec-continue",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "*stopped,reason=\"syscall-entry\",bkptno=\"2\",syscall-number=\"257\",syscall-name=\"openat\",frame={level=\"0\"},thread-id=\"1\"",
+                ],
+            )
+            .on(
+                "-exec-continue",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "~\"\\nCatchpoint 3 (signal SIGUSR1), \"",
+                    "*stopped,frame={level=\"0\",func=\"raise\"},thread-id=\"1\"",
+                ],
+            )
+            .on(
+                "-exec-continue",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "*stopped,reason=\"fork\",disp=\"keep\",bkptno=\"4\",newpid=\"4242\",frame={level=\"0\"},thread-id=\"1\"",
+                ],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let throw = CatchEvent::Throw(Some("std::.*".to_string()));
+            let catchpoint = dbg.insert_catchpoint(&mut rx, &throw).await.unwrap();
+            assert_eq!(Some("throw"), catchpoint.catch_type.as_deref());
+            let syscalls = CatchEvent::Syscall(vec!["openat".to_string(), "1".to_string()]);
+            let catchpoint = dbg.insert_catchpoint(&mut rx, &syscalls).await.unwrap();
+            assert_eq!("2", catchpoint.number);
+            let signal = CatchEvent::Signal(vec!["SIGUSR1".to_string()]);
+            assert_eq!("3", dbg.insert_catchpoint(&mut rx, &signal).await.unwrap().number);
+
+            dbg.load_executable(&mut rx, std::path::Path::new("/bin/true"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            let stop = dbg.wait_for_stop(&mut rx).await.unwrap();
+            let exception = CatchpointHit::Exception("throw".to_string());
+            assert_eq!(Some(exception), stop.catchpoint);
+            dbg.continue_(&mut rx).await.unwrap();
+            let stop = dbg.wait_for_stop(&mut rx).await.unwrap();
+            let syscall = CatchpointHit::Syscall {
+                returned: false,
+                name: Some("openat".to_string()),
+                number: Some(257),
+            };
+            assert_eq!(Some(syscall), stop.catchpoint);
+            dbg.continue_(&mut rx).await.unwrap();
+            let stop = dbg.wait_for_stop(&mut rx).await.unwrap();
+            assert_eq!(Some("3"), stop.breakpoint.as_deref());
+            let signal = CatchpointHit::Signal {
+                name: Some("SIGUSR1".to_string()),
+            };
+            assert_eq!(Some(signal), stop.catchpoint);
+            dbg.continue_(&mut rx).await.unwrap();
+            let stop = dbg.wait_for_stop(&mut rx).await.unwrap();
+            let fork = CatchpointHit::Fork {
+                child_pid: Some(4242),
+            };
+            assert_eq!(Some(fork), stop.catchpoint);
+        });
+
+        // gdb 8.0 has no `-catch-catch`
+        let backend = MockBackend::new()
+            .banner(&["~\"GNU gdb (GDB) 8.0\\n\""])
+            .on(
+                "-interpreter-exec console \"catch catch\"",
+                &[
+                    "=breakpoint-created,bkpt={number=\"1\",type=\"catchpoint\",disp=\"keep\",enabled=\"y\",what=\"exception catch\",catch-type=\"catch\",times=\"0\"}",
+                    "^done",
+                ],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let catch = CatchEvent::Catch(None);
+            assert_eq!("1", dbg.insert_catchpoint(&mut rx, &catch).await.unwrap().number);
+        });
+    }
+
+    #[test]
+    fn dprintf() {
+        let bkpt = Breakpoint {
+            number: "2".to_string(),
+            kind: Some("dprintf".to_string()),
+            script: vec![
+                "call (void) fprintf (stderr,\"x=%d, s=%s\\n\",f(a, b),\"s,t\")".to_string(),
+            ],
+            ..Default::default()
+        };
+        let (format, args) = bkpt.dprintf_format().unwrap();
+        assert_eq!("x=%d, s=%s\n", format);
+        assert_eq!(vec!["f(a, b)", "\"s,t\""], args);
+
+        let backend = MockBackend::new()
+            .on("-gdb-set dprintf-function fprintf", &["^done"])
+            .on("-gdb-set dprintf-channel stderr", &["^done"])
+            .on("-gdb-set dprintf-style call", &["^done"])
+            .on(
+                "-dprintf-insert -c \"n > 1\" \"foo.c:25\" \"At foo entry %d\\n\" \"n\"",
+                &["^done,bkpt={number=\"1\",type=\"dprintf\",disp=\"keep\",enabled=\"y\",addr=\"0x40055d\",func=\"foo\",file=\"foo.c\",line=\"25\",cond=\"n > 1\",times=\"0\",script={\"printf \\\"At foo entry %d\\\\n\\\",n\"},original-location=\"foo.c:25\"}"],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let style = DprintfStyle::Call {
+                function: "fprintf".to_string(),
+                channel: Some("stderr".to_string()),
+            };
+            dbg.set_dprintf_style(&mut rx, &style).await.unwrap();
+            let options = BreakpointOptions {
+                condition: Some("n > 1".to_string()),
+                ..Default::default()
+            };
+            let args = ["n".to_string()];
+            let bkpt = dbg
+                .insert_dprintf(&mut rx, "foo.c:25", "At foo entry %d\n", &args, &options)
+                .await
+                .unwrap();
+            assert_eq!(Some("dprintf"), bkpt.kind.as_deref());
+            let format = ("At foo entry %d\n".to_string(), args.to_vec());
+            assert_eq!(Some(format), bkpt.dprintf_format());
+
+            // re-created with `-dprintf-insert`
+            let table = dbg.breakpoints();
+            let results = dbg.restore_breakpoints(&mut rx, &table).await;
+            assert_eq!("1", results[0].as_ref().unwrap().number);
+        });
+    }
+
+    #[test]
+    fn skips() {
+        let info = "~\"Num   Enb Glob File                 RE Function\\n\"";
+        let backend = MockBackend::new()
+            .on(
+                "-interpreter-exec console \"skip -gfi \\\"/usr/include/c++/*\\\"\"",
+                &["~\"File(s) /usr/include/c++/* will be skipped when stepping.\\n\"", "^done"],
+            )
+            .on(
+                "-interpreter-exec console \"skip -fi \\\"main.cpp\\\" -rfu \\\"^std::\\\"\"",
+                &["^done"],
+            )
+            .on(
+                "-interpreter-exec console \"info skip\"",
+                &[
+                    info,
+                    "~\"1     y      y /usr/include/c++/*   n <none>\\n\"",
+                    "^done",
+                ],
+            )
+            .on(
+                "-interpreter-exec console \"info skip\"",
+                &[
+                    info,
+                    "~\"1     y      y /usr/include/c++/*   n <none>\\n\"",
+                    "~\"2     n      n main.cpp             y ^std::\\n\"",
+                    "^done",
+                ],
+            )
+            .on("-interpreter-exec console \"skip delete 1\"", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let spec = SkipSpec::file_glob("/usr/include/c++/*");
+            let skip = dbg.add_skip(&mut rx, &spec).await.unwrap();
+            assert_eq!(1, skip.number);
+            assert_eq!(spec, skip.spec);
+            let spec = SkipSpec {
+                function: Some("^std::".to_string()),
+                function_is_regex: true,
+                ..SkipSpec::file("main.cpp")
+            };
+            let skip = dbg.add_skip(&mut rx, &spec).await.unwrap();
+            assert_eq!(2, skip.number);
+            assert!(!skip.enabled);
+            assert_eq!(spec, skip.spec);
+            assert_eq!(2, dbg.list_skips(&mut rx).await.unwrap().len());
+            dbg.delete_skip(&mut rx, 1).await.unwrap();
+            let empty = SkipSpec::default();
+            let err = dbg.add_skip(&mut rx, &empty).await.unwrap_err();
+            assert!(matches!(err, Error::InvalidArgument(_)));
+        });
+    }
+
+    #[test]
+    fn source_mappings() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let substitute = format!("set substitute-path \\\"/build\\\" \\\"{}\\\"", root);
+        let backend = MockBackend::new()
+            .on(&format!("-interpreter-exec console \"{}\"", substitute), &["^done"])
+            .on(
+                "-interpreter-exec console \"set substitute-path \\\"/old\\\" \\\"/new\\\"\"",
+                &["^done"],
+            )
+            .on("-interpreter-exec console \"unset substitute-path \\\"/old\\\"\"", &["^done"])
+            .on(
+                "-environment-directory -r \"$cdir\" \"/build/src\"",
+                &["^done,source-path=\"$cdir:/build/src:$cwd\""],
+            )
+            .on("-gdb-set sysroot \"/opt/sysroot\"", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.add_substitute_path(&mut rx, "/build", root).await.unwrap();
+            dbg.add_substitute_path(&mut rx, "/old", "/new").await.unwrap();
+            dbg.remove_substitute_path(&mut rx, "/old").await.unwrap();
+            let dirs = ["$cdir".to_string(), "/build/src".to_string()];
+            dbg.set_source_directories(&mut rx, &dirs).await.unwrap();
+            dbg.set_sysroot(&mut rx, "/opt/sysroot").await.unwrap();
+
+            let lib = std::path::Path::new(root).join("src/lib.rs");
+            let frame = Frame {
+                fullname: Some("/build/src/lib.rs".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(Some(lib.clone()), dbg.resolve_frame_source(&frame));
+            // found in a source directory
+            let frame = Frame {
+                file: Some("lib.rs".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(Some(lib), dbg.resolve_frame_source(&frame));
+            let frame = Frame {
+                fullname: Some("/old/lib.rs".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(None, dbg.resolve_frame_source(&frame));
+        });
+    }
+
+    #[test]
+    fn substitute_paths() {
+        use crate::paths::substitute;
+        use std::path::{Path, PathBuf};
+        let rules = [
+            ("/build/".to_string(), "/src/".to_string()),
+            ("C:\\work".to_string(), "/mnt/work".to_string()),
+            ("/".to_string(), "/root".to_string()),
+        ];
+        assert_eq!("/src/a/b.c", substitute("/build/a/b.c", &rules));
+        assert_eq!("/src", substitute("/build", &rules));
+        assert_eq!("/mnt/work\\a.c", substitute("C:\\work\\a.c", &rules));
+        // whole components only
+        assert_eq!("/root/buildx/a.c", substitute("/buildx/a.c", &rules));
+        assert_eq!("C:\\workspace", substitute("C:\\workspace", &rules));
+        assert_eq!("a.c", substitute("a.c", &rules));
+
+        let root = env!("CARGO_MANIFEST_DIR");
+        let backend = MockBackend::new()
+            .on("-environment-pwd", &["^done,cwd=\"/build\""])
+            .on(
+                &format!(
+                    "-interpreter-exec console \"set substitute-path \\\"/build/\\\" \\\"{}/\\\"\"",
+                    root
+                ),
+                &["^done"],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            // no cwd yet, relative paths are kept
+            assert_eq!(PathBuf::from("src/lib.rs"), dbg.resolve_relative_path("src/lib.rs"));
+            dbg.working_directory(&mut rx).await.unwrap();
+            let path = dbg.resolve_relative_path("src/lib.rs");
+            assert_eq!(PathBuf::from("/build/src/lib.rs"), path);
+            dbg.add_substitute_path(&mut rx, "/build/", &format!("{}/", root)).await.unwrap();
+            let lib = Path::new(root).join("src/lib.rs");
+            assert_eq!(lib, dbg.resolve_relative_path("src/lib.rs"));
+            assert_eq!(lib, dbg.resolve_relative_path("/build/src/lib.rs"));
+            assert_eq!(PathBuf::from("/other/lib.rs"), dbg.resolve_relative_path("/other/lib.rs"));
+
+            // `fullname` first, then `file` against the cwd
+            let frame = Frame {
+                file: Some("src/lib.rs".to_string()),
+                fullname: Some("/other/lib.rs".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(Some(lib.clone()), dbg.resolve_frame_source(&frame));
+            let frame = Frame {
+                fullname: Some("/build/src/lib.rs".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(Some(lib), dbg.resolve_frame_source(&frame));
+            let frame = Frame {
+                file: Some("src/missing.rs".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(None, dbg.resolve_frame_source(&frame));
+            assert_eq!(None, dbg.resolve_frame_source(&Frame::default()));
+        });
+    }
+
+    #[test]
+    fn modules() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"/bin/true\"", &["^done"])
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "=library-loaded,id=\"/lib/ld.so\",target-name=\"/lib/ld.so\",host-name=\"/lib/ld.so\",symbols-loaded=\"0\",thread-group=\"i1\",ranges=[{from=\"0x1000\",to=\"0x2000\"}]",
+                    "=library-loaded,id=\"/lib/libfoo.so\",target-name=\"/lib/libfoo.so\",host-name=\"/sysroot/lib/libfoo.so\",symbols-loaded=\"0\",thread-group=\"i1\",low-address=\"0x4000\",high-address=\"0x5000\"",
+                    "=library-unloaded,id=\"/lib/ld.so\",target-name=\"/lib/ld.so\",host-name=\"/lib/ld.so\",thread-group=\"i1\"",
+                ],
+            )
+            .on(
+                "-interpreter-exec console \"sharedlibrary ^/lib/libfoo\\\\.so$\"",
+                &["~\"Reading symbols from /sysroot/lib/libfoo.so...\\n\"", "^done"],
+            )
+            .on(
+                "-file-list-shared-libraries \"^/lib/libfoo\\\\.so$\"",
+                &["^done,shared-libraries=[{id=\"/lib/libfoo.so\",target-name=\"/lib/libfoo.so\",host-name=\"/sysroot/lib/libfoo.so\",symbols-loaded=\"1\",thread-group=\"i1\",ranges=[{from=\"0x4000\",to=\"0x5000\"}]}]"],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("/bin/true"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            let mut events = Vec::new();
+            while events.len() < 3 {
+                if let Record::Event(event) = dbg.read_message_record(&mut rx).await.unwrap() {
+                    events.push(event);
+                }
+            }
+            let DebuggerEvent::LibraryLoaded(ld) = &events[0] else {
+                panic!("unexpected event {:?}", events[0]);
+            };
+            assert_eq!(vec![0x1000..0x2000], ld.ranges);
+            let DebuggerEvent::LibraryUnloaded(ld) = &events[2] else {
+                panic!("unexpected event {:?}", events[2]);
+            };
+            assert_eq!("/lib/ld.so", ld.id);
+
+            let modules = dbg.modules();
+            assert_eq!(1, modules.len());
+            assert_eq!("/sysroot/lib/libfoo.so", modules[0].host_name);
+            assert_eq!(vec![0x4000..0x5000], modules[0].ranges);
+            assert!(modules[0].contains(0x4800) && !modules[0].contains(0x5000));
+            assert!(!modules[0].symbols_loaded);
+
+            let module = dbg.load_symbols_for(&mut rx, "/lib/libfoo.so").await.unwrap();
+            assert!(module.symbols_loaded);
+            assert_eq!(vec![module], dbg.modules());
+        });
+    }
+
+    #[test]
+    fn pending_breakpoints() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"/bin/true\"", &["^done"])
+            .on(
+                "-break-insert -f \"plugin_init\"",
+                &["^done,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"<PENDING>\",pending=\"plugin_init\",times=\"0\",original-location=\"plugin_init\"}"],
+            )
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "=library-loaded,id=\"/lib/libplugin.so\",target-name=\"/lib/libplugin.so\",host-name=\"/lib/libplugin.so\",symbols-loaded=\"0\",thread-group=\"i1\",ranges=[{from=\"0x7000\",to=\"0x8000\"}]",
+                    "=breakpoint-modified,bkpt={number=\"1\",type=\"breakpoint\",disp=\"keep\",enabled=\"y\",addr=\"0x7010\",func=\"plugin_init\",file=\"plugin.c\",fullname=\"/src/plugin.c\",line=\"4\",thread-groups=[\"i1\"],times=\"0\",original-location=\"plugin_init\"}",
+                ],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("/bin/true"))
+                .await
+                .unwrap();
+            let options = BreakpointOptions {
+                pending: true,
+                ..Default::default()
+            };
+            let bkpt = dbg
+                .insert_breakpoint(&mut rx, "plugin_init", &options)
+                .await
+                .unwrap();
+            assert!(bkpt.is_pending());
+            assert_eq!(Some("plugin_init"), bkpt.pending.as_deref());
+            // re-created as pending by `restore_breakpoints()`
+            assert!(BreakpointOptions::from(&bkpt).pending);
+
+            dbg.run(&mut rx).await.unwrap();
+            let bound = loop {
+                if let Record::Event(DebuggerEvent::BreakpointBound(bkpt)) =
+                    dbg.read_message_record(&mut rx).await.unwrap()
+                {
+                    break bkpt;
+                }
+            };
+            assert_eq!("1", bound.number);
+            assert_eq!(Some("0x7010"), bound.addr.as_deref());
+            assert_eq!(Some(4), bound.line);
+            let table = dbg.breakpoints();
+            let bkpt = table.get("1").unwrap();
+            assert!(!bkpt.is_pending());
+            assert_eq!(Some("plugin.c"), bkpt.file.as_deref());
+        });
+    }
+
+    #[test]
+    fn mi_round_trip() {
+        let lines = [
+            "5^done,stack=[frame={level=\"0\",addr=\"0x1139\",func=\"main\"}]",
+            "^done,stack=[frame={level=\"0\"},frame={level=\"1\"}]",
+            "^done,BreakpointTable={nr_rows=\"1\",body=[bkpt={number=\"1\",addr=\"<MULTIPLE>\"},{number=\"1.1\"},{number=\"1.2\"}]}",
+            "^done,shared-libraries=[{id=\"/lib/a.so\"},{id=\"/lib/b.so\"}]",
+            "^done,locals=[name=\"x\"],args=[],frame={},value=\"a \\\"b\\\"\"",
+            "*stopped,reason=\"end-stepping-range\",thread-id=\"1\",stopped-threads=[\"1\"]",
+        ];
+        for line in lines {
+            let record = parser::parse_line(&format!("{}\n", line)).unwrap();
+            assert_eq!(Some(line.to_string()), record.to_mi_string());
+        }
+    }
+
+    #[test]
+    fn breakpoint_condition() {
+        let backend = MockBackend::new()
+            .on("-break-insert \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\",enabled=\"y\",original-location=\"main\"}"])
+            .on("-break-condition 1 \"strcmp(name, \\\"x\\\") == 0\"", &["^done"])
+            .on("-break-condition 1", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.insert_breakpoint(&mut rx, "main", &BreakpointOptions::default())
+                .await
+                .unwrap();
+            let condition = "strcmp(name, \"x\") == 0";
+            dbg.set_breakpoint_condition(&mut rx, "1", condition).await.unwrap();
+            let table = dbg.breakpoints();
+            assert_eq!(Some(condition), table.get("1").unwrap().condition.as_deref());
+            dbg.set_breakpoint_condition(&mut rx, "1", "").await.unwrap();
+            assert_eq!(None, dbg.breakpoints().get("1").unwrap().condition);
+        });
+    }
+
+    #[test]
+    fn until_and_jump() {
+        let stopped = "*stopped,reason=\"end-stepping-range\",thread-id=\"1\"";
+        let resumed = ["^running", "*running,thread-id=\"all\"", stopped];
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-exec-run", &resumed)
+            .on("-exec-until \"\\\"my file.c\\\":10\"", &resumed)
+            .on("-exec-jump \"'ns::f(int)'\"", &resumed);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            dbg.wait_for_stop(&mut rx).await.unwrap();
+            dbg.until(&mut rx, "\"my file.c\":10").await.unwrap();
+            dbg.wait_for_stop(&mut rx).await.unwrap();
+            dbg.jump(&mut rx, "'ns::f(int)'").await.unwrap();
+            dbg.wait_for_stop(&mut rx).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn with_stopped() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-exec-run", &["^running", "*running,thread-id=\"all\""])
+            .on(
+                "-exec-interrupt",
+                &["^done", "*stopped,reason=\"signal-received\",signal-name=\"SIGINT\",thread-id=\"1\""],
+            )
+            .on("-stack-info-depth", &["^done,depth=\"3\""])
+            .on("-exec-continue", &["^running", "*running,thread-id=\"all\""]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let dbg = std::sync::Arc::new(dbg);
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.set_interrupt_strategy(InterruptStrategy::ExecInterrupt);
+            dbg.run(&mut rx).await.unwrap();
+            assert_eq!(DebuggerState::Running, dbg.state());
+            let other = dbg.clone();
+            let (depth, resume) = dbg
+                .with_stopped(&mut rx, async |dbg, rx| {
+                    assert_eq!(DebuggerState::Stopped, dbg.state());
+                    // another task resuming the target waits for the section to end
+                    let resume = tokio::spawn(async move {
+                        let (_sender, mut unused) = tokio::sync::mpsc::channel(1);
+                        other.continue_(&mut unused).await
+                    });
+                    let record = dbg.send_cmd(rx, "-stack-info-depth").await.unwrap();
+                    assert!(!resume.is_finished());
+                    assert!(matches!(
+                        dbg.with_stopped(rx, async |_, _| ()).await,
+                        Err(Error::NestedFence)
+                    ));
+                    (record.get("depth").and_then(Value::as_u64), resume)
+                })
+                .await
+                .unwrap();
+            // the result of -exec-interrupt is not taken for the reply to the query
+            assert_eq!(Some(3), depth);
+            assert_eq!(DebuggerState::Running, dbg.state());
+            // resumed by the section already
+            assert!(matches!(
+                resume.await.unwrap(),
+                Err(Error::InvalidState {
+                    state: DebuggerState::Running,
+                    ..
+                })
+            ));
+        });
+    }
+
+    #[test]
+    fn breakpoint_groups() {
+        let backend = MockBackend::new()
+            .on("-break-insert \"main\"", &[&inserted(1, "main")])
+            .on("-break-insert \"main\"", &[&inserted(3, "main")])
+            .on("-break-insert \"foo.c:3\"", &[&inserted(2, "foo.c:3")])
+            .on("-break-insert \"foo.c:3\"", &[&inserted(4, "foo.c:3")])
+            .on("-break-disable 2 4", &["^done"])
+            .on("-break-delete 1 2 3 4", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            for location in ["main", "foo.c:3"] {
+                dbg.insert_breakpoint(&mut rx, location, &BreakpointOptions::default())
+                    .await
+                    .unwrap();
+            }
+            dbg.add_breakpoint_to_group("init", "1");
+            dbg.add_breakpoint_to_group("init", "2");
+            dbg.add_breakpoint_to_group("io", "2");
+
+            // removed breakpoints leave their groups
+            let mut table = dbg.breakpoints();
+            table.remove("2");
+            assert_eq!(vec!["1"], table.group("init"));
+            assert!(table.group("io").is_empty());
+            assert!(table.groups_of("2").is_empty());
+            assert_eq!(vec!["init", "io"], table.group_names());
+
+            // the re-created breakpoints get new numbers, and the groups of the old ones
+            let table = dbg.breakpoints();
+            let results = dbg.restore_breakpoints(&mut rx, &table).await;
+            assert!(results.iter().all(Result::is_ok));
+            let table = dbg.breakpoints();
+            assert_eq!(vec!["init"], table.groups_of("3"));
+            assert_eq!(vec!["init", "io"], table.groups_of("4"));
+            assert_eq!(vec!["1", "2", "3", "4"], table.group("init"));
+
+            dbg.disable_group(&mut rx, "io").await.unwrap();
+            assert!(!dbg.breakpoints().get("4").unwrap().enabled);
+            assert!(dbg.breakpoints().get("3").unwrap().enabled);
+            dbg.delete_group(&mut rx, "init").await.unwrap();
+            let table = dbg.breakpoints();
+            assert_eq!(0, table.iter().count());
+            assert!(table.group("io").is_empty());
+        });
+    }
+
+    #[test]
+    fn breakpoint_line_shift() {
+        let mut table = BreakpointTable::default();
+        for (number, line) in [("1", 3), ("2", 12), ("3", 20)] {
+            table.insert(Breakpoint {
+                number: number.to_string(),
+                file: Some("foo.c".to_string()),
+                fullname: Some("/src/foo.c".to_string()),
+                line: Some(line),
+                original_location: Some(format!("foo.c:{}", line)),
+                ..Default::default()
+            });
+        }
+        // lines 10..15 replaced with 2 lines
+        table.shift_lines(std::path::Path::new("/src/foo.c"), 10..15, -3);
+        let lines: Vec<_> = table.iter().map(|b| b.location().unwrap()).collect();
+        assert_eq!(vec!["foo.c:3", "foo.c:11", "foo.c:17"], lines);
+
+        // removing more lines than were edited removes the block, nothing wraps around
+        table.shift_lines(std::path::Path::new("/src/foo.c"), 5..12, -100);
+        let lines: Vec<_> = table.iter().map(|b| b.line.unwrap()).collect();
+        assert_eq!(vec![3, 5, 10], lines);
+    }
+
+    #[test]
+    fn value_renderers() {
+        let backend = MockBackend::new()
+            .on(
+                "-var-create  - * \"v\"",
+                &["^done,name=\"var1\",numchild=\"1\",value=\"{...}\",type=\"const std::vector<int> &\""],
+            )
+            .on(
+                "-var-list-children --all-values var1",
+                &["^done,numchild=\"1\",children=[child={name=\"var1.0\",exp=\"0\",numchild=\"0\",value=\"7\",type=\"int\"}]"],
+            )
+            .on("-var-delete var1", &["^done,ndeleted=\"1\""]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.register_renderer("std::map<*>", |_: &serde_json::Value| Some("map".to_string()));
+            dbg.register_renderer("std::vector<*>", |node: &serde_json::Value| {
+                let children = node["children"].as_object()?;
+                let items: Vec<_> = children.values().filter_map(|c| c["value"].as_str()).collect();
+                Some(format!("[{}]", items.join(", ")))
+            });
+            let value = dbg
+                .evaluate(&mut rx, "v", &FrameContext::default())
+                .await
+                .unwrap();
+            assert_eq!("[7]", value);
+        });
+        let mut renderers = render::ValueRenderers::default();
+        renderers.register("*::String", std::sync::Arc::new(|_: &serde_json::Value| None));
+        assert!(renderers.find("alloc::string::String").is_some());
+        assert!(renderers.find("alloc::string::Strings").is_none());
+    }
+
+    #[test]
+    fn gdbserver_args() {
+        let launch = GdbServerTarget::Launch {
+            program: "./app".to_string(),
+            args: vec!["-v".to_string()],
+        };
+        assert_eq!(vec![":2345", "./app", "-v"], GdbServer::new(launch, 2345).args());
+        let attach = GdbServer::new(GdbServerTarget::Attach(42), 2345);
+        assert_eq!(vec!["--attach", ":2345", "42"], attach.args());
+        assert_eq!("localhost:2345", attach.address());
+
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(MockBackend::new()).await.unwrap();
+            let server = GdbServer {
+                program: "/nonexistent/gdbserver".to_string(),
+                ..attach
+            };
+            let result = dbg.start_gdbserver(&mut rx, &server).await;
+            assert!(matches!(result, Err(Error::IOError(_))));
+            assert!(!dbg.is_remote_connected());
+        });
+    }
+
+    #[test]
+    fn debuginfod_downloads() {
+        let started = "Downloading 1.20 MB separate debug info for /lib/libfoo.so...";
+        let download = DebuginfodDownload {
+            kind: "separate debug info".to_string(),
+            target: "/lib/libfoo.so".to_string(),
+        };
+        assert_eq!(
+            Some(DebuginfodEvent::Started(download.clone())),
+            debuginfod::parse_debuginfod_line(started)
+        );
+        let source = "Downloading source file /src/foo.c...";
+        let Some(DebuginfodEvent::Started(source)) = debuginfod::parse_debuginfod_line(source)
+        else {
+            panic!("expected a source download");
+        };
+        assert_eq!(("source file", "/src/foo.c"), (source.kind.as_str(), source.target.as_str()));
+        assert_eq!(None, debuginfod::parse_debuginfod_line("Downloading is fun"));
+
+        let backend = MockBackend::new()
+            .banner(&["~\"Download failed: Connection timed out.  Continuing without separate debug info for /lib/libfoo.so.\\n\""])
+            .on("-interpreter-exec console \"nosharedlibrary\"", &["^done"])
+            .on(
+                "-interpreter-exec console \"sharedlibrary\"",
+                &["~\"Downloading separate debug info for /lib/libfoo.so...\\n\"", "^done"],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let event = loop {
+                if let Record::Event(event) = dbg.read_message_record(&mut rx).await.unwrap() {
+                    break event;
+                }
+            };
+            let DebuggerEvent::Debuginfod(DebuginfodEvent::Failed { reason, .. }) = event else {
+                panic!("expected a failed download");
+            };
+            assert_eq!("Connection timed out", reason);
+            assert_eq!(vec![download.clone()], dbg.failed_debuginfod_downloads());
+            assert!(dbg.retry_debuginfod_downloads(&mut rx, 3).await.unwrap().is_empty());
+            assert_eq!(Some(download), dbg.active_debuginfod_download());
+        });
+    }
+
+    /// The reply of gdb to a `-break-insert` creating breakpoint `number` at `location`
+    fn inserted(number: u32, location: &str) -> String {
+        format!(
+            "^done,bkpt={{number=\"{}\",type=\"breakpoint\",enabled=\"y\",original-location=\"{}\"}}",
+            number, location
+        )
+    }
+
+    /// The commands recorded by the `SessionRecorder` of `dbg`, without their tokens
+    fn sent_commands(dbg: &Debugger) -> Vec<String> {
+        dbg.dump_transcript()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                TranscriptEntry::Command { command, .. } => {
+                    Some(dbg::strip_token(&command).1.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Stop a running session with `policy`, which must send `cmd`, then start a new one
+    fn check_stop_debugging(policy: EndPolicy, cmd: &'static str) {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-exec-run", &["^running", "*running,thread-id=\"all\""])
+            .on(
+                "-exec-interrupt",
+                &["^done", "*stopped,reason=\"signal-received\",signal-name=\"SIGINT\",thread-id=\"1\""],
+            )
+            .on(cmd, &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.set_recorder(Some(SessionRecorder::new(100)));
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.set_interrupt_strategy(InterruptStrategy::ExecInterrupt);
+            dbg.run(&mut rx).await.unwrap();
+            assert_eq!(DebuggerState::Running, dbg.state());
+
+            dbg.stop_debugging(&mut rx, policy).await.unwrap();
+            let commands = sent_commands(&dbg);
+            // `-exec-interrupt` is written by `interrupt()` directly, it is not recorded
+            assert_eq!(vec!["-exec-run", cmd], commands[commands.len() - 2..]);
+            let ended = loop {
+                if let Record::Event(event @ DebuggerEvent::SessionEnded { .. }) =
+                    dbg.read_message_record(&mut rx).await.unwrap()
+                {
+                    break event;
+                }
+            };
+            let DebuggerEvent::SessionEnded { policy: ended, hooks } = ended else {
+                unreachable!();
+            };
+            assert_eq!(policy, ended);
+            assert!(hooks.is_empty());
+
+            // gdb is still there, ready for a new session
+            assert!(dbg.is_alive());
+            assert_eq!(DebuggerState::Idle, dbg.state());
+            assert_eq!(None, dbg.get_debuggee_pid(None));
+            dbg.run(&mut rx).await.unwrap();
+            assert_eq!(DebuggerState::Running, dbg.state());
+        });
+    }
+
+    #[test]
+    fn stop_debugging_kill() {
+        check_stop_debugging(EndPolicy::Kill, "-interpreter-exec console \"kill\"");
+    }
+
+    #[test]
+    fn stop_debugging_detach() {
+        check_stop_debugging(EndPolicy::Detach, "-target-detach");
+    }
+
+    #[test]
+    fn blocking_debugger() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-interpreter-exec console \"show version\"", &["~\"GNU gdb\\n\"", "^done"])
+            .on("-exec-run", &["^running", "*running,thread-id=\"all\""]);
+        // no runtime around: the blocking debugger brings its own, and drops it last
+        let mut dbg = blocking::Debugger::start_with_backend(backend).unwrap();
+        let record = dbg.send_cmd("-file-exec-and-symbols \"app\"").unwrap();
+        assert_eq!(ResultClass::Done, record.class);
+        assert_eq!("GNU gdb\n", dbg.console("show version").unwrap());
+        assert!(matches!(dbg.send_cmd("-exec-next"), Err(Error::GdbError(_))));
+        dbg.run(async |dbg, rx| dbg.run(rx).await).unwrap();
+        assert_eq!(DebuggerState::Running, dbg.run(async |dbg, _| dbg.state()));
+        drop(dbg);
+    }
+
+    #[test]
+    fn reload_executable() {
+        let path = std::env::temp_dir().join(format!("rust-gdb-reload-{}", std::process::id()));
+        std::fs::write(&path, b"v1").unwrap();
+        let load = format!("-file-exec-and-symbols {}", mi_quote(&path.to_string_lossy()));
+        let backend = MockBackend::new()
+            .on(&load, &["^done"])
+            .on("-break-insert \"main\"", &[&inserted(1, "main")])
+            .on("-break-insert \"main\"", &[&inserted(3, "main")])
+            .on("-break-insert \"foo.c:3\"", &[&inserted(2, "foo.c:3")])
+            .on("-break-insert \"foo.c:3\"", &["^error,msg=\"No source file named foo.c.\""])
+            .on("-break-delete", &["^done"]);
+        let file = path.clone();
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.set_recorder(Some(SessionRecorder::new(100)));
+            dbg.load_executable(&mut rx, &file).await.unwrap();
+            for location in ["main", "foo.c:3"] {
+                dbg.insert_breakpoint(&mut rx, location, &BreakpointOptions::default())
+                    .await
+                    .unwrap();
+            }
+
+            // unchanged: nothing is sent
+            let sent = sent_commands(&dbg).len();
+            let report = dbg.reload_executable(&mut rx).await.unwrap();
+            assert!(!report.reloaded);
+            assert_eq!(sent, sent_commands(&dbg).len());
+
+            // rebuilt
+            std::fs::write(&file, b"version 2").unwrap();
+            let report = dbg.reload_executable(&mut rx).await.unwrap();
+            assert!(report.reloaded);
+            assert_eq!(
+                vec![
+                    load.as_str(),
+                    "-break-delete",
+                    "-break-insert \"main\"",
+                    "-break-insert \"foo.c:3\""
+                ],
+                sent_commands(&dbg)[sent..]
+            );
+            let rebound: Vec<_> = report.rebound.iter().map(|b| b.number.as_str()).collect();
+            assert_eq!(vec!["3"], rebound);
+            assert_eq!(1, report.unresolved.len());
+            assert_eq!("2", report.unresolved[0].0.number);
+            assert!(matches!(report.unresolved[0].1, Error::GdbError(_)));
+            let numbers: Vec<_> = dbg.breakpoints().iter().map(|b| b.number.clone()).collect();
+            assert_eq!(vec!["3"], numbers);
+        });
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn script() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-break-insert \"main\"", &[&inserted(1, "main")])
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "*stopped,reason=\"breakpoint-hit\",bkptno=\"1\",frame={func=\"main\",line=\"3\"},thread-id=\"1\"",
+                ],
+            )
+            .on("-data-evaluate-expression \"x\"", &["^done,value=\"42\""])
+            .on("-stack-info-depth", &["^done,depth=\"1\""])
+            .on(
+                "-data-evaluate-expression \"y\"",
+                &["^error,msg=\"No symbol \\\"y\\\" in current context.\""],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.set_recorder(Some(SessionRecorder::new(100)));
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            let outputs = Script::new()
+                .break_at("main")
+                .run()
+                .expect_stop()
+                .eval("x")
+                .cmd("-stack-info-depth")
+                .execute(&dbg, &mut rx)
+                .await
+                .unwrap();
+            assert_eq!(4, outputs.len());
+            assert!(matches!(&outputs[0], ScriptOutput::Breakpoint(bkpt) if bkpt.number == "1"));
+            let ScriptOutput::Stop(stop) = &outputs[1] else {
+                panic!("expected a stop, got {:?}", outputs[1]);
+            };
+            assert_eq!(Some("breakpoint-hit"), stop.reason.as_deref());
+            assert_eq!(Some("1"), stop.breakpoint.as_deref());
+            assert!(matches!(
+                &outputs[2],
+                ScriptOutput::Value { expression, value } if expression == "x" && value == "42"
+            ));
+            let ScriptOutput::Record(record) = &outputs[3] else {
+                panic!("expected a record, got {:?}", outputs[3]);
+            };
+            assert_eq!(Some("1".to_string()), record.get("depth").and_then(Value::unquoted));
+
+            // the steps after the failing one are not run
+            let result = Script::new()
+                .eval("x")
+                .eval("y")
+                .cmd("-stack-info-depth")
+                .execute(&dbg, &mut rx)
+                .await;
+            assert!(matches!(result, Err(Error::GdbError(_))));
+            let commands = sent_commands(&dbg);
+            assert_eq!(
+                vec!["-data-evaluate-expression \"x\"", "-data-evaluate-expression \"y\""],
+                commands[commands.len() - 2..]
+            );
+        });
+    }
+
+    #[test]
+    fn exit_hooks() {
+        let backend = MockBackend::new()
+            .on("-interpreter-exec console \"monitor reset\"", &["^done"])
+            .on("-target-flash-erase", &["^error,msg=\"no flash\""])
+            // never replies
+            .on("-interpreter-exec console \"monitor halt\"", &[])
+            .on("-interpreter-exec console \"monitor resume\"", &["^done"])
+            .on("-interpreter-exec console \"kill\"", &["^done"])
+            .crash_on("-gdb-exit");
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let timeout = std::time::Duration::from_millis(100);
+            for hook in ["monitor reset", "-target-flash-erase", "monitor halt", "monitor resume"]
+            {
+                dbg.add_exit_hook(hook, timeout);
+            }
+            let started = std::time::Instant::now();
+            dbg.close(&mut rx).await.unwrap();
+            assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+            let mut hooks = None;
+            while let Some(record) = rx.recv().await {
+                if let Record::Event(DebuggerEvent::SessionEnded { policy, hooks: h }) = record {
+                    assert_eq!(EndPolicy::Kill, policy);
+                    hooks = Some(h);
+                }
+            }
+            let hooks = hooks.expect("no SessionEnded event");
+            let commands: Vec<_> = hooks.iter().map(|h| h.command.as_str()).collect();
+            assert_eq!(
+                vec!["monitor reset", "-target-flash-erase", "monitor halt", "monitor resume"],
+                commands
+            );
+            assert_eq!(None, hooks[0].error);
+            assert!(hooks[1].error.as_deref().unwrap().contains("no flash"));
+            assert_eq!(Some(Error::Timeout.to_string()), hooks[2].error);
+            // a hook that timed out does not keep the next ones from running
+            assert_eq!(None, hooks[3].error);
+        });
+    }
+
+    #[test]
+    fn extended_remote() {
+        let backend = MockBackend::new()
+            .on("-target-select extended-remote localhost:2345", &["^connected"])
+            .on("-thread-info", &["^done,threads=[]"])
+            .on("-list-thread-groups", &["^done,groups=[{id=\"i1\",type=\"process\"}]"])
+            .on("-gdb-set remote exec-file \"/opt/app\"", &["^done"])
+            .on("-exec-run", &["^running", "*running,thread-id=\"all\""])
+            .on("-interpreter-exec console \"kill\"", &["^done"])
+            .on("-target-disconnect", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.connect_extended_remote(&mut rx, "localhost:2345")
+                .await
+                .unwrap();
+            assert!(dbg.is_extended_remote());
+            dbg.set_remote_exec_file(&mut rx, "/opt/app").await.unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            dbg.restart(&mut rx).await.unwrap();
+            dbg.disconnect_remote(&mut rx).await.unwrap();
+            assert!(!dbg.is_remote_connected());
+            assert_eq!(DebuggerState::Idle, dbg.state());
+            assert!(matches!(
+                dbg.disconnect_remote(&mut rx).await,
+                Err(Error::NotConnected)
+            ));
+        });
+    }
+
+    #[test]
+    fn keepalive_timeout() {
+        // the stub stops answering: the keepalive queries get no result
+        let backend = MockBackend::new()
+            .on("-target-select remote localhost:1234", &["^connected"])
+            .on("-thread-info", &["^done,threads=[]"])
+            .on("-list-thread-groups", &["^done,groups=[]"])
+            .on("-data-read-memory-bytes $pc 1", &[])
+            .on("-data-read-memory $pc x 1 1 1", &[]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.connect_remote(&mut rx, "localhost:1234").await.unwrap();
+            dbg.enable_keepalive(std::time::Duration::from_millis(20));
+            let disconnected = async {
+                loop {
+                    if let Record::Event(DebuggerEvent::TargetDisconnected { reason }) =
+                        dbg.read_message_record(&mut rx).await.unwrap()
+                    {
+                        return reason;
+                    }
+                }
+            };
+            let reason = tokio::time::timeout(std::time::Duration::from_secs(5), disconnected)
+                .await
+                .unwrap();
+            assert_eq!("keepalive query timed out", reason);
+            assert!(!dbg.is_remote_connected());
+            dbg.disable_keepalive();
+        });
+    }
+
+    #[test]
+    fn reconnect() {
+        let backend = MockBackend::new()
+            .on("-target-select remote localhost:1234", &["^connected"])
+            .on("-thread-info", &["^done,threads=[]"])
+            .on("-list-thread-groups", &["^done,groups=[]"])
+            .on("-break-insert \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\",enabled=\"y\",original-location=\"main\"}"])
+            .on("-break-insert \"main\"", &["^done,bkpt={number=\"2\",type=\"breakpoint\",enabled=\"y\",original-location=\"main\"}"])
+            .on("-break-delete", &["^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            assert!(matches!(dbg.reconnect(&mut rx).await, Err(Error::NotConnected)));
+            dbg.connect_remote(&mut rx, "localhost:1234").await.unwrap();
+            dbg.insert_breakpoint(&mut rx, "main", &BreakpointOptions::default())
+                .await
+                .unwrap();
+            dbg.session.lock().unwrap().mark_disconnected();
+            assert!(!dbg.is_remote_connected());
+
+            dbg.reconnect(&mut rx).await.unwrap();
+            assert!(dbg.is_remote_connected());
+            // the breakpoint was deleted and inserted again, gdb gave it a new number
+            let table = dbg.breakpoints();
+            let numbers: Vec<_> = table.iter().map(|b| b.number.as_str()).collect();
+            assert_eq!(vec!["2"], numbers);
+            assert_eq!(Some("main"), table.get("2").unwrap().location().as_deref());
+        });
+    }
+
+    #[test]
+    fn inferior_list() {
+        let backend = MockBackend::new()
+            .on("-gdb-set detach-on-fork off", &["^done"])
+            .on("-add-inferior", &["^done,inferior=\"i2\""])
+            .on("-interpreter-exec console \"inferior 2\"", &["^done"])
+            .on(
+                "-list-thread-groups",
+                &["^done,groups=[{id=\"i1\",type=\"process\",pid=\"4242\",executable=\"/bin/app\",cores=[\"0\"]},{id=\"i2\",type=\"process\",exit-code=\"012\"}]"],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.set_detach_on_fork(&mut rx, false).await.unwrap();
+            let id = dbg.add_inferior(&mut rx).await.unwrap();
+            dbg.select_inferior(&mut rx, &id).await.unwrap();
+            let inferiors = dbg.inferiors(&mut rx).await.unwrap();
+            assert_eq!(2, inferiors.len());
+            assert_eq!(Some(4242), inferiors[0].pid);
+            assert_eq!(Some("/bin/app"), inferiors[0].executable.as_deref());
+            assert_eq!("2", inferiors[1].number());
+            assert_eq!((None, Some(10)), (inferiors[1].pid, inferiors[1].exit_code));
+        });
+    }
+
+    #[test]
+    fn exit_status() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "=thread-group-started,id=\"i1\",pid=\"77\"",
+                    "*running,thread-id=\"all\"",
+                    "=thread-group-exited,id=\"i1\",exit-code=\"03\"",
+                    "*stopped,reason=\"exited\",exit-code=\"03\"",
+                ],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            dbg.wait_for_stop(&mut rx).await.unwrap();
+            assert_eq!(None, dbg.get_debuggee_pid(Some("i1")));
+            let status = dbg.debuggee_exit_status().unwrap();
+            assert_eq!((Some(3), false), (status.code(), status.success()));
+            assert_eq!(None, dbg.get_debuggee_pid(None));
+        });
+
+        let Record::Async(AsyncRecord::Exec(stopped)) =
+            parse_line("*stopped,reason=\"exited-signalled\",signal-name=\"SIGSEGV\"\n").unwrap()
+        else {
+            panic!("expected an exec record");
+        };
+        assert_eq!(
+            Some(ExitStatus::Signalled("SIGSEGV".to_string())),
+            ExitStatus::from_record(&stopped)
+        );
+    }
+
+    #[test]
+    fn inferior_pids() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on(
+                "-exec-run",
+                &[
+                    "^running",
+                    "=thread-group-started,id=\"i1\",pid=\"100\"",
+                    "=thread-created,id=\"1\",group-id=\"i1\"",
+                    "=thread-group-started,id=\"i2\",pid=\"101\"",
+                    "=thread-group-exited,id=\"i1\",exit-code=\"0\"",
+                    "*stopped,reason=\"exited-normally\"",
+                ],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.run(&mut rx).await.unwrap();
+            dbg.wait_for_stop(&mut rx).await.unwrap();
+            assert_eq!(Some(101), dbg.get_debuggee_pid(Some("i2")));
+            assert_eq!(None, dbg.get_debuggee_pid(Some("i1")));
+            // the forked child is still alive
+            assert_eq!(Some(101), dbg.get_debuggee_pid(None));
+        });
+    }
+
+    #[test]
+    fn launch_config() {
+        let args = vec!["-v".to_string(), "two words".to_string(), "it's".to_string()];
+        assert_eq!(
+            r#"-exec-arguments "-v" "'two words'" "'it'\\''s'""#,
+            launch::exec_arguments_cmd(&args)
+        );
+
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"/bin/app\"", &["^done"])
+            .on("-exec-arguments \"--fast\"", &["^done"])
+            .on("-environment-cd \"/tmp\"", &["^done"])
+            .on("-environment-pwd", &["^done,cwd=\"/tmp\""])
+            .on("-interpreter-exec console \"set environment RUST_LOG=debug\"", &["^done"])
+            .on("-exec-run --start", &["^running", "*running,thread-id=\"all\""]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let config = LaunchConfig {
+                executable: Some("/bin/app".into()),
+                args: vec!["--fast".to_string()],
+                cwd: Some("/tmp".to_string()),
+                env: vec![("RUST_LOG".to_string(), "debug".to_string())],
+                stop_at_main: true,
+                ..Default::default()
+            };
+            dbg.launch(&mut rx, &config).await.unwrap();
+            assert_eq!(DebuggerState::Running, dbg.state());
+        });
+    }
+
+    #[test]
+    fn run_to_main() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on(
+                "-exec-run --start",
+                &[
+                    "^running",
+                    "*running,thread-id=\"all\"",
+                    "*stopped,reason=\"breakpoint-hit\",disp=\"del\",bkptno=\"1\",frame={level=\"0\",func=\"main\",file=\"app.c\",line=\"3\"},thread-id=\"1\"",
+                ],
+            );
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            let frame = dbg.run_to_main(&mut rx).await.unwrap();
+            assert_eq!(Some("main"), frame.func.as_deref());
+            assert_eq!(Some(3), frame.line);
+            assert_eq!(DebuggerState::Stopped, dbg.state());
+        });
+    }
+
+    #[test]
+    fn send_signal() {
+        run_async(async move {
+            let (dbg, _rx) = Debugger::start_with_backend(MockBackend::new()).await.unwrap();
+            #[cfg(windows)]
+            assert_eq!(
+                std::io::ErrorKind::Unsupported,
+                dbg.send_signal(DebugSignal::User1).unwrap_err().kind()
+            );
+            // no debuggee yet
+            let error = dbg.send_signal(DebugSignal::User1).unwrap_err();
+            assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+            let error = dbg.send_signal_to_group(DebugSignal::Kill).unwrap_err();
+            assert_eq!(std::io::ErrorKind::NotFound, error.kind());
+        });
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn signal_process_group() {
+        use std::os::unix::process::{CommandExt, ExitStatusExt};
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let pid = child.id() as usize;
+        assert_eq!(pid, crate::process_group(pid).unwrap());
+        crate::signal_process_group(pid, Signal::Kill).unwrap();
+        assert_eq!(Some(libc::SIGKILL), child.wait().unwrap().signal());
+        assert!(crate::signal(pid, Signal::Term).is_err());
+        assert!(crate::signal(0, Signal::Term).is_err());
+    }
+
+    #[test]
+    fn restart_gdb() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-break-insert \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\",enabled=\"y\",original-location=\"main\"}"])
+            .on("-break-list", &["^done,BreakpointTable={}"])
+            .crash_on("crash");
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.insert_breakpoint(&mut rx, "main", &BreakpointOptions::default())
+                .await
+                .unwrap();
+            assert!(dbg.is_alive());
+
+            dbg.send_cmd_raw("crash").await.unwrap();
+            let mut state = dbg.watch_state();
+            state.wait_for(|s| *s == DebuggerState::Dead).await.unwrap();
+            assert!(!dbg.is_alive());
+
+            let report = dbg.restart_gdb(&mut rx).await.unwrap();
+            assert!(dbg.is_alive());
+            assert_eq!(Some(std::path::Path::new("app")), report.executable.as_deref());
+            assert_eq!(1, report.restored.len());
+            assert_eq!(1, dbg.breakpoints().len());
+            loop {
+                if let Record::Event(event) = dbg.read_message_record(&mut rx).await.unwrap() {
+                    let expected = DebuggerEvent::Restarted {
+                        restored: 1,
+                        unresolved: 0,
+                    };
+                    assert_eq!(expected, event);
+                    break;
+                }
+            }
+
+            // supervisor mode
+            dbg.send_cmd_raw("crash").await.unwrap();
+            let mut state = dbg.watch_state();
+            state.wait_for(|s| *s == DebuggerState::Dead).await.unwrap();
+            dbg.set_auto_restart(true);
+            dbg.send_cmd(&mut rx, "-break-list").await.unwrap();
+            assert!(dbg.is_alive());
+            assert_eq!(1, dbg.breakpoints().len());
+        });
+    }
+
+    #[test]
+    fn gdb_exits_during_command() {
+        let backend = MockBackend::new().crash_on("-break-list");
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let result = dbg.send_cmd(&mut rx, "-break-list").await;
+            assert!(matches!(result, Err(Error::ChannelClosed)), "{:?}", result);
+            assert!(!dbg.is_alive());
+            let stop = dbg.wait_for_stop(&mut rx).await;
+            assert!(matches!(stop, Err(Error::ChannelClosed)), "{:?}", stop);
+        });
+    }
+
+    #[test]
+    fn supervisor_restarts_on_crash() {
+        let backend = MockBackend::new().crash_on("crash");
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.set_auto_restart(true);
+            // gdb dies running the command
+            let result = dbg.send_cmd(&mut rx, "crash").await;
+            assert!(matches!(result, Err(Error::ChannelClosed)), "{:?}", result);
+            assert!(dbg.is_alive());
+            loop {
+                if let Record::Event(event) = dbg.read_message_record(&mut rx).await.unwrap() {
+                    let expected = DebuggerEvent::Restarted {
+                        restored: 0,
+                        unresolved: 0,
+                    };
+                    assert_eq!(expected, event);
+                    break;
+                }
+            }
+        });
+
+        // every new gdb dies when the sysroot is set again: give up
+        let backend = MockBackend::new()
+            .crash_on("crash")
+            .crash_on("-gdb-set sysroot \"/sysroot\"");
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.session.lock().unwrap().sysroot = Some("/sysroot".to_string());
+            dbg.set_auto_restart(true);
+            let result = dbg.send_cmd(&mut rx, "crash").await;
+            assert!(matches!(result, Err(Error::ChannelClosed)), "{:?}", result);
+            assert!(!dbg.is_alive());
+        });
+    }
+
+    #[test]
+    fn write_errors() {
+        run_async(async move {
+            let backend = MockBackend::new().crash_on("crash");
+            let (dbg, _rx) = Debugger::start_with_backend(backend).await.unwrap();
+            dbg.send_cmd_raw("crash").await.unwrap();
+            let mut state = dbg.watch_state();
+            state.wait_for(|s| *s == DebuggerState::Dead).await.unwrap();
+            // the write fails in the writer task, the next call reports it
+            dbg.send_cmd_raw("-gdb-version").await.unwrap();
+            let mut failed = false;
+            for _ in 0..100 {
+                if let Err(Error::IOError(_)) = dbg.send_cmd_raw("-gdb-version").await {
+                    failed = true;
+                    break;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+            assert!(failed);
+        });
+    }
+
+    #[test]
+    fn metrics() {
+        let backend = MockBackend::new()
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-interpreter-exec console \"show version\"", &["~\"GNU gdb\\n\"", "^done"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            // `-list-features` and `-gdb-version`, which the mock rejects
+            let started = dbg.metrics();
+            assert_eq!(2, started.commands_sent);
+            assert_eq!(0, started.pending);
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            assert_eq!("GNU gdb\n", dbg.console(&mut rx, "show version").await.unwrap());
+            let metrics = dbg.metrics();
+            assert_eq!(4, metrics.commands_sent);
+            assert_eq!(0, metrics.pending);
+            assert!(crate::metrics::is_result_line("12^done"));
+            assert!(!crate::metrics::is_result_line("4294967295^done"));
+            assert!(!crate::metrics::is_result_line("*stopped"));
+        });
+    }
+
+    #[test]
+    #[cfg(not(windows))]
+    fn startup_failure() {
+        // sh rejects gdb's `--interpreter=mi` and exits
+        let backend = GdbBackend {
+            program: "sh".to_string(),
+            args: Vec::new(),
+            mi_version: MiVersion::Latest,
+            kill_on_host_exit: false,
+        };
+        run_async(async move {
+            let Err(Error::StartupFailed(stderr)) = Debugger::start_with_backend(backend).await
+            else {
+                panic!("expected a startup failure");
+            };
+            assert!(stderr.contains("option"), "{}", stderr);
+        });
+    }
+
+    #[test]
+    fn lossy_lines() {
+        run_async(async move {
+            let long = "x".repeat(100_000);
+            let mut input = b"~\"\xff\xfe\"\r\n".to_vec();
+            input.extend_from_slice(long.as_bytes());
+            input.extend_from_slice(b"\nlast");
+            let mut lines = crate::dbg::LossyLines::new(&input[..]);
+            assert_eq!(
+                Some("~\"\u{fffd}\u{fffd}\"".to_string()),
+                lines.next_line().await.unwrap()
+            );
+            assert_eq!(Some(long), lines.next_line().await.unwrap());
+            assert_eq!(Some("last".to_string()), lines.next_line().await.unwrap());
+            assert_eq!(None, lines.next_line().await.unwrap());
+        });
+    }
+
+    #[test]
+    fn mi_parser() {
+        let mut parser = MiParser::new();
+        assert!(parser.feed(b"~\"GNU g").is_empty());
+        assert_eq!(b"~\"GNU g", parser.pending());
+        let records = parser.feed(b"db\\n\"\r\n12^done,value=\"1\"\n(gdb) \n*stop");
+        assert_eq!(3, records.len());
+        let Record::Stream(msg::StreamRecord::Console(text)) = &records[0] else {
+            panic!("expected a console record");
+        };
+        assert_eq!("GNU gdb\n", msg::unquote(text));
+        let Record::Result(result) = &records[1] else {
+            panic!("expected a result record");
+        };
+        assert_eq!(Some("12".to_string()), result.token_str());
+        assert!(matches!(records[2], Record::Prompt));
+        let records = parser.feed(b"ped,reason=\"end-stepping-range\"\nnot mi\n");
+        assert!(matches!(records[0], Record::Async(_)));
+        assert!(matches!(&records[1], Record::Raw(line) if line == "not mi"));
+        assert!(parser.feed(b"^done").is_empty());
+        assert!(matches!(parser.finish(), Some(Record::Result(_))));
+        assert!(parser.finish().is_none());
+
+        parser.set_max_nesting_depth(1);
+        let records = parser.feed(b"^done,v=[\"1\"]\n^done,v=[[\"1\"]]\n");
+        assert!(matches!(records[0], Record::Result(_)));
+        assert!(matches!(records[1], Record::Raw(_)));
+        // the limit belongs to the parser, other parsers keep the default
+        assert!(parser::parse_line("^done,v=[[\"1\"]]\n").is_ok());
+    }
+
+    #[test]
+    fn debugger_nesting_depth() {
+        let backend =
+            MockBackend::new().on("-stack-list-frames", &["^done,stack=[frame={level=\"0\"}]"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let record = dbg.send_cmd(&mut rx, "-stack-list-frames").await.unwrap();
+            assert!(record.get("stack").is_some());
+            dbg.set_lenient_parsing(true);
+            dbg.set_max_nesting_depth(1);
+            let record = dbg.send_cmd(&mut rx, "-stack-list-frames").await.unwrap();
+            assert!(record.get("stack").is_none());
+        });
+    }
+
+    #[test]
+    fn parse_borrowed() {
+        let line = "5^done,changelist=[{name=\"var1\",value=\"42\"}]\n";
+        let Ok(Record::Result(record)) = parse_line_borrowed(line) else {
+            panic!("expected a result record");
+        };
+        assert_eq!(Some(5), record.token);
+        let Some(Value::ValueList(changes)) = record.content.first().map(|var| &var.value) else {
+            panic!("expected a list");
+        };
+        let Value::VariableList(vars) = &changes[0] else {
+            panic!("expected a tuple");
+        };
+        assert_eq!("name", vars[0].name);
+        assert!(matches!(vars[1].value, Value::String("\"42\"")));
+
+        let owned = parse_line_borrowed(line).unwrap().into_owned();
+        assert_eq!(parse_line(line).unwrap().to_json(), owned.to_json());
+    }
+
+    #[test]
+    fn command_tokens() {
+        let tokens = TokenGenerator::new();
+        assert_eq!((1, "1-exec-next".to_string()), tokens.tag("-exec-next"));
+        assert_eq!((42, "42-exec-next".to_string()), tokens.tag("42-exec-next"));
+        assert_eq!(2, tokens.next_token());
+        assert!(matches!(
+            parser::parse_line("99999999999999999999^done\n"),
+            Err(Error::ParseError { expected: "a token", .. })
+        ));
+
+        let backend = MockBackend::new()
+            .on("-stale", &["^done,value=\"stale\""])
+            .on("-fresh", &["^done,value=\"fresh\""]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            // a result nobody reads must not be taken for the next command's
+            dbg.send_cmd_raw("99-stale").await.unwrap();
+            let record = dbg.send_cmd(&mut rx, "-fresh").await.unwrap();
+            assert_eq!(Some("fresh".to_string()), record.get("value").and_then(Value::unquoted));
+            assert_ne!(Some(99), record.token);
+        });
+    }
+
+    #[test]
+    fn capabilities() {
+        let backend = MockBackend::new()
+            .banner(&["~\"GNU gdb (GDB) 8.0\\n\""])
+            .on(
+                "-list-features",
+                &["^done,features=[\"frozen-varobjs\",\"pending-breakpoints\",\"thread-info\",\"breakpoint-notifications\"]"],
+            )
+            .on("-list-target-features", &["^done,features=[\"async\"]"])
+            .on("-file-exec-and-symbols \"app\"", &["^done"])
+            .on("-break-insert -t \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\"}"])
+            .on("-exec-run", &["^running", "*running,thread-id=\"all\""]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            // startup output is not lost to the queries
+            let banner = dbg.read_message_record(&mut rx).await.unwrap();
+            let Record::Stream(StreamRecord::Console(banner)) = banner else {
+                panic!("expected the banner");
+            };
+            assert!(banner.contains("GNU gdb"));
+            let capabilities = dbg.capabilities().unwrap();
+            assert!(capabilities.async_execution);
+            assert!(capabilities.pending_breakpoints);
+            assert!(!capabilities.exec_run_start_option);
+            assert!(!capabilities.data_read_memory_bytes);
+            assert!(capabilities.has("frozen-varobjs"));
+            // no `--start`: a temporary breakpoint on main instead
+            dbg.load_executable(&mut rx, std::path::Path::new("app"))
+                .await
+                .unwrap();
+            dbg.run_start(&mut rx).await.unwrap();
+            assert_eq!(DebuggerState::Running, dbg.state());
+        });
+    }
+
+    #[test]
+    fn gdb_version() {
+        let version = |banner| Version::from_banner(banner);
+        assert_eq!(Some(Version::new(13, 1, 0)), version("GNU gdb (GDB) 13.1\n"));
+        let ubuntu = "GNU gdb (Ubuntu 12.1-0ubuntu1~22.04) 12.1";
+        assert_eq!(Some(Version::new(12, 1, 0)), version(ubuntu));
+        assert_eq!(Some(Version::new(13, 2, 0)), version("GNU gdb (GDB) Fedora Linux 13.2-3.fc39"));
+        assert_eq!(Some(Version::new(14, 0, 50)), version("GNU gdb (GDB) 14.0.50.20230907-git"));
+        assert_eq!(None, version("Copyright (C) 2023 Free Software Foundation, Inc."));
+        assert!(Version::new(9, 1, 0) > Version::new(8, 3, 1));
+        assert_eq!("14.0.50", Version::new(14, 0, 50).to_string());
+
+        let backend = MockBackend::new()
+            .banner(&["~\"GNU gdb (GDB) 8.0\\n\""])
+            .on("-break-insert \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\"}"]);
+        run_async(async move {
+            let (dbg, mut rx) = Debugger::start_with_backend(backend).await.unwrap();
+            assert_eq!(Some(Version::new(8, 0, 0)), dbg.gdb_version());
+            let qualified = BreakpointOptions {
+                qualified: true,
+                ..Default::default()
+            };
+            let err = dbg.insert_breakpoint(&mut rx, "main", &qualified).await.unwrap_err();
+            let required = Version::QUALIFIED_BREAKPOINTS;
+            assert!(matches!(err, Error::Unsupported { required: r, .. } if r == required));
+            assert!(dbg
+                .insert_breakpoint(&mut rx, "main", &BreakpointOptions::default())
+                .await
+                .is_ok());
+        });
+
+        // `gdb -q` prints no banner
+        let backend = MockBackend::new().on(
+            "-gdb-version",
+            &["~\"GNU gdb (GDB) 14.2\\n\"", "~\"Copyright (C) 2023\\n\"", "^done"],
+        );
+        run_async(async move {
+            let (dbg, _rx) = Debugger::start_with_backend(backend).await.unwrap();
+            assert_eq!(Some(Version::new(14, 2, 0)), dbg.gdb_version());
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "dap")]
+    fn dap_session() {
+        use tokio::io::AsyncWriteExt;
+
+        let stopped = parse_line("*stopped,reason=\"breakpoint-hit\",thread-id=\"2\"\n").unwrap();
+        let events = dap::events_for(&stopped);
+        assert_eq!("stopped", events[0].0);
+        assert_eq!(serde_json::json!("breakpoint"), events[0].1["reason"]);
+        assert_eq!(serde_json::json!(2), events[0].1["threadId"]);
+        let exited = parse_line("*stopped,reason=\"exited\",exit-code=\"012\"\n").unwrap();
+        let events = dap::events_for(&exited);
+        assert_eq!(serde_json::json!({"exitCode": 10}), events[0].1);
+        assert_eq!("terminated", events[1].0);
+
+        let backend = MockBackend::new().on(
+            "-thread-info",
+            &["^done,threads=[{id=\"1\",target-id=\"process 42\",name=\"app\"}]"],
+        );
+        run_async(async move {
+            let (dbg, rx) = Debugger::start_with_backend(backend).await.unwrap();
+            let (client, server) = tokio::io::duplex(4096);
+            let (server_read, server_write) = tokio::io::split(server);
+            let server = tokio::spawn(DapServer::new(dbg, rx).serve(server_read, server_write));
+
+            let (client_read, mut client_write) = tokio::io::split(client);
+            let mut client_read = tokio::io::BufReader::new(client_read);
+            for (seq, command) in [(1, "initialize"), (2, "threads")] {
+                let request = serde_json::json!({"seq": seq, "command": command});
+                client_write.write_all(&dap::encode_message(&request)).await.unwrap();
+            }
+            let mut read = async || dap::read_message(&mut client_read).await.unwrap().unwrap();
+            let response = read().await;
+            assert_eq!(serde_json::json!(true), response["success"]);
+            assert_eq!(serde_json::json!("initialized"), read().await["event"]);
+            let response = read().await;
+            assert_eq!(serde_json::json!(2), response["request_seq"]);
+            assert_eq!(serde_json::json!("app"), response["body"]["threads"][0]["name"]);
+
+            client_write.shutdown().await.unwrap();
+            server.await.unwrap().unwrap();
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "demangle")]
+    fn demangle_symbols() {
+        assert_eq!(
+            "core::ptr::drop_in_place",
+            demangle("_ZN4core3ptr13drop_in_place17h0123456789abcdefE")
+        );
+        assert_eq!("foo(int)", demangle("_Z3fooi"));
+        assert_eq!("main", demangle("main"));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_roundtrip() {
+        let record = parser::parse_line("*stopped,frame={func=\"main\",args=[]}\n").unwrap();
+        let json = serde_json::to_string(&record).unwrap();
+        let back: Record = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", record), format!("{:?}", back));
+    }
+
+    #[test]
+    fn entry_point_from_info_files() {
+        let info = "Symbols from \"/tmp/a.out\".\nLocal exec file:\n\t`/tmp/a.out', file type elf64-x86-64.\n\tEntry point: 0x401020\n\t0x0000000000400318 - 0x0000000000400334 is .interp\n";
+        assert_eq!(Some("0x401020".to_string()), entry::parse_entry_point(info));
+        assert_eq!(None, entry::parse_entry_point("Local exec file:\n"));
+
+        let info = format!("{}\t0x0000000000401000 - 0x0000000000401195 is .text\n\t0x00007ffff7fc5238 - 0x00007ffff7fc525c is .note.gnu.property in /lib64/ld-linux-x86-64.so.2\n", info);
+        let sections = entry::parse_exec_sections(&info);
+        assert_eq!(Some(0x401020), sections.entry_point);
+        assert_eq!(3, sections.sections.len());
+        let text = sections.section_at(0x401020).unwrap();
+        assert_eq!(".text", text.name);
+        assert_eq!(0x195, text.size());
+        assert_eq!(
+            Some("/lib64/ld-linux-x86-64.so.2"),
+            sections.sections[2].library.as_deref()
+        );
+    }
+
+    #[test]
+    fn debugger_is_send() {
+        fn assert_send<T: Send + Sync>() {}
+        assert_send::<Debugger>();
+
+        // a shared handle can be used from spawned tasks
+        #[allow(dead_code)]
+        fn share(dbg: std::sync::Arc<Debugger>, mut rx: tokio::sync::mpsc::Receiver<Record>) {
+            tokio::spawn(async move { dbg.send_cmd(&mut rx, "-gdb-version").await.is_ok() });
+        }
+    }
+}
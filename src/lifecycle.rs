@@ -0,0 +1,183 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::events::DebuggerEvent;
+use crate::msg::{self, Record};
+use crate::state::DebuggerState;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
+
+/// How long `stop_debugging()` waits for the target to stop after interrupting it
+const INTERRUPT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long `close()` waits for gdb to exit before it is killed
+const GDB_EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What to do with the debuggee when the debug session is stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EndPolicy {
+    /// Kill the debuggee
+    Kill,
+    /// Detach from the debuggee and let it run
+    Detach,
+}
+
+/// A command run right before the debug session ends, e.g. `monitor reset` or the detach
+/// sequence of a probe. See `Debugger::add_exit_hook()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitHook {
+    /// An MI command (starting with `-`) or a console command
+    pub command: String,
+    /// How long to wait for gdb's reply before moving on to the next hook
+    pub timeout: Duration,
+}
+
+/// The outcome of an exit hook, reported in `DebuggerEvent::SessionEnded`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExitHookResult {
+    pub command: String,
+    /// Why the hook failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+impl Debugger {
+    /// Run `command` before the debug session ends, by `stop_debugging()` and `close()`.
+    /// Hooks run in the order they were added, after the target is interrupted and before
+    /// it is killed or detached from. A hook that fails or does not reply within `timeout`
+    /// does not prevent the next ones from running
+    pub fn add_exit_hook(&self, command: &str, timeout: Duration) {
+        self.session.lock().unwrap().exit_hooks.push(ExitHook {
+            command: command.to_string(),
+            timeout,
+        });
+    }
+
+    /// Remove all the exit hooks
+    pub fn clear_exit_hooks(&self) {
+        self.session.lock().unwrap().exit_hooks.clear();
+    }
+
+    /// Return the exit hooks, in the order they run
+    pub fn exit_hooks(&self) -> Vec<ExitHook> {
+        self.session.lock().unwrap().exit_hooks.clone()
+    }
+
+    /// End the debug session ("stop debugging" button): drop buffered and queued output,
+    /// interrupt the target if it is running, run the exit hooks, then kill or detach from
+    /// it according to `policy`. A gdbserver started with `start_gdbserver()` is killed too.
+    /// A `DebuggerEvent::SessionEnded` record is emitted once done. gdb itself keeps
+    /// running, so the Debugger can be used for a new session
+    pub async fn stop_debugging(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        policy: EndPolicy,
+    ) -> Result<()> {
+        self.pending.lock().unwrap().clear();
+        while output_channel.try_recv().is_ok() {}
+
+        if !self.can_send_commands() && self.interrupt() {
+            let stopped =
+                tokio::time::timeout(INTERRUPT_TIMEOUT, self.wait_for_stop(output_channel)).await;
+            if !matches!(stopped, Ok(Ok(_))) {
+                tracing::debug!("target did not stop after being interrupted");
+            }
+        }
+
+        let hooks = self.run_exit_hooks(output_channel).await;
+
+        let cmd = match policy {
+            EndPolicy::Kill => "-interpreter-exec console \"kill\"",
+            EndPolicy::Detach => "-target-detach",
+        };
+        if let Err(e) = self.send_cmd(output_channel, cmd).await {
+            // there may be nothing to kill or detach from
+            tracing::debug!("{:?} failed: {}", policy, e);
+        }
+        self.stop_gdbserver();
+
+        self.reset_session();
+        let _ = self
+            .events
+            .send(Record::Event(DebuggerEvent::SessionEnded { policy, hooks }))
+            .await;
+        Ok(())
+    }
+
+    /// End the debug session like `stop_debugging()` with `EndPolicy::Kill`, then ask gdb
+    /// to exit and wait for its output to end. gdb is killed if it is still running 5
+    /// seconds later
+    pub async fn close(self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.stop_debugging(output_channel, EndPolicy::Kill).await?;
+        // gdb exits on purpose, don't bring it back
+        self.set_auto_restart(false);
+        let mut closed = self.events.output_closed.subscribe();
+        self.send_cmd_raw("-gdb-exit").await?;
+        let exited = closed.wait_for(|closed| *closed);
+        match tokio::time::timeout(GDB_EXIT_TIMEOUT, exited).await {
+            // nothing left for `terminate()` to kill when `self` is dropped
+            Ok(Ok(_)) => self.gdb_pid.store(usize::MAX, Ordering::Relaxed),
+            _ => tracing::debug!("gdb did not exit within {:?}", GDB_EXIT_TIMEOUT),
+        }
+        Ok(())
+    }
+
+    async fn run_exit_hooks(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Vec<ExitHookResult> {
+        let hooks = self.exit_hooks();
+        let mut results = Vec::with_capacity(hooks.len());
+        for hook in hooks {
+            let cmd = if hook.command.starts_with('-') {
+                hook.command.clone()
+            } else {
+                format!("-interpreter-exec console {}", mi_quote(&hook.command))
+            };
+            let outcome = tokio::time::timeout(hook.timeout, self.send_cmd(output_channel, &cmd))
+                .await
+                .unwrap_or(Err(Error::Timeout));
+            if let Err(e) = &outcome {
+                tracing::debug!("exit hook {} failed: {}", hook.command, e);
+            }
+            results.push(ExitHookResult {
+                command: hook.command,
+                error: outcome.err().map(|e| e.to_string()),
+            });
+        }
+        results
+    }
+
+    /// Forget everything about the debuggee, keeping the breakpoints
+    pub(crate) fn reset_session(&self) {
+        self.pending.lock().unwrap().clear();
+        self.debugee_pid.store(usize::MAX, Ordering::Relaxed);
+        self.set_state(DebuggerState::Idle);
+        self.post_mortem.store(false, Ordering::Relaxed);
+        let mut session = self.session.lock().unwrap();
+        session.selected_thread = None;
+        session.selected_frame = None;
+        session.last_stop = None;
+        session.running = Default::default();
+        session.exit_status = None;
+        session.inferior_pids.clear();
+        session.modules.clear();
+    }
+}
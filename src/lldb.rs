@@ -0,0 +1,54 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{BackendIo, DebuggerBackend, GdbBackend, MiDialect, MiVersion, Result};
+
+/// An lldb-mi process, for platforms where gdb is hard to get (macOS). Start it with
+/// `Debugger::start_with_backend(LldbMiBackend::default())`
+#[derive(Debug, Clone)]
+pub struct LldbMiBackend {
+    /// The lldb-mi binary
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Default for LldbMiBackend {
+    /// The binary named by the `LLDB_MI_BINARY` environment variable, or `lldb-mi`
+    fn default() -> Self {
+        LldbMiBackend {
+            program: ::std::env::var("LLDB_MI_BINARY").unwrap_or("lldb-mi".to_string()),
+            args: Vec::new(),
+        }
+    }
+}
+
+impl DebuggerBackend for LldbMiBackend {
+    fn spawn(&mut self) -> Result<BackendIo> {
+        // lldb-mi accepts gdb's `--interpreter=mi` for compatibility
+        GdbBackend {
+            program: self.program.clone(),
+            args: self.args.clone(),
+            mi_version: MiVersion::Latest,
+            kill_on_host_exit: false,
+        }
+        .spawn()
+    }
+
+    fn dialect(&self) -> MiDialect {
+        MiDialect::LldbMi
+    }
+}
@@ -0,0 +1,98 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{escape_command, strip_token, Debugger};
+use crate::msg::{MessageRecord, ResultClass};
+use crate::remote::KEEPALIVE_TOKEN;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tracing::field::Empty;
+use tracing::Span;
+
+/// Command counters, see `Debugger::metrics()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DebuggerMetrics {
+    /// Commands sent to gdb
+    pub commands_sent: u64,
+    /// Average time from sending a command with `send_cmd()` or `console()` to reading
+    /// its result record
+    pub average_latency: Duration,
+    /// Commands gdb did not reply to yet
+    pub pending: u64,
+}
+
+/// Is `line` a result record, other than a keepalive reply?
+pub(crate) fn is_result_line(line: &str) -> bool {
+    let (token, rest) = strip_token(line);
+    rest.starts_with('^') && token.parse() != Ok(KEEPALIVE_TOKEN)
+}
+
+/// The `command` span of a command, from sending it until its result record is read.
+/// Its fields are the MI token (if the command has one), the command, the result class
+/// and the elapsed time in microseconds
+pub(crate) struct CommandTrace {
+    pub span: Span,
+    started: Instant,
+}
+
+impl CommandTrace {
+    pub fn new(cmd: &str) -> Self {
+        let (token, command) = strip_token(cmd);
+        let span = tracing::debug_span!(
+            "command",
+            token = token,
+            command = %escape_command(command),
+            result = Empty,
+            elapsed_us = Empty,
+        );
+        CommandTrace {
+            span,
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Debugger {
+    /// Commands sent, average latency and commands waiting for a reply
+    pub fn metrics(&self) -> DebuggerMetrics {
+        let sent = self.stats.commands_sent.load(Ordering::Relaxed);
+        let received = self.stats.results_received.load(Ordering::Relaxed);
+        let samples = self.stats.latency_samples.load(Ordering::Relaxed);
+        let total_us = self.stats.latency_total_us.load(Ordering::Relaxed);
+        DebuggerMetrics {
+            commands_sent: sent,
+            average_latency: match samples {
+                0 => Duration::ZERO,
+                n => Duration::from_micros(total_us / n),
+            },
+            pending: sent.saturating_sub(received),
+        }
+    }
+
+    /// Close the span of `trace` with the result of its command
+    pub(crate) fn end_command(&self, trace: CommandTrace, record: &MessageRecord<ResultClass>) {
+        let elapsed = trace.started.elapsed();
+        let elapsed_us = elapsed.as_micros() as u64;
+        trace.span.record("result", tracing::field::debug(&record.class));
+        trace.span.record("elapsed_us", elapsed_us);
+        tracing::debug!(parent: &trace.span, "result received in {:?}", elapsed);
+        self.stats
+            .latency_total_us
+            .fetch_add(elapsed_us, Ordering::Relaxed);
+        self.stats.latency_samples.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{strip_token, BackendIo, DebuggerBackend, Result};
+use std::collections::VecDeque;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// A `DebuggerBackend` replaying canned MI output instead of running gdb, so code driving
+/// a `Debugger` can be tested without a gdb binary:
+///
+/// ```no_run
+/// # async fn example() -> gdb::Result<()> {
+/// let backend = gdb::MockBackend::new()
+///     .on("-file-exec-and-symbols \"app\"", &["^done"])
+///     .on("-exec-run", &["^running", "*running,thread-id=\"all\""])
+///     .on("-break-insert \"main\"", &["^done,bkpt={number=\"1\",type=\"breakpoint\"}"]);
+/// let (dbg, mut rx) = gdb::Debugger::start_with_backend(backend).await?;
+/// dbg.load_executable(&mut rx, std::path::Path::new("app")).await?;
+/// dbg.run(&mut rx).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Commands without a response get an `^error` result
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    banner: Vec<String>,
+    /// Responses by command, each used once except the last one
+    responses: Vec<(String, VecDeque<Vec<String>>)>,
+    /// Commands that make the mock exit, see `crash_on()`
+    crashes: Vec<String>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lines printed on startup, before any command is received. A prompt follows them
+    pub fn banner(mut self, lines: &[&str]) -> Self {
+        self.banner = lines.iter().map(|line| line.to_string()).collect();
+        self
+    }
+
+    /// Reply to `command` with `output`, followed by a prompt. Registering the same command
+    /// again queues another response: they are replayed in order, the last one repeats.
+    /// Tokens are not part of `command`, they are added to the result record
+    pub fn on(self, command: &str, output: &[&str]) -> Self {
+        let mut output: Vec<String> = output.iter().map(|line| line.to_string()).collect();
+        output.push("(gdb) ".to_string());
+        self.respond(command, output)
+    }
+
+    /// Exit without replying when `command` is received, like gdb crashing
+    pub fn crash_on(mut self, command: &str) -> Self {
+        self.crashes.push(command.trim().to_string());
+        self
+    }
+
+    fn respond(mut self, command: &str, output: Vec<String>) -> Self {
+        let command = command.trim();
+        match self.responses.iter_mut().find(|(c, _)| c == command) {
+            Some((_, queue)) => queue.push_back(output),
+            None => self
+                .responses
+                .push((command.to_string(), VecDeque::from([output]))),
+        }
+        self
+    }
+
+    /// Build the responses from a transcript written by `TranscriptFile`, or from
+    /// `> command` and `< output` lines. Output is replayed verbatim, prompts included.
+    /// Output before the first command is the banner
+    pub fn from_transcript(transcript: &str) -> Self {
+        let mut mock = MockBackend::new();
+        let mut current: Option<(String, Vec<String>)> = None;
+        for line in transcript.lines() {
+            // `TranscriptFile` lines start with a timestamp
+            let line = match line.split_once(' ') {
+                Some((at, rest)) if at.parse::<f64>().is_ok() => rest,
+                _ => line,
+            };
+            if let Some(command) = line.strip_prefix("> ") {
+                if let Some((command, output)) = current.take() {
+                    mock = mock.respond(&command, output);
+                }
+                current = Some((strip_token(command).1.to_string(), Vec::new()));
+            } else if let Some(output) = line.strip_prefix("< ") {
+                // crate events are not gdb output
+                if output.starts_with('{') {
+                    continue;
+                }
+                match current.as_mut() {
+                    Some((_, lines)) => lines.push(output.to_string()),
+                    None => mock.banner.push(output.to_string()),
+                }
+            }
+        }
+        if let Some((command, output)) = current.take() {
+            mock = mock.respond(&command, output);
+        }
+        mock
+    }
+
+    fn response(&mut self, command: &str) -> Vec<String> {
+        let queue = self
+            .responses
+            .iter_mut()
+            .find(|(c, _)| c == command)
+            .map(|(_, queue)| queue);
+        match queue {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap_or_default(),
+            Some(queue) => queue.front().cloned().unwrap_or_default(),
+            None => vec![
+                format!("^error,msg=\"mock: unexpected command {}\"", command.replace('"', "\\\"")),
+                "(gdb) ".to_string(),
+            ],
+        }
+    }
+}
+
+impl DebuggerBackend for MockBackend {
+    /// Each spawned mock replays the responses from the start
+    fn spawn(&mut self) -> Result<BackendIo> {
+        let (ours, theirs) = tokio::io::duplex(64 * 1024);
+        let mut mock = self.clone();
+        tokio::spawn(async move {
+            let (input, mut output) = tokio::io::split(theirs);
+            let mut banner = std::mem::take(&mut mock.banner);
+            if !banner.iter().any(|line| line.starts_with("(gdb)")) {
+                banner.push("(gdb) ".to_string());
+            }
+            for line in banner {
+                if output.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+            let mut commands = BufReader::new(input).lines();
+            while let Ok(Some(command)) = commands.next_line().await {
+                let (token, command) = strip_token(&command);
+                if mock.crashes.iter().any(|c| c == command) {
+                    return;
+                }
+                for line in mock.response(command) {
+                    let line = match line.starts_with('^') {
+                        true => format!("{}{}\n", token, line),
+                        false => format!("{}\n", line),
+                    };
+                    if output.write_all(line.as_bytes()).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        let (stdout, stdin) = tokio::io::split(ours);
+        Ok(BackendIo {
+            stdout: Box::new(stdout),
+            stdin: Box::new(stdin),
+            stderr: None,
+            pid: None,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg::{self, find_field, MessageRecord, Value, Variable};
+use std::ops::Range;
+use tokio::sync::mpsc::Receiver;
+
+/// A shared library loaded by the program, from `=library-loaded` or
+/// `-file-list-shared-libraries`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Module {
+    /// gdb's id of the library, usually its target path
+    pub id: String,
+    /// The path on the target
+    pub target_name: String,
+    /// The path gdb read the library from, different from `target_name` with a
+    /// sysroot or a remote target
+    pub host_name: String,
+    /// gdb read the library's symbols, see `Debugger::load_symbols_for()`
+    pub symbols_loaded: bool,
+    /// The inferior that loaded the library, e.g. `i1`
+    pub thread_group: Option<String>,
+    /// The addresses of the library's code
+    pub ranges: Vec<Range<u64>>,
+}
+
+impl Module {
+    /// Build from a `=library-loaded` record
+    pub fn from_record<C>(record: &MessageRecord<C>) -> Option<Module> {
+        Self::from_vars(&record.content)
+    }
+
+    /// Build from an element of `-file-list-shared-libraries`
+    pub fn from_value(value: &Value) -> Option<Module> {
+        match value {
+            Value::VariableList(vars) => Self::from_vars(vars),
+            _ => None,
+        }
+    }
+
+    fn from_vars(vars: &[Variable]) -> Option<Module> {
+        let text = |name| find_field(vars, name).and_then(Value::unquoted);
+        let address = |value: Option<&Value>| value.and_then(Value::as_u64);
+        let range = |value: &Value| Some(address(value.get("from"))?..address(value.get("to"))?);
+        // gdb < 8.1 reports a single range
+        let ranges = match find_field(vars, "ranges").and_then(Value::as_list) {
+            Some(ranges) => ranges.iter().filter_map(range).collect(),
+            None => {
+                let low = address(find_field(vars, "low-address"));
+                let high = address(find_field(vars, "high-address"));
+                low.zip(high).map(|(low, high)| low..high).into_iter().collect()
+            }
+        };
+        let id = text("id")?;
+        Some(Module {
+            target_name: text("target-name").unwrap_or_else(|| id.clone()),
+            host_name: text("host-name").unwrap_or_else(|| id.clone()),
+            id,
+            symbols_loaded: text("symbols-loaded").as_deref() == Some("1"),
+            thread_group: text("thread-group"),
+            ranges,
+        })
+    }
+
+    /// Does the library's code contain `addr`?
+    pub fn contains(&self, addr: u64) -> bool {
+        self.ranges.iter().any(|range| range.contains(&addr))
+    }
+}
+
+/// The shared libraries loaded by the program, in load order
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ModuleTable {
+    modules: Vec<Module>,
+}
+
+impl ModuleTable {
+    /// Add `module`, replacing the one with the same id in the same inferior
+    pub fn insert(&mut self, module: Module) {
+        let existing = self
+            .modules
+            .iter_mut()
+            .find(|m| m.id == module.id && m.thread_group == module.thread_group);
+        match existing {
+            Some(existing) => *existing = module,
+            None => self.modules.push(module),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str, thread_group: Option<&str>) {
+        self.modules
+            .retain(|m| m.id != id || m.thread_group.as_deref() != thread_group);
+    }
+
+    /// Forget the libraries of inferior `thread_group`, whose process exited
+    pub fn remove_thread_group(&mut self, thread_group: &str) {
+        self.modules
+            .retain(|m| m.thread_group.as_deref() != Some(thread_group));
+    }
+
+    pub fn clear(&mut self) {
+        self.modules.clear();
+    }
+
+    pub fn to_vec(&self) -> Vec<Module> {
+        self.modules.clone()
+    }
+}
+
+impl Debugger {
+    /// The shared libraries loaded by the program, as reported by `=library-loaded`
+    pub fn modules(&self) -> Vec<Module> {
+        self.session.lock().unwrap().modules.to_vec()
+    }
+
+    /// Read the symbols of shared library `module` (its id or target path) with
+    /// `sharedlibrary`, e.g. after `set auto-solib-add off`. Return the updated module
+    pub async fn load_symbols_for(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        module: &str,
+    ) -> Result<Module> {
+        let regex = format!("^{}$", regex::escape(module));
+        self.console(output_channel, &format!("sharedlibrary {}", regex))
+            .await?;
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-file-list-shared-libraries {}", mi_quote(&regex)),
+            )
+            .await?;
+        let modules: Vec<Module> = record
+            .get("shared-libraries")
+            .and_then(Value::as_list)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Module::from_value)
+            .collect();
+        let mut session = self.session.lock().unwrap();
+        for module in &modules {
+            session.modules.insert(module.clone());
+        }
+        modules.into_iter().next().ok_or(Error::UnexpectedResponse)
+    }
+}
@@ -15,67 +15,186 @@
  * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use crate::events::DebuggerEvent;
+use serde_json::{json, Map};
+use std::borrow::Cow;
+use std::fmt;
 use std::str;
 
+/// A record of gdb output. The strings are owned by default, `RecordRef` borrows them
+/// from the parsed line instead (see `parse_line_borrowed()`)
 #[derive(Debug, Clone)]
-pub enum Record {
-    Result(MessageRecord<ResultClass>),
-    Async(AsyncRecord),
-    Stream(StreamRecord),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Record<S = String> {
+    Result(MessageRecord<ResultClass, S>),
+    Async(AsyncRecord<S>),
+    Stream(StreamRecord<S>),
+    Event(DebuggerEvent),
+    /// A line of gdb output that could not be parsed, without its line terminator
+    Raw(S),
+    /// The `(gdb)` prompt: gdb is done emitting output for the current command
+    Prompt,
 }
 
 #[derive(Debug, Clone)]
-pub struct MessageRecord<ClassT> {
-    pub token: Option<String>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageRecord<ClassT, S = String> {
+    /// The token the command was sent with, see `TokenGenerator`
+    pub token: Option<u64>,
     pub class: ClassT,
-    pub content: Vec<Variable>,
+    pub content: Vec<Variable<S>>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ResultClass {
     Done,
     Running,
     Connected,
     Error,
     Exit,
+    /// A class this crate doesn't know, as gdb wrote it
+    Unknown(String),
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AsyncClass {
     Stopped,
-    Other,
+    Running,
+    ThreadSelected,
+    BreakpointCreated,
+    BreakpointModified,
+    BreakpointDeleted,
+    /// `+download` progress of `-target-download`
+    Download,
+    /// `=thread-group-started`: the process of an inferior started
+    ThreadGroupStarted,
+    /// `=thread-group-exited`: the process of an inferior exited
+    ThreadGroupExited,
+    /// `=library-loaded`: a shared library was loaded, see `Module`
+    LibraryLoaded,
+    /// `=library-unloaded`
+    LibraryUnloaded,
+    /// Any other class, e.g. `cmd-param-changed`, as gdb wrote it
+    Unknown(String),
 }
 
 #[derive(Debug, Clone)]
-pub enum AsyncRecord {
-    Exec(MessageRecord<AsyncClass>),
-    Status(MessageRecord<AsyncClass>),
-    Notify(MessageRecord<AsyncClass>),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AsyncRecord<S = String> {
+    Exec(MessageRecord<AsyncClass, S>),
+    Status(MessageRecord<AsyncClass, S>),
+    Notify(MessageRecord<AsyncClass, S>),
 }
 
 #[derive(Debug, Clone)]
-pub enum StreamRecord {
-    Console(Constant),
-    Target(Constant),
-    Log(Constant),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StreamRecord<S = Constant> {
+    Console(S),
+    Target(S),
+    Log(S),
 }
 
+/// A `name=value` result. The name is empty for the anonymous tuples some records carry,
+/// e.g. `+download,{section=".text",...}`
 #[derive(Debug, Clone)]
-pub struct Variable {
-    pub name: VarName,
-    pub value: Value,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variable<S = VarName> {
+    pub name: S,
+    pub value: Value<S>,
 }
 
 #[derive(Debug, Clone)]
-pub enum Value {
-    String(Constant),
-    VariableList(Vec<Variable>),
-    ValueList(Vec<Value>),
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value<S = Constant> {
+    String(S),
+    /// A tuple, `{name=value,...}`
+    VariableList(Vec<Variable<S>>),
+    /// A list of values, `[value,...]`
+    ValueList(Vec<Value<S>>),
+    /// A list of results, `[name=value,...]`, e.g. `stack=[frame={...},frame={...}]`.
+    /// Accessors like `get()` treat it as a tuple
+    ResultList(Vec<Variable<S>>),
 }
 
 pub type VarName = String;
 pub type Constant = String;
 
+/// A record borrowing its strings from the line it was parsed from
+pub type RecordRef<'a> = Record<&'a str>;
+pub type ValueRef<'a> = Value<&'a str>;
+pub type VariableRef<'a> = Variable<&'a str>;
+
+impl RecordRef<'_> {
+    /// Copy the borrowed strings, e.g. to keep the record once the line is gone
+    pub fn into_owned(self) -> Record {
+        match self {
+            Record::Result(record) => Record::Result(record.into_owned()),
+            Record::Async(AsyncRecord::Exec(record)) => {
+                Record::Async(AsyncRecord::Exec(record.into_owned()))
+            }
+            Record::Async(AsyncRecord::Status(record)) => {
+                Record::Async(AsyncRecord::Status(record.into_owned()))
+            }
+            Record::Async(AsyncRecord::Notify(record)) => {
+                Record::Async(AsyncRecord::Notify(record.into_owned()))
+            }
+            Record::Stream(StreamRecord::Console(s)) => {
+                Record::Stream(StreamRecord::Console(s.to_string()))
+            }
+            Record::Stream(StreamRecord::Target(s)) => {
+                Record::Stream(StreamRecord::Target(s.to_string()))
+            }
+            Record::Stream(StreamRecord::Log(s)) => {
+                Record::Stream(StreamRecord::Log(s.to_string()))
+            }
+            Record::Event(event) => Record::Event(event),
+            Record::Raw(line) => Record::Raw(line.to_string()),
+            Record::Prompt => Record::Prompt,
+        }
+    }
+}
+
+impl<ClassT> MessageRecord<ClassT, &str> {
+    /// Copy the borrowed strings
+    pub fn into_owned(self) -> MessageRecord<ClassT> {
+        MessageRecord {
+            token: self.token,
+            class: self.class,
+            content: self.content.into_iter().map(Variable::into_owned).collect(),
+        }
+    }
+}
+
+impl VariableRef<'_> {
+    /// Copy the borrowed strings
+    pub fn into_owned(self) -> Variable {
+        Variable {
+            name: self.name.to_string(),
+            value: self.value.into_owned(),
+        }
+    }
+}
+
+impl ValueRef<'_> {
+    /// Copy the borrowed strings
+    pub fn into_owned(self) -> Value {
+        match self {
+            Value::String(s) => Value::String(s.to_string()),
+            Value::VariableList(vars) => {
+                Value::VariableList(vars.into_iter().map(Variable::into_owned).collect())
+            }
+            Value::ResultList(vars) => {
+                Value::ResultList(vars.into_iter().map(Variable::into_owned).collect())
+            }
+            Value::ValueList(values) => {
+                Value::ValueList(values.into_iter().map(Value::into_owned).collect())
+            }
+        }
+    }
+}
+
 impl str::FromStr for ResultClass {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -85,7 +204,7 @@ impl str::FromStr for ResultClass {
             "connected" => Ok(ResultClass::Connected),
             "error" => Ok(ResultClass::Error),
             "exit" => Ok(ResultClass::Exit),
-            _ => Err("unrecognized result class".to_string()),
+            _ => Ok(ResultClass::Unknown(s.to_string())),
         }
     }
 }
@@ -95,7 +214,398 @@ impl str::FromStr for AsyncClass {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "stopped" => Ok(AsyncClass::Stopped),
-            _ => Ok(AsyncClass::Other),
+            "running" => Ok(AsyncClass::Running),
+            "thread-selected" => Ok(AsyncClass::ThreadSelected),
+            "breakpoint-created" => Ok(AsyncClass::BreakpointCreated),
+            "breakpoint-modified" => Ok(AsyncClass::BreakpointModified),
+            "breakpoint-deleted" => Ok(AsyncClass::BreakpointDeleted),
+            "download" => Ok(AsyncClass::Download),
+            "thread-group-started" => Ok(AsyncClass::ThreadGroupStarted),
+            "thread-group-exited" => Ok(AsyncClass::ThreadGroupExited),
+            "library-loaded" => Ok(AsyncClass::LibraryLoaded),
+            "library-unloaded" => Ok(AsyncClass::LibraryUnloaded),
+            _ => Ok(AsyncClass::Unknown(s.to_string())),
+        }
+    }
+}
+
+impl Record {
+    /// Convert to JSON, for web UIs and log pipelines:
+    ///
+    /// * `{"type": "result", "token": 12, "class": "done", "results": {...}}`
+    /// * `{"type": "exec" | "status" | "notify", "token": null, "class": "stopped", "results": {...}}`
+    /// * `{"type": "console" | "target" | "log", "text": "..."}`
+    /// * `{"type": "event", "event": "TargetDisconnected", ...}`
+    /// * `{"type": "raw", "text": "..."}`
+    /// * `{"type": "prompt"}`
+    ///
+    /// See `Value::to_json()` for the conversion of the results
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Record::Result(record) => record.to_json("result", record.class.as_str()),
+            Record::Async(AsyncRecord::Exec(record)) => record.to_json("exec", record.class.as_str()),
+            Record::Async(AsyncRecord::Status(record)) => {
+                record.to_json("status", record.class.as_str())
+            }
+            Record::Async(AsyncRecord::Notify(record)) => {
+                record.to_json("notify", record.class.as_str())
+            }
+            Record::Stream(StreamRecord::Console(text)) => {
+                json!({"type": "console", "text": unquote(text)})
+            }
+            Record::Stream(StreamRecord::Target(text)) => {
+                json!({"type": "target", "text": unquote(text)})
+            }
+            Record::Stream(StreamRecord::Log(text)) => json!({"type": "log", "text": unquote(text)}),
+            Record::Event(event) => {
+                let mut json = event.to_json();
+                json["type"] = json!("event");
+                json
+            }
+            Record::Raw(line) => json!({"type": "raw", "text": line}),
+            Record::Prompt => json!({"type": "prompt"}),
+        }
+    }
+}
+
+impl Record {
+    /// The MI line this record was parsed from, without the line terminator.
+    /// Crate events have no MI representation
+    pub fn to_mi_string(&self) -> Option<String> {
+        match self {
+            Record::Result(record) => Some(record.to_string()),
+            Record::Async(record) => Some(record.to_string()),
+            Record::Stream(record) => Some(record.to_string()),
+            Record::Event(_) => None,
+            Record::Raw(line) => Some(line.clone()),
+            Record::Prompt => Some("(gdb) ".to_string()),
+        }
+    }
+}
+
+/// MI syntax, e.g. `12^done,value="42"`
+impl fmt::Display for MessageRecord<ResultClass> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_mi(f, '^', self.class.as_str())
+    }
+}
+
+/// MI syntax, e.g. `*stopped,reason="breakpoint-hit"`. Async classes the crate does not
+/// know (`AsyncClass::Unknown`) are written as they were received
+impl fmt::Display for AsyncRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsyncRecord::Exec(record) => record.write_mi(f, '*', record.class.as_str()),
+            AsyncRecord::Status(record) => record.write_mi(f, '+', record.class.as_str()),
+            AsyncRecord::Notify(record) => record.write_mi(f, '=', record.class.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for StreamRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StreamRecord::Console(text) => write!(f, "~{}", text),
+            StreamRecord::Target(text) => write!(f, "@{}", text),
+            StreamRecord::Log(text) => write!(f, "&{}", text),
+        }
+    }
+}
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.name.is_empty() {
+            return write!(f, "{}", self.value);
+        }
+        write!(f, "{}={}", self.name, self.value)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // constants keep their quotes and escapes, they are written as parsed
+        match self {
+            Value::String(s) => write!(f, "{}", s),
+            Value::ResultList(vars) => {
+                write!(f, "[")?;
+                write_separated(f, vars)?;
+                write!(f, "]")
+            }
+            Value::VariableList(vars) => {
+                write!(f, "{{")?;
+                write_separated(f, vars)?;
+                write!(f, "}}")
+            }
+            Value::ValueList(values) => {
+                write!(f, "[")?;
+                write_separated(f, values)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn write_separated<T: fmt::Display>(f: &mut fmt::Formatter, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl ResultClass {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            ResultClass::Done => "done",
+            ResultClass::Running => "running",
+            ResultClass::Connected => "connected",
+            ResultClass::Error => "error",
+            ResultClass::Exit => "exit",
+            ResultClass::Unknown(class) => class,
+        }
+    }
+}
+
+impl AsyncClass {
+    pub(crate) fn as_str(&self) -> &str {
+        match self {
+            AsyncClass::Stopped => "stopped",
+            AsyncClass::Running => "running",
+            AsyncClass::ThreadSelected => "thread-selected",
+            AsyncClass::BreakpointCreated => "breakpoint-created",
+            AsyncClass::BreakpointModified => "breakpoint-modified",
+            AsyncClass::BreakpointDeleted => "breakpoint-deleted",
+            AsyncClass::Download => "download",
+            AsyncClass::ThreadGroupStarted => "thread-group-started",
+            AsyncClass::ThreadGroupExited => "thread-group-exited",
+            AsyncClass::LibraryLoaded => "library-loaded",
+            AsyncClass::LibraryUnloaded => "library-unloaded",
+            AsyncClass::Unknown(class) => class,
+        }
+    }
+}
+
+impl<ClassT, S> MessageRecord<ClassT, S> {
+    /// The token as text, e.g. `"12"`, like it was before tokens were numbers
+    pub fn token_str(&self) -> Option<String> {
+        self.token.map(|token| token.to_string())
+    }
+}
+
+impl<ClassT> MessageRecord<ClassT> {
+    fn write_mi(&self, f: &mut fmt::Formatter, prefix: char, class: &str) -> fmt::Result {
+        if let Some(token) = &self.token {
+            write!(f, "{}", token)?;
+        }
+        write!(f, "{}{}", prefix, class)?;
+        for var in &self.content {
+            write!(f, ",{}", var)?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self, kind: &str, class: &str) -> serde_json::Value {
+        json!({
+            "type": kind,
+            "token": self.token,
+            "class": class,
+            "results": tuple_to_json(&self.content),
+        })
+    }
+
+    /// Return the value of the top level field `name`
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        find_field(&self.content, name)
+    }
+
+    /// The values of all the top level fields called `name`, for records repeating it
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Value> {
+        find_fields(&self.content, name)
+    }
+
+    /// Return the value at a dotted path of field names, e.g. `frame.fullname`
+    pub fn lookup(&self, path: &str) -> Option<&Value> {
+        let (first, rest) = match path.split_once('.') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (path, None),
+        };
+        let value = self.get(first)?;
+        match rest {
+            Some(rest) => value.lookup(rest),
+            None => Some(value),
+        }
+    }
+}
+
+impl Value {
+    /// Return the unescaped content of a string value
+    pub fn unquoted(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(unquote(s)),
+            _ => None,
+        }
+    }
+
+    /// Same as `unquoted()`, borrowing the content when it has no escape sequences
+    pub fn as_str(&self) -> Option<Cow<'_, str>> {
+        let Value::String(s) = self else {
+            return None;
+        };
+        if s.contains('\\') {
+            return Some(Cow::Owned(unquote(s)));
+        }
+        let s = s.strip_prefix('"').unwrap_or(s);
+        Some(Cow::Borrowed(s.strip_suffix('"').unwrap_or(s)))
+    }
+
+    /// A decimal or `0x` prefixed hexadecimal number, e.g. a line or an address
+    pub fn as_u64(&self) -> Option<u64> {
+        let s = self.as_str()?;
+        match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        }
+    }
+
+    /// The elements of a `[...]` list of values
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::ValueList(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Field `name` of a tuple or a list of results
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::VariableList(vars) | Value::ResultList(vars) => find_field(vars, name),
+            _ => None,
+        }
+    }
+
+    /// The values of all the fields of a tuple called `name`, e.g. the `frame`s of
+    /// `[frame={...},frame={...}]`. Anonymous elements (`[bkpt={...},{...}]`) are
+    /// called `""`
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Value> {
+        let vars = match self {
+            Value::VariableList(vars) | Value::ResultList(vars) => vars.as_slice(),
+            _ => &[],
+        };
+        find_fields(vars, name)
+    }
+
+    /// Return the value at a dotted path of field names, e.g. `frame.fullname`
+    pub fn lookup(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |value, name| value.get(name))
+    }
+
+    /// Convert to JSON: strings become (unescaped) JSON strings, lists arrays and tuples
+    /// objects. When a tuple repeats a name (`[frame={...},frame={...}]`) the values are
+    /// collected in an array under that name
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::String(s) => json!(unquote(s)),
+            Value::ValueList(values) => values.iter().map(Value::to_json).collect(),
+            Value::VariableList(vars) | Value::ResultList(vars) => tuple_to_json(vars),
+        }
+    }
+
+    /// The tuples of a list, whether the list is anonymous (`[{...},{...}]`)
+    /// or named (`[frame={...},frame={...}]`)
+    pub fn iter_tuples(&self) -> impl Iterator<Item = &[Variable]> {
+        let values: Box<dyn Iterator<Item = &Value>> = match self {
+            Value::ValueList(values) => Box::new(values.iter()),
+            Value::VariableList(vars) | Value::ResultList(vars) => {
+                Box::new(vars.iter().map(|var| &var.value))
+            }
+            Value::String(_) => Box::new(std::iter::empty()),
+        };
+        values.filter_map(|value| match value {
+            Value::VariableList(vars) => Some(vars.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+fn tuple_to_json(vars: &[Variable]) -> serde_json::Value {
+    let mut object = Map::new();
+    let mut repeated = Vec::new();
+    for var in vars {
+        let value = var.value.to_json();
+        match object.get_mut(&var.name) {
+            None => {
+                object.insert(var.name.clone(), value);
+            }
+            Some(serde_json::Value::Array(values)) if repeated.contains(&var.name) => {
+                values.push(value)
+            }
+            Some(existing) => {
+                *existing = json!([existing.take(), value]);
+                repeated.push(var.name.clone());
+            }
+        }
+    }
+    serde_json::Value::Object(object)
+}
+
+/// Find the first variable named `name` in `vars`
+pub(crate) fn find_field<'a>(vars: &'a [Variable], name: &str) -> Option<&'a Value> {
+    vars.iter().find(|var| var.name == name).map(|var| &var.value)
+}
+
+/// The values of the variables named `name` in `vars`
+fn find_fields<'a>(vars: &'a [Variable], name: &'a str) -> impl Iterator<Item = &'a Value> {
+    vars.iter().filter(move |var| var.name == name).map(|var| &var.value)
+}
+
+/// Strip the surrounding quotes of an MI c-string and resolve its escape sequences.
+/// gdb escapes non-ASCII and control bytes as octal (`\303\251`), the bytes are decoded
+/// as UTF-8
+pub(crate) fn unquote(s: &str) -> String {
+    let s = s.strip_prefix('"').unwrap_or(s);
+    let s = s.strip_suffix('"').unwrap_or(s);
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&c, tail)) = rest.split_first() {
+        rest = tail;
+        if c != b'\\' {
+            bytes.push(c);
+            continue;
         }
+        let Some((&escape, tail)) = rest.split_first() else {
+            bytes.push(b'\\');
+            break;
+        };
+        rest = tail;
+        let byte = match escape {
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'e' => 0x1b,
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'v' => 0x0b,
+            b'0'..=b'7' => {
+                // up to 3 octal digits
+                let mut value = u32::from(escape - b'0');
+                for _ in 0..2 {
+                    match rest.first() {
+                        Some(&d @ b'0'..=b'7') => {
+                            value = value * 8 + u32::from(d - b'0');
+                            rest = &rest[1..];
+                        }
+                        _ => break,
+                    }
+                }
+                value as u8
+            }
+            // `\"`, `\\` and unknown escapes
+            other => other,
+        };
+        bytes.push(byte);
     }
+    String::from_utf8_lossy(&bytes).into_owned()
 }
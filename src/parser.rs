@@ -17,144 +17,259 @@
 
 use crate::dbg;
 use crate::msg;
-use regex;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::str;
 
+// compiling a regex is far more expensive than matching it, do it once
+static TOKEN: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[0-9]+").unwrap());
+static RESULT_CLASS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[-a-zA-Z]+").unwrap());
+static ASYNC_CLASS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[-a-zA-Z]+").unwrap());
+static VARNAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_-]*").unwrap());
+static CONSTANT: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^"(?:[^"\\]|\\.)*""#).unwrap());
+
+/// How deep tuples and lists may nest in a record parsed by `parse_line()` and friends.
+/// Deeper records fail to parse with `Error::TooDeep`, see
+/// `MiParser::set_max_nesting_depth()` and `Debugger::set_max_nesting_depth()` to change it
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 512;
+
+/// Parse a single line of gdb's MI output
 pub fn parse_line(line: &str) -> Result<msg::Record, dbg::Error> {
-    if let Some(result) = parse_result_line(line) {
-        Ok(msg::Record::Result(result))
-    } else if let Some(async_record) = parse_async_line(line) {
-        Ok(msg::Record::Async(async_record))
+    parse_record(line, DEFAULT_MAX_NESTING_DEPTH)
+}
+
+/// Same as `parse_line()`, without copying the strings of the record: they borrow from
+/// `line`. Saves allocations when most records are looked at and dropped, e.g. filtering
+/// `-var-update` output. `RecordRef::into_owned()` converts the records to keep
+pub fn parse_line_borrowed(line: &str) -> Result<msg::RecordRef<'_>, dbg::Error> {
+    parse_record(line, DEFAULT_MAX_NESTING_DEPTH)
+}
+
+/// Like `parse_line()`, but a result or async record that breaks off is kept: it comes
+/// with the results parsed before the error, and the error. Fails only if `line` is not
+/// a record at all
+pub fn parse_line_lenient(line: &str) -> Result<(msg::Record, Option<dbg::Error>), dbg::Error> {
+    salvage_line(line, DEFAULT_MAX_NESTING_DEPTH)
+}
+
+/// `parse_line_lenient()` with tuples and lists nested at most `max_depth` deep
+pub(crate) fn salvage_line(
+    line: &str,
+    max_depth: usize,
+) -> Result<(msg::Record, Option<dbg::Error>), dbg::Error> {
+    let (record, failure) =
+        salvage_record(line, max_depth).map_err(|failure| failure.into_error(line))?;
+    Ok((record, failure.map(|failure| failure.into_error(line))))
+}
+
+/// `parse_line()` with tuples and lists nested at most `max_depth` deep
+pub(crate) fn parse_record<'a, S: From<&'a str>>(
+    line: &'a str,
+    max_depth: usize,
+) -> Result<msg::Record<S>, dbg::Error> {
+    match salvage_record(line, max_depth) {
+        Ok((record, None)) => Ok(record),
+        Ok((_, Some(failure))) | Err(failure) => Err(failure.into_error(line)),
+    }
+}
+
+/// What was parsed, and where parsing stopped if it did not reach the end of the line
+type Salvaged<'a, T> = (T, Option<Failure<'a>>);
+
+fn salvage_record<'a, S: From<&'a str>>(
+    line: &'a str,
+    max_depth: usize,
+) -> Result<Salvaged<'a, msg::Record<S>>, Failure<'a>> {
+    if let Some((result, failure)) = result_record(line, max_depth)? {
+        Ok((msg::Record::Result(result), failure))
+    } else if let Some((async_record, failure)) = async_record(line, max_depth)? {
+        Ok((msg::Record::Async(async_record), failure))
     } else if let Some(stream) = parse_stream_line(line) {
-        Ok(msg::Record::Stream(stream))
+        Ok((msg::Record::Stream(stream), None))
     } else {
-        Err(dbg::Error::ParseError)
+        Err(Failure::At(line, "a record"))
     }
 }
 
-pub fn parse_result_line(mut line: &str) -> Option<msg::MessageRecord<msg::ResultClass>> {
-    let mut token = None;
-    if let Some((tok, rest)) = parse_token(line) {
-        token = Some(tok);
-        line = rest;
+/// Why parsing stopped
+enum Failure<'a> {
+    /// At the start of this unparsed rest of the line, which is not what was expected
+    At(&'a str, &'static str),
+    TooDeep,
+}
+
+impl Failure<'_> {
+    fn into_error(self, line: &str) -> dbg::Error {
+        match self {
+            Failure::At(rest, expected) => dbg::Error::ParseError {
+                line: line.trim_end_matches(['\r', '\n']).to_string(),
+                offset: line.len() - rest.len(),
+                expected,
+            },
+            Failure::TooDeep => dbg::Error::TooDeep,
+        }
     }
-    if !line.starts_with("^") {
-        return None;
+}
+
+/// Parses MI output that arrives in chunks (e.g. from a pty), keeping the incomplete
+/// last line until the rest of it is fed. Lines that fail to parse are returned as
+/// `Record::Raw`, invalid UTF-8 is replaced with U+FFFD
+#[derive(Debug)]
+pub struct MiParser {
+    buf: Vec<u8>,
+    max_depth: usize,
+}
+
+impl Default for MiParser {
+    fn default() -> Self {
+        Self {
+            buf: Vec::new(),
+            max_depth: DEFAULT_MAX_NESTING_DEPTH,
+        }
     }
-    line = line.split_at(1).1;
-    let class = if let Some((class, rest)) = parse_result_class(line) {
-        line = rest;
-        class
-    } else {
-        return None;
-    };
-    let mut result = Vec::new();
-    if line.starts_with("\n") || line.starts_with("\r\n") {
-        return Some(msg::MessageRecord::<msg::ResultClass> {
-            token,
-            class,
-            content: result,
-        });
-    } else if !line.starts_with(",") {
-        return None;
+}
+
+impl MiParser {
+    pub fn new() -> Self {
+        Self::default()
     }
-    line = line.split_at(1).1;
-    if let Some((variable, rest)) = parse_variable(line) {
-        line = rest;
-        result.push(variable);
-    } else {
-        return None;
+
+    /// Limit the nesting of tuples and lists in the records of this parser, instead of
+    /// `DEFAULT_MAX_NESTING_DEPTH`. Deeper records are returned as `Record::Raw`
+    pub fn set_max_nesting_depth(&mut self, depth: usize) {
+        self.max_depth = depth;
     }
-    while !line.starts_with("\n") && !line.starts_with("\r\n") {
-        if !line.starts_with(",") {
+
+    /// Parse the lines completed by `data`
+    pub fn feed(&mut self, data: &[u8]) -> Vec<msg::Record> {
+        self.buf.extend_from_slice(data);
+        let Some(end) = self.buf.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.buf.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.buf, rest);
+        complete
+            .split(|&b| b == b'\n')
+            .filter_map(|line| self.parse_bytes(line))
+            .collect()
+    }
+
+    /// Parse what is left of an unterminated last line, e.g. once the input is closed
+    pub fn finish(&mut self) -> Option<msg::Record> {
+        let rest = std::mem::take(&mut self.buf);
+        self.parse_bytes(&rest)
+    }
+
+    /// The bytes of the incomplete line fed so far
+    pub fn pending(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn parse_bytes(&self, line: &[u8]) -> Option<msg::Record> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
             return None;
         }
-        line = line.split_at(1).1;
-        if let Some((variable, rest)) = parse_variable(line) {
-            line = rest;
-            result.push(variable);
-        } else {
-            return None;
+        let line = String::from_utf8_lossy(line);
+        if line.starts_with("(gdb)") {
+            return Some(msg::Record::Prompt);
+        }
+        match parse_record(&format!("{}\n", line), self.max_depth) {
+            Ok(record) => Some(record),
+            Err(_) => Some(msg::Record::Raw(line.into_owned())),
         }
     }
-    Some(msg::MessageRecord::<msg::ResultClass> {
-        token,
-        class,
-        content: result,
-    })
 }
 
-pub fn parse_async_line(mut line: &str) -> Option<msg::AsyncRecord> {
-    let mut token = None;
-    if let Some((tok, rest)) = parse_token(line) {
-        token = Some(tok);
-        line = rest;
+type ResultRecord<'a, S> = Salvaged<'a, msg::MessageRecord<msg::ResultClass, S>>;
+
+fn result_record<'a, S: From<&'a str>>(
+    mut line: &'a str,
+    max_depth: usize,
+) -> Result<Option<ResultRecord<'a, S>>, Failure<'a>> {
+    let (token, rest) = parse_token(line)?;
+    line = rest;
+    if !line.starts_with("^") {
+        return Ok(None);
     }
-    let async_type = if let Some(first) = line.chars().nth(0) {
-        match first {
-            '=' | '+' | '*' => first,
-            _ => return None,
-        }
-    } else {
-        return None;
+    line = line.split_at(1).1;
+    let Some((class, rest)) = parse_result_class(line) else {
+        return Err(Failure::At(line, "a result class"));
+    };
+    let (content, failure) = parse_results(rest, max_depth);
+    Ok(Some((
+        msg::MessageRecord {
+            token,
+            class,
+            content,
+        },
+        failure,
+    )))
+}
+
+fn async_record<'a, S: From<&'a str>>(
+    mut line: &'a str,
+    max_depth: usize,
+) -> Result<Option<Salvaged<'a, msg::AsyncRecord<S>>>, Failure<'a>> {
+    let (token, rest) = parse_token(line)?;
+    line = rest;
+    let async_type = match line.chars().next() {
+        Some(first @ ('=' | '+' | '*')) => first,
+        _ => return Ok(None),
     };
     line = line.split_at(1).1;
-    let class = if let Some((class, rest)) = parse_async_class(line) {
-        line = rest;
-        class
-    } else {
-        return None;
+    let Some((class, rest)) = parse_async_class(line) else {
+        return Err(Failure::At(line, "an async class"));
+    };
+    let (content, failure) = parse_results(rest, max_depth);
+    let msg = msg::MessageRecord {
+        token,
+        class,
+        content,
     };
+    let record = match async_type {
+        '=' => msg::AsyncRecord::Notify(msg),
+        '+' => msg::AsyncRecord::Status(msg),
+        '*' => msg::AsyncRecord::Exec(msg),
+        _ => panic!("unrecognized async type ???!!!"),
+    };
+    Ok(Some((record, failure)))
+}
+
+/// Parse the `,name=value,...` list that follows the record class, up to the end of line.
+/// Stops at the first error, with the results parsed before it
+fn parse_results<'a, S: From<&'a str>>(
+    mut line: &'a str,
+    max_depth: usize,
+) -> Salvaged<'a, Vec<msg::Variable<S>>> {
     let mut result = Vec::new();
-    if line.starts_with("\n") || line.starts_with("\r\n") {
-        let msg = msg::MessageRecord::<msg::AsyncClass> {
-            token: token,
-            class: class,
-            content: result,
-        };
-        return Some(match async_type {
-            '=' => msg::AsyncRecord::Notify(msg),
-            '+' => msg::AsyncRecord::Status(msg),
-            '*' => msg::AsyncRecord::Exec(msg),
-            _ => panic!("unrecognized async type ???!!!"),
-        });
-    } else if !line.starts_with(",") {
-        return None;
-    }
-    line = line.split_at(1).1;
-    if let Some((variable, rest)) = parse_variable(line) {
-        line = rest;
-        result.push(variable);
-    } else {
-        return None;
-    }
     while !line.starts_with("\n") && !line.starts_with("\r\n") {
         if !line.starts_with(",") {
-            return None;
+            return (result, Some(Failure::At(line, "`,` or the end of the line")));
         }
-        line = line.split_at(1).1;
-        if let Some((variable, rest)) = parse_variable(line) {
-            line = rest;
-            result.push(variable);
+        let data = line.split_at(1).1;
+        let parsed = if data.starts_with('{') {
+            // anonymous tuple, e.g. `+download,{section=".text",...}`
+            parse_value(data, max_depth).map(|(value, rest)| {
+                let name = S::from("");
+                (msg::Variable { name, value }, rest)
+            })
         } else {
-            return None;
+            parse_variable(data, max_depth)
+        };
+        match parsed {
+            Ok((variable, rest)) => {
+                line = rest;
+                result.push(variable);
+            }
+            Err(failure) => return (result, Some(failure)),
         }
     }
-    let msg = msg::MessageRecord::<msg::AsyncClass> {
-        token: token,
-        class: class,
-        content: result,
-    };
-    Some(match async_type {
-        '=' => msg::AsyncRecord::Notify(msg),
-        '+' => msg::AsyncRecord::Status(msg),
-        '*' => msg::AsyncRecord::Exec(msg),
-        _ => panic!("unrecognized async type ???!!!"),
-    })
+    (result, None)
 }
 
-pub fn parse_stream_line(mut line: &str) -> Option<msg::StreamRecord> {
-    let stream_type = match line.chars().nth(0) {
+pub fn parse_stream_line<'a, S: From<&'a str>>(mut line: &'a str) -> Option<msg::StreamRecord<S>> {
+    let stream_type = match line.chars().next() {
         Some(t @ '~') | Some(t @ '@') | Some(t @ '&') => t,
         _ => return None,
     };
@@ -175,158 +290,189 @@ pub fn parse_stream_line(mut line: &str) -> Option<msg::StreamRecord> {
     }
 }
 
-fn parse<T: str::FromStr>(data: &str, toklen: usize) -> (T, &str) {
+fn parse<T: str::FromStr>(data: &str, toklen: usize) -> Option<(T, &str)> {
     let (x, y) = data.split_at(toklen);
-    (T::from_str(x).ok().unwrap(), y)
+    Some((T::from_str(x).ok()?, y))
 }
 
-fn parse_token(data: &str) -> Option<(String, &str)> {
-    let Ok(re) = regex::Regex::new( r"^[0-9]+") else {
-        return None;
-    };
-    if let Some(mat) = re.find(data) {
-        Some(parse(data, mat.end() - mat.start()))
-    } else {
-        None
-    }
+/// Split `len` bytes off the start of `data`
+fn take<'a, S: From<&'a str>>(data: &'a str, len: usize) -> (S, &'a str) {
+    let (x, y) = data.split_at(len);
+    (S::from(x), y)
 }
 
-fn parse_result_class(data: &str) -> Option<(msg::ResultClass, &str)> {
-    let Ok(re) = regex::Regex::new( r"^(done|connected|running|error|exit)") else {
-        return None;
+/// The token in front of a record, if it has one
+fn parse_token(data: &str) -> Result<(Option<u64>, &str), Failure<'_>> {
+    let Some(mat) = TOKEN.find(data) else {
+        return Ok((None, data));
     };
+    // too many digits for a `u64`
+    let (token, rest) = parse(data, mat.end() - mat.start()).ok_or(Failure::At(data, "a token"))?;
+    Ok((Some(token), rest))
+}
 
-    if let Some(mat) = re.find(data) {
-        Some(parse(data, mat.end() - mat.start()))
-    } else {
-        None
-    }
+fn parse_result_class(data: &str) -> Option<(msg::ResultClass, &str)> {
+    RESULT_CLASS
+        .find(data)
+        .and_then(|mat| parse(data, mat.end() - mat.start()))
 }
 
 fn parse_async_class(data: &str) -> Option<(msg::AsyncClass, &str)> {
-    let Ok(re) = regex::Regex::new(r"^[-a-zA-Z]+") else {
-        return None;
-    };
-    if let Some(mat) = re.find(data) {
-        Some(parse(data, mat.end() - mat.start()))
-    } else {
-        None
-    }
+    ASYNC_CLASS
+        .find(data)
+        .and_then(|mat| parse(data, mat.end() - mat.start()))
 }
 
-fn parse_varname(data: &str) -> Option<(msg::VarName, &str)> {
-    let Ok(re) = regex::Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_-]*") else {
-        return None;
-    };
-    if let Some(mat) = re.find(data) {
-        Some(parse(data, mat.end() - mat.start()))
-    } else {
-        None
-    }
+fn parse_varname<'a, S: From<&'a str>>(data: &'a str) -> Option<(S, &'a str)> {
+    VARNAME.find(data).map(|mat| take(data, mat.end() - mat.start()))
 }
 
-fn parse_constant(data: &str) -> Option<(msg::Value, &str)> {
-    let Ok(re) = regex::Regex::new(r#"^(".*?[^\\]"|"")"#) else
-    {
-        return None;
-    };
-    if let Some(mat) = re.find(data) {
-        let (value, rest) = parse(data, mat.end() - mat.start());
+fn parse_constant<'a, S: From<&'a str>>(data: &'a str) -> Option<(msg::Value<S>, &'a str)> {
+    if let Some(mat) = CONSTANT.find(data) {
+        let (value, rest) = take(data, mat.end() - mat.start());
         Some((msg::Value::String(value), rest))
     } else {
         None
     }
 }
 
-fn parse_variable_list(data: &str) -> Option<(msg::Value, &str)> {
-    let mut end = "}";
-    if data.starts_with("[") {
-        end = "]";
-    }
-    if !data.starts_with("{") {
-        return None;
-    }
-    let mut data = data.split_at(1).1;
-    let mut result = Vec::new();
-    if data.starts_with(end) {
-        return Some((msg::Value::VariableList(result), data.split_at(1).1));
-    }
-    if let Some((variable, rest)) = parse_variable(data) {
-        data = rest;
-        result.push(variable);
-    } else {
-        return None;
-    }
-    while !data.starts_with(end) {
-        if !data.starts_with(",") {
-            return None;
-        }
-        data = data.split_at(1).1;
-        if let Some((variable, rest)) = parse_variable(data) {
-            data = rest;
-            result.push(variable);
-        } else {
-            return None;
-        }
-    }
-    Some((msg::Value::VariableList(result), data.split_at(1).1))
+/// A tuple or list whose elements are being parsed
+struct Open<S> {
+    end: char,
+    /// `None` until the first element tells whether this is a tuple or a list
+    items: Option<Items<S>>,
+    /// Name of the tuple element being parsed
+    name: Option<S>,
 }
 
-fn parse_value_list(data: &str) -> Option<(msg::Value, &str)> {
-    if !data.starts_with("[") {
-        return None;
-    }
-    let mut data = data.split_at(1).1;
-    let mut result = Vec::new();
-    if data.starts_with(']') {
-        return Some((msg::Value::ValueList(result), data.split_at(1).1));
-    }
-    if let Some((value, rest)) = parse_value(data) {
-        data = rest;
-        result.push(value);
-    } else {
-        return None;
-    }
-    while !data.starts_with("]") {
-        if !data.starts_with(",") {
-            return None;
-        }
-        data = data.split_at(1).1;
-        if let Some((value, rest)) = parse_value(data) {
-            data = rest;
-            result.push(value);
-        } else {
-            return None;
+enum Items<S> {
+    Tuple(Vec<msg::Variable<S>>),
+    List(Vec<msg::Value<S>>),
+}
+
+impl<S> Open<S> {
+    fn into_value(self) -> msg::Value<S> {
+        match self.items {
+            Some(Items::Tuple(vars)) if self.end == ']' => msg::Value::ResultList(vars),
+            Some(Items::Tuple(vars)) => msg::Value::VariableList(vars),
+            Some(Items::List(values)) => msg::Value::ValueList(values),
+            // `{}` is an empty tuple, `[]` an empty list
+            None if self.end == '}' => msg::Value::VariableList(Vec::new()),
+            None => msg::Value::ValueList(Vec::new()),
         }
     }
-    Some((msg::Value::ValueList(result), data.split_at(1).1))
 }
 
-fn parse_value(data: &str) -> Option<(msg::Value, &str)> {
-    parse_constant(data)
-        .or(parse_variable_list(data))
-        .or(parse_value_list(data))
+/// `name=` at the start of `data`
+fn parse_name<'a, S: From<&'a str>>(data: &'a str) -> Option<(S, &'a str)> {
+    let (name, rest) = parse_varname(data)?;
+    Some((name, rest.strip_prefix('=')?))
 }
 
-fn parse_variable(data: &str) -> Option<(msg::Variable, &str)> {
-    if let Some((var, rest)) = parse_varname(data) {
-        match rest.chars().nth(0) {
-            Some('=') => {
-                if let Some((val, rest)) = parse_value(rest.split_at(1).1) {
-                    Some((
-                        msg::Variable {
-                            name: var,
-                            value: val,
-                        },
-                        rest,
-                    ))
-                } else {
-                    None
+/// Parse a value, with tuples and lists nested at most `max_depth` deep. Nested tuples
+/// and lists are handled with an explicit stack, so deeply nested records can't overflow
+/// the call stack
+fn parse_value<'a, S: From<&'a str>>(
+    mut data: &'a str,
+    max_depth: usize,
+) -> Result<(msg::Value<S>, &'a str), Failure<'a>> {
+    let mut stack: Vec<Open<S>> = Vec::new();
+    loop {
+        // the start of a value: a constant, or a tuple/list to descend into
+        let mut value = match data.chars().next() {
+            Some(open @ ('{' | '[')) => {
+                if stack.len() >= max_depth {
+                    return Err(Failure::TooDeep);
                 }
+                let end = if open == '{' { '}' } else { ']' };
+                data = data.split_at(1).1;
+                match data.strip_prefix(end) {
+                    Some(rest) => {
+                        data = rest;
+                        Open {
+                            end,
+                            items: None,
+                            name: None,
+                        }
+                        .into_value()
+                    }
+                    None => {
+                        // a tuple if the first element is named, a list otherwise
+                        let (items, name) = match parse_name(data) {
+                            Some((name, rest)) => {
+                                data = rest;
+                                (Items::Tuple(Vec::new()), Some(name))
+                            }
+                            None => (Items::List(Vec::new()), None),
+                        };
+                        stack.push(Open {
+                            end,
+                            items: Some(items),
+                            name,
+                        });
+                        continue;
+                    }
+                }
+            }
+            _ => {
+                let (value, rest) = parse_constant(data).ok_or(Failure::At(data, "a value"))?;
+                data = rest;
+                value
+            }
+        };
+        // the value is complete: add it to its parent, closing finished parents
+        loop {
+            let Some(top) = stack.last_mut() else {
+                return Ok((value, data));
+            };
+            match &mut top.items {
+                Some(Items::Tuple(vars)) => vars.push(msg::Variable {
+                    name: top.name.take().ok_or(Failure::At(data, "a name"))?,
+                    value,
+                }),
+                Some(Items::List(values)) => values.push(value),
+                None => return Err(Failure::At(data, "a value")),
             }
-            _ => None,
+            if let Some(rest) = data.strip_prefix(',') {
+                data = rest;
+                // gdb mixes named and anonymous elements, e.g. the locations in
+                // `body=[bkpt={...},{...}]`: such lists are tuples, with empty names for
+                // the anonymous elements
+                let named = parse_name(data);
+                if let (Some(_), Some(Items::List(values))) = (&named, &mut top.items) {
+                    let vars = std::mem::take(values).into_iter().map(|value| msg::Variable {
+                        name: S::from(""),
+                        value,
+                    });
+                    top.items = Some(Items::Tuple(vars.collect()));
+                }
+                match named {
+                    Some((name, rest)) => {
+                        top.name = Some(name);
+                        data = rest;
+                    }
+                    None if matches!(top.items, Some(Items::Tuple(_))) => {
+                        top.name = Some(S::from(""))
+                    }
+                    None => {}
+                }
+                break;
+            }
+            let Some(rest) = data.strip_prefix(top.end) else {
+                let expected = if top.end == '}' { "`,` or `}`" } else { "`,` or `]`" };
+                return Err(Failure::At(data, expected));
+            };
+            data = rest;
+            value = stack.pop().map(Open::into_value).ok_or(Failure::At(data, "a value"))?;
         }
-    } else {
-        None
     }
 }
+
+fn parse_variable<'a, S: From<&'a str>>(
+    data: &'a str,
+    max_depth: usize,
+) -> Result<(msg::Variable<S>, &'a str), Failure<'a>> {
+    let (name, rest) = parse_name(data).ok_or(Failure::At(data, "a name"))?;
+    let (value, rest) = parse_value(rest, max_depth)?;
+    Ok((msg::Variable { name, value }, rest))
+}
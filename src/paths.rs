@@ -0,0 +1,187 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{cli_quote, mi_quote, Debugger, Error, Result};
+use crate::events::Frame;
+use crate::msg::{self, Value};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::Receiver;
+
+/// Rewrite `path` with the first matching `(from, to)` rule. Like gdb's substitute-path,
+/// `from` must match whole path components; trailing separators of the rule are ignored
+pub(crate) fn substitute(path: &str, rules: &[(String, String)]) -> String {
+    const SEPARATORS: [char; 2] = ['/', '\\'];
+    for (from, to) in rules.iter().filter(|(from, _)| !from.is_empty()) {
+        let from = from.trim_end_matches(SEPARATORS);
+        if let Some(rest) = path.strip_prefix(from) {
+            if rest.is_empty() || rest.starts_with(SEPARATORS) {
+                return format!("{}{}", to.trim_end_matches(SEPARATORS), rest);
+            }
+        }
+    }
+    path.to_string()
+}
+
+impl Debugger {
+    /// Query the inferior's working directory (`-environment-pwd`) and remember it
+    pub async fn working_directory(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<String> {
+        let record = self.send_cmd(output_channel, "-environment-pwd").await?;
+        let cwd = record
+            .get("cwd")
+            .and_then(Value::unquoted)
+            .ok_or(Error::UnexpectedResponse)?;
+        self.session.lock().unwrap().cwd = Some(cwd.clone());
+        Ok(cwd)
+    }
+
+    /// Change the inferior's working directory (`-environment-cd`)
+    pub async fn set_working_directory(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        dir: &str,
+    ) -> Result<()> {
+        self.send_cmd(output_channel, &format!("-environment-cd {}", mi_quote(dir)))
+            .await?;
+        self.working_directory(output_channel).await?;
+        Ok(())
+    }
+
+    /// Map a path reported by gdb (e.g. a frame's `file`) to a host path: relative paths
+    /// are resolved against the inferior's working directory, then the substitute-path
+    /// rules are applied. Call `working_directory()` once to populate the cwd
+    pub fn resolve_relative_path(&self, path: &str) -> PathBuf {
+        let session = self.session.lock().unwrap();
+        let path = match &session.cwd {
+            Some(cwd) if Path::new(path).is_relative() => {
+                Path::new(cwd).join(path).to_string_lossy().to_string()
+            }
+            _ => path.to_string(),
+        };
+        PathBuf::from(substitute(&path, &session.substitute_paths))
+    }
+
+    /// Find the sources under `to` instead of `from` (`set substitute-path`), e.g. for
+    /// a program built in a container. `from` matches whole path components, the first
+    /// matching rule wins
+    pub async fn add_substitute_path(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        from: &str,
+        to: &str,
+    ) -> Result<()> {
+        let cmd = format!("set substitute-path {} {}", cli_quote(from), cli_quote(to));
+        self.console(output_channel, &cmd).await?;
+        let mut session = self.session.lock().unwrap();
+        // gdb replaces the rule of `from`
+        session.substitute_paths.retain(|(old, _)| old != from);
+        session.substitute_paths.push((from.to_string(), to.to_string()));
+        Ok(())
+    }
+
+    /// Remove the substitute-path rule of `from` (`unset substitute-path`)
+    pub async fn remove_substitute_path(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        from: &str,
+    ) -> Result<()> {
+        let cmd = format!("unset substitute-path {}", cli_quote(from));
+        self.console(output_channel, &cmd).await?;
+        let mut session = self.session.lock().unwrap();
+        session.substitute_paths.retain(|(old, _)| old != from);
+        Ok(())
+    }
+
+    /// Search the sources in `dirs`, replacing the directories set before
+    /// (`-environment-directory -r`)
+    pub async fn set_source_directories(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        dirs: &[String],
+    ) -> Result<()> {
+        let mut cmd = "-environment-directory -r".to_string();
+        for dir in dirs {
+            cmd.push(' ');
+            cmd.push_str(&mi_quote(dir));
+        }
+        self.send_cmd(output_channel, &cmd).await?;
+        self.session.lock().unwrap().source_directories = dirs.to_vec();
+        Ok(())
+    }
+
+    /// Load the target's shared libraries from under `path` (`set sysroot`), e.g. a
+    /// copy of a remote device's file system
+    pub async fn set_sysroot(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        path: &str,
+    ) -> Result<()> {
+        self.send_cmd(output_channel, &format!("-gdb-set sysroot {}", mi_quote(path)))
+            .await?;
+        self.session.lock().unwrap().sysroot = Some(path.to_string());
+        Ok(())
+    }
+
+    /// Set the substitute-path rules, source directories and sysroot again, after gdb
+    /// was restarted
+    pub(crate) async fn restore_source_mappings(&self, output_channel: &mut Receiver<msg::Record>) {
+        let (rules, dirs, sysroot) = {
+            let mut session = self.session.lock().unwrap();
+            let rules = std::mem::take(&mut session.substitute_paths);
+            (rules, session.source_directories.clone(), session.sysroot.clone())
+        };
+        for (from, to) in rules {
+            if let Err(e) = self.add_substitute_path(output_channel, &from, &to).await {
+                tracing::debug!("failed to restore substitute-path {}: {}", from, e);
+            }
+        }
+        if !dirs.is_empty() {
+            if let Err(e) = self.set_source_directories(output_channel, &dirs).await {
+                tracing::debug!("failed to restore the source directories: {}", e);
+            }
+        }
+        if let Some(sysroot) = sysroot {
+            if let Err(e) = self.set_sysroot(output_channel, &sysroot).await {
+                tracing::debug!("failed to restore the sysroot: {}", e);
+            }
+        }
+    }
+
+    /// Where the source of `frame` is on this machine, without asking gdb: its
+    /// `fullname`, or its `file` looked up in the source directories, mapped with
+    /// `resolve_relative_path()`. `None` when none of them exists
+    pub fn resolve_frame_source(&self, frame: &Frame) -> Option<PathBuf> {
+        let mut candidates = Vec::new();
+        if let Some(fullname) = &frame.fullname {
+            candidates.push(self.resolve_relative_path(fullname));
+        }
+        if let Some(file) = &frame.file {
+            if Path::new(file).is_relative() {
+                let session = self.session.lock().unwrap();
+                // `$cdir` and `$cwd` are expanded by gdb in `fullname`
+                for dir in session.source_directories.iter().filter(|d| !d.starts_with('$')) {
+                    let path = Path::new(dir).join(file).to_string_lossy().to_string();
+                    candidates.push(PathBuf::from(substitute(&path, &session.substitute_paths)));
+                }
+            }
+            candidates.push(self.resolve_relative_path(file));
+        }
+        candidates.into_iter().find(|path| path.exists())
+    }
+}
@@ -0,0 +1,112 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::Breakpoint;
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::sync::mpsc::Receiver;
+
+/// The executable loaded with `Debugger::load_executable()` and the state of the
+/// file at that time
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct LoadedExecutable {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+}
+
+impl LoadedExecutable {
+    fn stat(path: &Path) -> LoadedExecutable {
+        let metadata = std::fs::metadata(path).ok();
+        LoadedExecutable {
+            path: path.to_path_buf(),
+            modified: metadata.as_ref().and_then(|m| m.modified().ok()),
+            size: metadata.map(|m| m.len()).unwrap_or(0),
+        }
+    }
+
+    /// Was the file rebuilt since it was loaded?
+    fn changed_on_disk(&self) -> bool {
+        *self != Self::stat(&self.path)
+    }
+}
+
+/// Outcome of `Debugger::reload_executable()`
+#[derive(Debug, Default)]
+pub struct ReloadReport {
+    /// False if the executable did not change, nothing was done
+    pub reloaded: bool,
+    /// The breakpoints bound again in the new executable
+    pub rebound: Vec<Breakpoint>,
+    /// The breakpoints that no longer resolve, with gdb's error. They are removed
+    /// from the breakpoint table
+    pub unresolved: Vec<(Breakpoint, Error)>,
+}
+
+impl Debugger {
+    /// Load `path` as the executable to debug (`-file-exec-and-symbols`)
+    pub async fn load_executable(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        path: &Path,
+    ) -> Result<()> {
+        self.send_cmd(
+            output_channel,
+            &format!("-file-exec-and-symbols {}", mi_quote(&path.to_string_lossy())),
+        )
+        .await?;
+        self.session.lock().unwrap().executable = Some(LoadedExecutable::stat(path));
+        Ok(())
+    }
+
+    /// Reload the executable if it was rebuilt since `load_executable()` and bind the
+    /// breakpoints again, for an edit-rebuild-restart cycle without a new session.
+    /// Calling it when the file did not change is a no-op
+    pub async fn reload_executable(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<ReloadReport> {
+        let Some(executable) = self.session.lock().unwrap().executable.clone() else {
+            return Err(Error::GdbError("no executable loaded".to_string()));
+        };
+        if !executable.changed_on_disk() {
+            return Ok(ReloadReport::default());
+        }
+        self.load_executable(output_channel, &executable.path).await?;
+
+        // the old addresses are meaningless now, re-create the breakpoints by location
+        let breakpoints = self.breakpoints();
+        if !breakpoints.is_empty() {
+            self.send_cmd(output_channel, "-break-delete").await?;
+        }
+        self.session.lock().unwrap().breakpoints = Default::default();
+        let mut report = ReloadReport {
+            reloaded: true,
+            ..Default::default()
+        };
+        let results = self.restore_breakpoints(output_channel, &breakpoints).await;
+        for (old, result) in breakpoints.iter().zip(results) {
+            match result {
+                Ok(bkpt) => report.rebound.push(bkpt),
+                Err(e) => report.unresolved.push((old.clone(), e)),
+            }
+        }
+        Ok(report)
+    }
+}
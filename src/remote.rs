@@ -0,0 +1,321 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Result};
+use crate::events::DebuggerEvent;
+use crate::msg::{self, MessageRecord, Record, ResultClass, Value};
+use crate::session::SessionState;
+use crate::state::DebuggerState;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Receiver;
+
+/// Token used to tag keepalive queries. Their results are consumed internally and never
+/// reach the output channel
+pub(crate) const KEEPALIVE_TOKEN: u64 = 4294967295;
+
+/// The keepalive query: read a single byte at the current pc, which requires a round trip
+/// to the target
+const KEEPALIVE_QUERY: &str = "-data-read-memory-bytes $pc 1";
+
+/// `KEEPALIVE_QUERY` for gdbs without `-data-read-memory-bytes`
+const KEEPALIVE_QUERY_FALLBACK: &str = "-data-read-memory $pc x 1 1 1";
+
+/// Pid gdb makes up for remote stubs that do not report processes
+const FAKE_REMOTE_PID: &str = "42000";
+
+/// Features of the remote stub, detected when connecting. Minimal stubs (e.g. `gdbstub`
+/// based emulators) often implement only the mandatory packets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteCapabilities {
+    /// The stub reports its threads. When `false`, gdb shows a single synthetic thread
+    pub threads: bool,
+    /// The stub reports a real process id
+    pub process_info: bool,
+}
+
+impl Default for RemoteCapabilities {
+    fn default() -> Self {
+        RemoteCapabilities {
+            threads: true,
+            process_info: true,
+        }
+    }
+}
+
+/// The remote target the session is connected to
+#[derive(Debug, Clone)]
+pub(crate) struct RemoteTarget {
+    pub address: String,
+    /// Connected with `target extended-remote`
+    pub extended: bool,
+    pub connected: bool,
+    pub capabilities: RemoteCapabilities,
+    /// When the outstanding keepalive query was sent
+    pub keepalive_sent: Option<Instant>,
+}
+
+/// Does `text` (an error message or a log line) report a lost connection to the target?
+pub(crate) fn is_disconnect_message(text: &str) -> bool {
+    text.contains("Remote connection closed")
+        || text.contains("Remote communication error")
+        || text.contains("Remote target disconnected")
+}
+
+impl SessionState {
+    /// Mark the remote target as disconnected. Return `true` if it was connected before
+    pub(crate) fn mark_disconnected(&mut self) -> bool {
+        match self.remote.as_mut() {
+            Some(remote) if remote.connected => {
+                remote.connected = false;
+                remote.keepalive_sent = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle the result of a keepalive query. Return `true` if the target was found
+    /// disconnected
+    pub(crate) fn on_keepalive_result(&mut self, record: &MessageRecord<ResultClass>) -> bool {
+        if let Some(remote) = self.remote.as_mut() {
+            remote.keepalive_sent = None;
+        }
+        let msg = record
+            .get("msg")
+            .and_then(Value::unquoted)
+            .unwrap_or_default();
+        record.class == ResultClass::Error && is_disconnect_message(&msg) && self.mark_disconnected()
+    }
+}
+
+impl Debugger {
+    /// Connect to a remote target (`target remote`), e.g. `localhost:1234` or `/dev/ttyUSB0`
+    pub async fn connect_remote(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        address: &str,
+    ) -> Result<()> {
+        self.connect(output_channel, address, false).await
+    }
+
+    /// Connect to a remote target in extended mode (`target extended-remote`), typically a
+    /// `gdbserver --multi`. The server outlives the programs it runs: set the program with
+    /// `set_remote_exec_file()`, then `run()` and `restart()` it, and leave the server
+    /// running with `disconnect_remote()`
+    pub async fn connect_extended_remote(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        address: &str,
+    ) -> Result<()> {
+        self.connect(output_channel, address, true).await
+    }
+
+    async fn connect(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        address: &str,
+        extended: bool,
+    ) -> Result<()> {
+        let mode = if extended { "extended-remote" } else { "remote" };
+        self.send_cmd(output_channel, &format!("-target-select {} {}", mode, address))
+            .await?;
+        let capabilities = self.detect_remote_capabilities(output_channel).await;
+        if !capabilities.process_info {
+            // never signal a local process using the pid gdb made up
+            self.debugee_pid.store(usize::MAX, Ordering::Relaxed);
+        }
+        self.session.lock().unwrap().remote = Some(RemoteTarget {
+            address: address.to_string(),
+            extended,
+            connected: true,
+            capabilities,
+            keepalive_sent: None,
+        });
+        Ok(())
+    }
+
+    /// Probe the stub we just connected to. Failing queries count as missing features
+    async fn detect_remote_capabilities(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> RemoteCapabilities {
+        let mut capabilities = RemoteCapabilities::default();
+        match self.send_cmd(output_channel, "-thread-info").await {
+            Ok(record) => {
+                if let Some(Value::ValueList(threads)) = record.get("threads") {
+                    let synthetic = threads.iter().all(|thread| match thread {
+                        Value::VariableList(vars) => {
+                            msg::find_field(vars, "target-id").and_then(Value::unquoted)
+                                == Some("Remote target".to_string())
+                        }
+                        _ => true,
+                    });
+                    capabilities.threads = !synthetic;
+                }
+            }
+            Err(_) => capabilities.threads = false,
+        }
+        match self.send_cmd(output_channel, "-list-thread-groups").await {
+            Ok(record) => {
+                if let Some(Value::ValueList(groups)) = record.get("groups") {
+                    capabilities.process_info = groups.iter().any(|group| match group {
+                        Value::VariableList(vars) => {
+                            let pid = msg::find_field(vars, "pid").and_then(Value::unquoted);
+                            pid.is_some() && pid.as_deref() != Some(FAKE_REMOTE_PID)
+                        }
+                        _ => false,
+                    });
+                }
+            }
+            Err(_) => capabilities.process_info = false,
+        }
+        tracing::debug!("remote capabilities: {:?}", capabilities);
+        capabilities
+    }
+
+    /// Return the capabilities of the connected remote stub, if any
+    pub fn remote_capabilities(&self) -> Option<RemoteCapabilities> {
+        let session = self.session.lock().unwrap();
+        session.remote.as_ref().map(|r| r.capabilities.clone())
+    }
+
+    /// Is the session connected to a remote target?
+    pub fn is_remote_connected(&self) -> bool {
+        let session = self.session.lock().unwrap();
+        session.remote.as_ref().map(|r| r.connected).unwrap_or(false)
+    }
+
+    /// Is the session connected with `connect_extended_remote()`?
+    pub fn is_extended_remote(&self) -> bool {
+        let session = self.session.lock().unwrap();
+        session.remote.as_ref().is_some_and(|r| r.connected && r.extended)
+    }
+
+    /// The program an extended-remote server runs on `run()`, as a path on the remote
+    /// machine (`set remote exec-file`)
+    pub async fn set_remote_exec_file(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        path: &str,
+    ) -> Result<()> {
+        self.send_cmd(
+            output_channel,
+            &format!("-gdb-set remote exec-file {}", mi_quote(path)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Disconnect from the remote target (`-target-disconnect`) without killing the
+    /// server or the program, which keeps running. `reconnect()` connects again
+    pub async fn disconnect_remote(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        if !self.is_remote_connected() {
+            return Err(crate::dbg::Error::NotConnected);
+        }
+        self.disable_keepalive();
+        self.send_cmd(output_channel, "-target-disconnect").await?;
+        self.session.lock().unwrap().mark_disconnected();
+        self.reset_session();
+        Ok(())
+    }
+
+    /// Re-establish the connection to the last remote target and re-apply the
+    /// breakpoints recorded in the breakpoint table
+    pub async fn reconnect(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        let (address, extended) = {
+            let session = self.session.lock().unwrap();
+            match session.remote.as_ref() {
+                Some(remote) => (remote.address.clone(), remote.extended),
+                None => return Err(crate::dbg::Error::NotConnected),
+            }
+        };
+        self.connect(output_channel, &address, extended).await?;
+
+        let breakpoints = self.breakpoints();
+        self.send_cmd(output_channel, "-break-delete").await?;
+        self.session.lock().unwrap().breakpoints = Default::default();
+        for result in self.restore_breakpoints(output_channel, &breakpoints).await {
+            if let Err(e) = result {
+                tracing::debug!("failed to re-apply breakpoint: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Periodically query the remote target while it is stopped, and emit a
+    /// `DebuggerEvent::TargetDisconnected` record once it stops answering.
+    /// A query left unanswered for two intervals is considered a silent disconnect
+    pub fn enable_keepalive(&self, interval: Duration) {
+        self.disable_keepalive();
+        let stdin = self.stdin.clone();
+        let events = self.events.clone();
+        let state = self.state.clone();
+        let session = self.session.clone();
+        let query = match self.supports(|c| c.data_read_memory_bytes) {
+            true => KEEPALIVE_QUERY,
+            false => KEEPALIVE_QUERY_FALLBACK,
+        };
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let disconnected = {
+                    let mut session = session.lock().unwrap();
+                    let Some(remote) = session.remote.as_mut() else {
+                        continue;
+                    };
+                    if !remote.connected {
+                        continue;
+                    }
+                    match remote.keepalive_sent {
+                        Some(sent) if sent.elapsed() > interval * 2 => session.mark_disconnected(),
+                        Some(_) => false,
+                        None => {
+                            if *state.borrow() != DebuggerState::Running {
+                                remote.keepalive_sent = Some(Instant::now());
+                                let cmd = format!("{}{}\n", KEEPALIVE_TOKEN, query);
+                                if stdin.try_send(cmd).is_err() {
+                                    remote.keepalive_sent = None;
+                                }
+                            }
+                            false
+                        }
+                    }
+                };
+                if disconnected {
+                    let event = DebuggerEvent::TargetDisconnected {
+                        reason: "keepalive query timed out".to_string(),
+                    };
+                    if events.send(Record::Event(event)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        *self.keepalive.lock().unwrap() = Some(handle);
+    }
+
+    /// Stop the keepalive task started by `enable_keepalive()`
+    pub fn disable_keepalive(&self) {
+        if let Some(handle) = self.keepalive.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Result};
+use crate::msg::{self, find_field, Value, Variable};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc::Receiver;
+
+/// Levels of children expanded before a value is handed to its renderer
+const RENDER_DEPTH: usize = 2;
+
+/// Formats values of the types it is registered for, see `Debugger::register_renderer()`.
+/// The value is passed in the format of `Debugger::dump_variables()`:
+/// `{"type": "std::vector<int>", "value": "{...}", "children": {...}}`, children expanded
+/// two levels deep. Return `None` to keep gdb's own rendering
+pub trait ValueRenderer: Send + Sync {
+    fn render(&self, value: &serde_json::Value) -> Option<String>;
+}
+
+impl<F: Fn(&serde_json::Value) -> Option<String> + Send + Sync> ValueRenderer for F {
+    fn render(&self, value: &serde_json::Value) -> Option<String> {
+        self(value)
+    }
+}
+
+/// Does `text` match `pattern`, where `*` matches any sequence of characters?
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // where the last `*` was seen, and the text position it currently matches up to
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// The type a renderer is looked up with: qualifiers and references don't change how a
+/// value is displayed
+fn base_type(type_name: &str) -> &str {
+    let mut name = type_name.trim();
+    for qualifier in ["const ", "volatile "] {
+        name = name.strip_prefix(qualifier).unwrap_or(name);
+    }
+    name.trim_end_matches('&').trim_end()
+}
+
+/// Renderers keyed by type name patterns, e.g. `alloc::string::String` or `std::vector<*>`
+#[derive(Default, Clone)]
+pub(crate) struct ValueRenderers {
+    entries: Vec<(String, Arc<dyn ValueRenderer>)>,
+}
+
+impl ValueRenderers {
+    /// Render the types matching `pattern` with `renderer`. Patterns registered last are
+    /// tried first
+    pub fn register(&mut self, pattern: &str, renderer: Arc<dyn ValueRenderer>) {
+        self.entries.push((pattern.to_string(), renderer));
+    }
+
+    /// The renderer for `type_name`
+    pub fn find(&self, type_name: &str) -> Option<Arc<dyn ValueRenderer>> {
+        let type_name = base_type(type_name);
+        self.entries
+            .iter()
+            .rev()
+            .find(|(pattern, _)| glob_match(pattern, type_name))
+            .map(|(_, renderer)| renderer.clone())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Render `node` and its children (bottom up) in place, replacing their `value`
+    pub fn apply(&self, node: &mut serde_json::Value) {
+        if let Some(serde_json::Value::Object(children)) = node.get_mut("children") {
+            for child in children.values_mut() {
+                self.apply(child);
+            }
+        }
+        let renderer = node["type"].as_str().and_then(|kind| self.find(kind));
+        if let Some(value) = renderer.and_then(|renderer| renderer.render(node)) {
+            node["value"] = serde_json::Value::String(value);
+        }
+    }
+}
+
+pub(crate) type SharedRenderers = Arc<Mutex<ValueRenderers>>;
+
+impl Debugger {
+    /// Display the values whose type matches `pattern` with `renderer`. `*` in `pattern`
+    /// matches any sequence, e.g. `std::vector<*>`. Renderers apply to `locals()`,
+    /// `frame_args()`, `evaluate()` and `dump_variables()`
+    pub fn register_renderer<R: ValueRenderer + 'static>(&self, pattern: &str, renderer: R) {
+        self.renderers
+            .lock()
+            .unwrap()
+            .register(pattern, Arc::new(renderer));
+    }
+
+    /// Remove all the renderers
+    pub fn clear_renderers(&self) {
+        *self.renderers.lock().unwrap() = ValueRenderers::default();
+    }
+
+    pub(crate) fn renderers(&self) -> ValueRenderers {
+        self.renderers.lock().unwrap().clone()
+    }
+
+    /// Render varobj `varobj` described by `vars`, if a renderer handles its type
+    pub(crate) async fn render_varobj(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        renderers: &ValueRenderers,
+        varobj: &str,
+        vars: &[Variable],
+    ) -> Result<Option<String>> {
+        let kind = find_field(vars, "type").and_then(Value::unquoted);
+        let Some(renderer) = kind.and_then(|kind| renderers.find(&kind)) else {
+            return Ok(None);
+        };
+        let mut node = self
+            .varobj_to_json(output_channel, varobj, vars, RENDER_DEPTH)
+            .await?;
+        if let Some(serde_json::Value::Object(children)) = node.get_mut("children") {
+            for child in children.values_mut() {
+                renderers.apply(child);
+            }
+        }
+        Ok(renderer.render(&node))
+    }
+
+    /// Render `expression` evaluated with the frame `options` (see `context_options()`),
+    /// if a renderer handles its type
+    pub(crate) async fn render_expression(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        renderers: &ValueRenderers,
+        options: &str,
+        expression: &str,
+    ) -> Result<Option<String>> {
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-var-create {} - * {}", options, mi_quote(expression)),
+            )
+            .await?;
+        let Some(varobj) = record.get("name").and_then(Value::unquoted) else {
+            return Ok(None);
+        };
+        let rendered = self
+            .render_varobj(output_channel, renderers, &varobj, &record.content)
+            .await;
+        self.send_cmd(output_channel, &format!("-var-delete {}", varobj))
+            .await?;
+        rendered
+    }
+}
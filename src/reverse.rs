@@ -0,0 +1,210 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Result};
+use crate::msg;
+use std::ops::RangeInclusive;
+use tokio::sync::mpsc::Receiver;
+
+/// How execution is recorded for reverse debugging, see `Debugger::record_start()`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordMethod {
+    /// Record every instruction with its side effects (`record full`). Slow, but
+    /// everything can be executed in reverse
+    #[default]
+    Full,
+    /// Record the control flow with the processor's branch tracing (`record btrace`):
+    /// fast, but memory and registers other than the pc can't be restored
+    Btrace,
+}
+
+impl RecordMethod {
+    fn command(self) -> &'static str {
+        match self {
+            RecordMethod::Full => "record full",
+            RecordMethod::Btrace => "record btrace",
+        }
+    }
+}
+
+/// An instruction of `Debugger::instruction_history()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedInstruction {
+    /// The instruction number in the execution log
+    pub number: u64,
+    pub addr: String,
+    /// The function and offset, e.g. `main+4`
+    pub symbol: Option<String>,
+    /// The disassembled instruction
+    pub instruction: String,
+    /// The replay position is at this instruction
+    pub current: bool,
+}
+
+impl RecordedInstruction {
+    /// Parse a line of `record instruction-history`:
+    /// `2\t=> 0x00000000004004ed <main+11>:\tjmp    0x4004f6 <main+20>`
+    pub fn from_line(line: &str) -> Option<RecordedInstruction> {
+        let (number, rest) = line.split_once('\t')?;
+        let rest = rest.trim_start();
+        let (current, rest) = match rest.strip_prefix("=>") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, rest),
+        };
+        let (location, instruction) = rest.split_once(":\t")?;
+        let (addr, symbol) = match location.split_once(' ') {
+            Some((addr, symbol)) => (addr, symbol.strip_prefix('<')?.strip_suffix('>')),
+            None => (location, None),
+        };
+        Some(RecordedInstruction {
+            number: number.trim().parse().ok()?,
+            addr: addr.to_string(),
+            symbol: symbol.map(str::to_string),
+            instruction: instruction.to_string(),
+            current,
+        })
+    }
+}
+
+/// A function call of `Debugger::function_call_history()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedCall {
+    /// The call number in the execution log
+    pub number: u64,
+    pub function: String,
+    /// The source lines executed, e.g. `foo.c:3,5`
+    pub source: Option<String>,
+}
+
+impl RecordedCall {
+    /// Parse a line of `record function-call-history /l`: `3\tfoo\tat foo.c:3,5`
+    pub fn from_line(line: &str) -> Option<RecordedCall> {
+        let mut fields = line.split('\t');
+        let number = fields.next()?.trim().parse().ok()?;
+        let function = fields.next()?.trim().to_string();
+        let source = fields
+            .next()
+            .map(|source| source.trim_start_matches("at ").to_string());
+        Some(RecordedCall {
+            number,
+            function,
+            source,
+        })
+    }
+}
+
+/// `begin,end` of the history commands, gdb picks the lines around the replay position
+/// when `None`
+fn history_range(range: Option<RangeInclusive<u64>>) -> String {
+    match range {
+        Some(range) => format!(" {},{}", range.start(), range.end()),
+        None => String::new(),
+    }
+}
+
+/// Reverse debugging: the program's execution is recorded while it runs forward, and
+/// can be replayed backwards. Each method returns once gdb acknowledged the command,
+/// see `wait_for_stop()`. A `*stopped,reason="no-history"` record is received when the
+/// beginning of the recording is reached
+impl Debugger {
+    /// Start recording the program's execution with `method`. The program must be
+    /// running. The capabilities are detected again, `reverse` is set once recording
+    pub async fn record_start(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        method: RecordMethod,
+    ) -> Result<()> {
+        self.console(output_channel, method.command()).await?;
+        if let Err(e) = self.detect_capabilities(output_channel).await {
+            tracing::debug!("failed to detect gdb's capabilities: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Stop recording and discard the execution log. When replaying, the program
+    /// continues from the replay position
+    pub async fn record_stop(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.console(output_channel, "record stop").await?;
+        if let Err(e) = self.detect_capabilities(output_channel).await {
+            tracing::debug!("failed to detect gdb's capabilities: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Run backwards until a breakpoint or the beginning of the recording
+    /// (`-exec-continue --reverse`)
+    pub async fn reverse_continue(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        self.exec(output_channel, "-exec-continue --reverse").await
+    }
+
+    /// Step backwards into the previous source line (`-exec-step --reverse`)
+    pub async fn reverse_step_into(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        self.exec(output_channel, "-exec-step --reverse").await
+    }
+
+    /// Step backwards over the previous source line (`-exec-next --reverse`)
+    pub async fn reverse_step_over(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        self.exec(output_channel, "-exec-next --reverse").await
+    }
+
+    /// Run backwards to the call of the current function (`-exec-finish --reverse`)
+    pub async fn reverse_finish(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.exec(output_channel, "-exec-finish --reverse").await
+    }
+
+    /// Execute one machine instruction backwards (`-exec-step-instruction --reverse`)
+    pub async fn reverse_step_instruction(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        self.exec(output_channel, "-exec-step-instruction --reverse")
+            .await
+    }
+
+    /// The recorded instructions numbered `range`, or the ones around the replay
+    /// position. Only available with `RecordMethod::Btrace`
+    pub async fn instruction_history(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        range: Option<RangeInclusive<u64>>,
+    ) -> Result<Vec<RecordedInstruction>> {
+        let cmd = format!("record instruction-history{}", history_range(range));
+        let output = self.console(output_channel, &cmd).await?;
+        Ok(output.lines().filter_map(RecordedInstruction::from_line).collect())
+    }
+
+    /// The recorded function calls numbered `range`, or the ones around the replay
+    /// position. Only available with `RecordMethod::Btrace`
+    pub async fn function_call_history(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        range: Option<RangeInclusive<u64>>,
+    ) -> Result<Vec<RecordedCall>> {
+        let cmd = format!("record function-call-history /l{}", history_range(range));
+        let output = self.console(output_channel, &cmd).await?;
+        Ok(output.lines().filter_map(RecordedCall::from_line).collect())
+    }
+}
@@ -0,0 +1,82 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{spawn_command, BackendIo, DebuggerBackend, MiVersion, Result};
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Replay a recording of rr (https://rr-project.org) with `rr replay`, which starts gdb
+/// connected to the replayed program: it can be executed backwards without
+/// `Debugger::record_start()`. Start it with `Debugger::start_with_backend()`
+#[derive(Debug, Clone)]
+pub struct RrBackend {
+    /// The rr binary
+    pub program: String,
+    /// The trace directory, rr's latest recording when `None`
+    pub trace_dir: Option<PathBuf>,
+    /// The gdb binary rr starts (`--debugger`), rr's default when `None`
+    pub gdb: Option<String>,
+    /// Options passed to `rr replay`, e.g. `--onprocess`
+    pub rr_args: Vec<String>,
+    /// Options passed to gdb
+    pub args: Vec<String>,
+    pub mi_version: MiVersion,
+}
+
+impl RrBackend {
+    /// Replay the recording in `trace_dir`
+    pub fn new(trace_dir: impl Into<PathBuf>) -> Self {
+        RrBackend {
+            trace_dir: Some(trace_dir.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for RrBackend {
+    /// The binary named by the `RR_BINARY` environment variable, or `rr`, replaying the
+    /// latest recording
+    fn default() -> Self {
+        RrBackend {
+            program: ::std::env::var("RR_BINARY").unwrap_or("rr".to_string()),
+            trace_dir: None,
+            gdb: None,
+            rr_args: Vec::new(),
+            args: Vec::new(),
+            mi_version: MiVersion::Latest,
+        }
+    }
+}
+
+impl DebuggerBackend for RrBackend {
+    fn spawn(&mut self) -> Result<BackendIo> {
+        let mut command = Command::new(&self.program);
+        command.arg("replay").args(&self.rr_args);
+        if let Some(gdb) = &self.gdb {
+            command.arg("--debugger").arg(gdb);
+        }
+        if let Some(trace_dir) = &self.trace_dir {
+            command.arg(trace_dir);
+        }
+        // everything after `--` is passed to gdb
+        command
+            .arg("--")
+            .arg(self.mi_version.interpreter())
+            .args(&self.args);
+        spawn_command(command, false)
+    }
+}
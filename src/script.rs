@@ -0,0 +1,165 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::{Breakpoint, BreakpointOptions};
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::events::StopEvent;
+use crate::msg::{self, MessageRecord, ResultClass, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// A step of a `Script`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    BreakAt(String),
+    Run,
+    Continue,
+    StepOver,
+    StepInto,
+    StepOut,
+    ExpectStop,
+    Eval(String),
+    /// A raw MI command
+    Command(String),
+}
+
+/// What a step produced, see `Script::execute()`
+#[derive(Debug, Clone)]
+pub enum ScriptOutput {
+    Breakpoint(Breakpoint),
+    Stop(StopEvent),
+    Value { expression: String, value: String },
+    Record(MessageRecord<ResultClass>),
+}
+
+/// A scripted debug session, e.g. for reproducible bug reports or integration tests:
+///
+/// ```ignore
+/// let outputs = Script::new()
+///     .break_at("main.rs:10")
+///     .run()
+///     .expect_stop()
+///     .eval("x")
+///     .execute(&dbg, &mut rx)
+///     .await?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+impl Script {
+    pub fn new() -> Self {
+        Script::default()
+    }
+
+    /// Insert a breakpoint at `location`
+    pub fn break_at(self, location: &str) -> Self {
+        self.step(Step::BreakAt(location.to_string()))
+    }
+
+    pub fn run(self) -> Self {
+        self.step(Step::Run)
+    }
+
+    pub fn continue_(self) -> Self {
+        self.step(Step::Continue)
+    }
+
+    pub fn step_over(self) -> Self {
+        self.step(Step::StepOver)
+    }
+
+    pub fn step_into(self) -> Self {
+        self.step(Step::StepInto)
+    }
+
+    pub fn step_out(self) -> Self {
+        self.step(Step::StepOut)
+    }
+
+    /// Wait for the program to stop
+    pub fn expect_stop(self) -> Self {
+        self.step(Step::ExpectStop)
+    }
+
+    /// Evaluate `expression` in the selected frame
+    pub fn eval(self, expression: &str) -> Self {
+        self.step(Step::Eval(expression.to_string()))
+    }
+
+    /// Send a raw MI command
+    pub fn cmd(self, cmd: &str) -> Self {
+        self.step(Step::Command(cmd.to_string()))
+    }
+
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Run the steps in order and return the breakpoints, stops, values and records they
+    /// produced. Execution stops at the first failing step
+    pub async fn execute(
+        &self,
+        dbg: &Debugger,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<Vec<ScriptOutput>> {
+        let mut outputs = Vec::new();
+        for step in &self.steps {
+            match step {
+                Step::BreakAt(location) => {
+                    let bkpt = dbg
+                        .insert_breakpoint(output_channel, location, &BreakpointOptions::default())
+                        .await?;
+                    outputs.push(ScriptOutput::Breakpoint(bkpt));
+                }
+                Step::Run => dbg.run(output_channel).await?,
+                Step::Continue => dbg.continue_(output_channel).await?,
+                Step::StepOver => dbg.step_over(output_channel).await?,
+                Step::StepInto => dbg.step_into(output_channel).await?,
+                Step::StepOut => dbg.step_out(output_channel).await?,
+                Step::ExpectStop => {
+                    outputs.push(ScriptOutput::Stop(dbg.wait_for_stop(output_channel).await?));
+                }
+                Step::Eval(expression) => {
+                    let record = dbg
+                        .send_cmd(
+                            output_channel,
+                            &format!("-data-evaluate-expression {}", mi_quote(expression)),
+                        )
+                        .await?;
+                    let value = record
+                        .get("value")
+                        .and_then(Value::unquoted)
+                        .ok_or(Error::UnexpectedResponse)?;
+                    outputs.push(ScriptOutput::Value {
+                        expression: expression.clone(),
+                        value,
+                    });
+                }
+                Step::Command(cmd) => {
+                    outputs.push(ScriptOutput::Record(dbg.send_cmd(output_channel, cmd).await?));
+                }
+            }
+        }
+        Ok(outputs)
+    }
+}
@@ -0,0 +1,242 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::{Breakpoint, BreakpointTable};
+use crate::capabilities::Capabilities;
+use crate::dbg::{InterruptStrategy, MiDialect};
+use crate::debuginfod::DebuginfodState;
+use crate::events::{ExitStatus, Frame, StopEvent};
+use crate::lifecycle::ExitHook;
+use crate::modules::{Module, ModuleTable};
+use crate::msg::{AsyncClass, AsyncRecord, MessageRecord, ResultClass, Value};
+use crate::reload::LoadedExecutable;
+use crate::remote::RemoteTarget;
+use crate::state::DebuggerState;
+use crate::timeline::Timeline;
+use crate::version::Version;
+use crate::watchpoints::out_of_scope_watchpoint;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Short description of a breakpoint, as shown in status bars
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakpointSummary {
+    pub number: String,
+    pub location: Option<String>,
+    pub enabled: bool,
+    pub hit_count: u32,
+}
+
+/// A point-in-time copy of the session state. Obtained with `Debugger::snapshot()`
+#[derive(Debug, Clone)]
+pub struct SessionSnapshotView {
+    pub state: DebuggerState,
+    /// Debugging a core file
+    pub post_mortem: bool,
+    pub selected_thread: Option<String>,
+    pub selected_frame: Option<u32>,
+    pub debuggee_pid: Option<usize>,
+    pub breakpoints: Vec<BreakpointSummary>,
+    /// Breakpoint group names and the numbers of their members
+    pub breakpoint_groups: Vec<(String, Vec<String>)>,
+    pub last_stop: Option<StopEvent>,
+}
+
+/// The threads reported running by `*running` records. In all-stop mode gdb reports
+/// `thread-id="all"`, in non-stop mode threads run and stop individually
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RunningThreads {
+    /// All the threads were resumed
+    pub all: bool,
+    /// Threads resumed individually
+    pub threads: BTreeSet<String>,
+    /// Threads that stopped since all were resumed
+    pub stopped: BTreeSet<String>,
+}
+
+impl RunningThreads {
+    pub fn on_running(&mut self, thread_id: Option<&str>) {
+        match thread_id {
+            Some("all") | None => {
+                self.all = true;
+                self.threads.clear();
+                self.stopped.clear();
+            }
+            Some(id) => {
+                self.stopped.remove(id);
+                self.threads.insert(id.to_string());
+            }
+        }
+    }
+
+    /// `stopped_threads` is the `stopped-threads` field: `"all"` or a list of thread ids.
+    /// Missing in all-stop mode, where all the threads stop
+    pub fn on_stopped(&mut self, stopped_threads: Option<&Value>) {
+        let Some(Value::ValueList(ids)) = stopped_threads else {
+            *self = RunningThreads::default();
+            return;
+        };
+        for id in ids.iter().filter_map(Value::unquoted) {
+            self.threads.remove(&id);
+            if self.all {
+                self.stopped.insert(id);
+            }
+        }
+    }
+
+    pub fn contains(&self, thread_id: &str) -> bool {
+        (self.all && !self.stopped.contains(thread_id)) || self.threads.contains(thread_id)
+    }
+
+    /// Only some threads run (non-stop mode): gdb accepts commands
+    pub fn is_partial(&self) -> bool {
+        !self.all && !self.threads.is_empty()
+    }
+}
+
+/// Session state maintained by the reader task from gdb's async records
+#[derive(Debug, Default)]
+pub(crate) struct SessionState {
+    pub selected_thread: Option<String>,
+    pub selected_frame: Option<u32>,
+    pub breakpoints: BreakpointTable,
+    pub last_stop: Option<StopEvent>,
+    pub remote: Option<RemoteTarget>,
+    /// The executable loaded with `Debugger::load_executable()`
+    pub executable: Option<LoadedExecutable>,
+    /// gdb was given a program, with `load_executable()` or any other way
+    pub program_loaded: bool,
+    /// The inferior's working directory
+    pub cwd: Option<String>,
+    /// Source path substitution rules, `(from, to)`
+    pub substitute_paths: Vec<(String, String)>,
+    /// See `Debugger::set_source_directories()`
+    pub source_directories: Vec<String>,
+    /// See `Debugger::set_sysroot()`
+    pub sysroot: Option<String>,
+    /// Commands run before the session ends, see `Debugger::add_exit_hook()`
+    pub exit_hooks: Vec<ExitHook>,
+    /// See `Debugger::set_interrupt_strategy()`
+    pub interrupt_strategy: InterruptStrategy,
+    /// Set while recording, see `Debugger::start_timeline()`
+    pub timeline: Option<Timeline>,
+    pub running: RunningThreads,
+    /// The pids of the inferiors whose process is alive, by thread group id
+    pub inferior_pids: BTreeMap<String, u32>,
+    /// How the last run of the program ended, cleared when it runs again
+    pub exit_status: Option<ExitStatus>,
+    pub dialect: MiDialect,
+    pub debuginfod: DebuginfodState,
+    /// See `Debugger::capabilities()`
+    pub capabilities: Option<Capabilities>,
+    /// See `Debugger::gdb_version()`
+    pub gdb_version: Option<Version>,
+    /// See `Debugger::modules()`
+    pub modules: ModuleTable,
+}
+
+impl SessionState {
+    /// Update the state from an async record
+    pub fn on_async(&mut self, record: &AsyncRecord) {
+        let (AsyncRecord::Exec(msg) | AsyncRecord::Status(msg) | AsyncRecord::Notify(msg)) = record;
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.on_async(msg);
+        }
+        match msg.class {
+            AsyncClass::Stopped => {
+                if let Some(number) = out_of_scope_watchpoint(msg) {
+                    self.breakpoints.remove(&number);
+                }
+                self.running.on_stopped(msg.get("stopped-threads"));
+                let stop = StopEvent::from_record(msg);
+                if stop.thread_id.is_some() {
+                    self.selected_thread = stop.thread_id.clone();
+                }
+                self.selected_frame = stop.frame.as_ref().map(|f| f.level.unwrap_or(0));
+                self.last_stop = Some(stop);
+                if let Some(status) = ExitStatus::from_record(msg) {
+                    self.exit_status = Some(status);
+                }
+            }
+            AsyncClass::ThreadGroupStarted => {
+                let id = msg.get("id").and_then(Value::unquoted);
+                let pid = msg.get("pid").and_then(Value::unquoted);
+                if let (Some(id), Some(pid)) = (id, pid.and_then(|pid| pid.parse().ok())) {
+                    self.inferior_pids.insert(id, pid);
+                }
+            }
+            AsyncClass::ThreadGroupExited => {
+                if let Some(id) = msg.get("id").and_then(Value::unquoted) {
+                    self.inferior_pids.remove(&id);
+                    self.modules.remove_thread_group(&id);
+                }
+                // `*stopped` follows with the signal, if any
+                if let Some(status) = ExitStatus::from_record(msg) {
+                    self.exit_status = Some(status);
+                }
+            }
+            AsyncClass::Running => {
+                self.exit_status = None;
+                self.selected_frame = None;
+                let thread_id = msg.get("thread-id").and_then(Value::unquoted);
+                self.running.on_running(thread_id.as_deref());
+            }
+            AsyncClass::ThreadSelected => {
+                self.selected_thread = msg.get("id").and_then(Value::unquoted);
+                self.selected_frame = msg
+                    .get("frame")
+                    .and_then(Frame::from_value)
+                    .and_then(|f| f.level);
+            }
+            AsyncClass::BreakpointCreated | AsyncClass::BreakpointModified => {
+                if let Some(bkpt) = Breakpoint::from_results(&msg.content) {
+                    self.breakpoints.insert(bkpt);
+                }
+            }
+            AsyncClass::BreakpointDeleted => {
+                if let Some(id) = msg.get("id").and_then(Value::unquoted) {
+                    self.breakpoints.remove(&id);
+                }
+            }
+            AsyncClass::LibraryLoaded => {
+                if let Some(module) = Module::from_record(msg) {
+                    self.modules.insert(module);
+                }
+            }
+            AsyncClass::LibraryUnloaded => {
+                if let Some(id) = msg.get("id").and_then(Value::unquoted) {
+                    let thread_group = msg.get("thread-group").and_then(Value::unquoted);
+                    self.modules.remove(&id, thread_group.as_deref());
+                }
+            }
+            AsyncClass::Download | AsyncClass::Unknown(_) => {}
+        }
+    }
+
+    /// Update the state from a result record. Results of `-break-insert` and friends
+    /// carry the breakpoint that was created
+    pub fn on_result(&mut self, record: &MessageRecord<ResultClass>) {
+        if let Some(timeline) = self.timeline.as_mut() {
+            timeline.on_result(record);
+        }
+        if record.class != ResultClass::Done {
+            return;
+        }
+        if let Some(bkpt) = Breakpoint::from_results(&record.content) {
+            self.breakpoints.insert(bkpt);
+        }
+    }
+}
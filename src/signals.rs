@@ -0,0 +1,95 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::Debugger;
+use crate::{process_group, signal, signal_process_group};
+use std::io;
+use std::sync::atomic::Ordering;
+use sysinfo::Signal;
+
+/// A signal for the debuggee, see `Debugger::send_signal()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebugSignal {
+    /// `SIGINT`. On Windows, a debug break in the process
+    Interrupt,
+    /// `SIGTERM`. On Windows, `CTRL_BREAK_EVENT` for console programs, otherwise the
+    /// process is terminated like with `Kill`
+    Terminate,
+    /// `SIGKILL`
+    Kill,
+    /// `SIGUSR1`, not available on Windows
+    User1,
+    /// `SIGUSR2`, not available on Windows
+    User2,
+}
+
+impl Debugger {
+    /// Send `sig` to the debuggee. Fails with `ErrorKind::NotFound` if its pid is unknown,
+    /// `ErrorKind::Unsupported` if the signal is not available on this platform, or the
+    /// error of sending it
+    pub fn send_signal(&self, sig: DebugSignal) -> io::Result<()> {
+        let signal_id = platform_signal(sig)?;
+        signal(self.signal_target()?, signal_id)
+    }
+
+    /// Send `sig` to the debuggee's process group, reaching the processes it started too.
+    /// Fails with `ErrorKind::InvalidInput` if the debuggee shares the host's process
+    /// group, or in the same cases as `send_signal()`
+    pub fn send_signal_to_group(&self, sig: DebugSignal) -> io::Result<()> {
+        let signal_id = platform_signal(sig)?;
+        let pid = self.signal_target()?;
+        let pgid = process_group(pid)?;
+        if process_group(std::process::id() as usize).is_ok_and(|own| own == pgid) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the debuggee is in our process group",
+            ));
+        }
+        signal_process_group(pgid, signal_id)
+    }
+
+    /// The pid of the debuggee, the process signals are sent to
+    fn signal_target(&self) -> io::Result<usize> {
+        match self.debugee_pid.load(Ordering::Relaxed) {
+            usize::MAX => Err(io::Error::new(io::ErrorKind::NotFound, "no debuggee")),
+            pid => Ok(pid),
+        }
+    }
+}
+
+/// The signal sent for `sig`. Fails with `ErrorKind::Unsupported` when the platform has no
+/// equivalent
+fn platform_signal(sig: DebugSignal) -> io::Result<Signal> {
+    let signal = match sig {
+        DebugSignal::Interrupt => Some(Signal::Interrupt),
+        DebugSignal::Kill => Some(Signal::Kill),
+        DebugSignal::Terminate => Some(Signal::Term),
+        #[cfg(windows)]
+        DebugSignal::User1 | DebugSignal::User2 => None,
+        #[cfg(not(windows))]
+        DebugSignal::User1 => Some(Signal::User1),
+        #[cfg(not(windows))]
+        DebugSignal::User2 => Some(Signal::User2),
+    };
+    signal.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{:?} is not available on this platform", sig),
+        )
+    })
+}
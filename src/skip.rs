@@ -0,0 +1,160 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{cli_quote, Debugger, Error, Result};
+use crate::msg;
+use tokio::sync::mpsc::Receiver;
+
+/// What `step_into()` steps over instead of into, see `Debugger::add_skip()`. The file
+/// and function criteria can be combined, both must match then
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SkipSpec {
+    /// A source file, or a glob pattern with `file_is_glob`
+    pub file: Option<String>,
+    pub file_is_glob: bool,
+    /// A function name, or a regular expression with `function_is_regex`
+    pub function: Option<String>,
+    pub function_is_regex: bool,
+}
+
+impl SkipSpec {
+    /// Skip function `name`
+    pub fn function(name: &str) -> Self {
+        SkipSpec {
+            function: Some(name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Skip the functions matching `regex`, e.g. `^std::`
+    pub fn function_regex(regex: &str) -> Self {
+        SkipSpec {
+            function: Some(regex.to_string()),
+            function_is_regex: true,
+            ..Default::default()
+        }
+    }
+
+    /// Skip the functions of source file `path`
+    pub fn file(path: &str) -> Self {
+        SkipSpec {
+            file: Some(path.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Skip the functions of the source files matching `glob`, e.g. `/usr/include/*`
+    pub fn file_glob(glob: &str) -> Self {
+        SkipSpec {
+            file: Some(glob.to_string()),
+            file_is_glob: true,
+            ..Default::default()
+        }
+    }
+
+    /// The `skip` CLI command
+    fn command(&self) -> String {
+        let mut cmd = "skip".to_string();
+        if let Some(file) = &self.file {
+            let flag = if self.file_is_glob { "-gfi" } else { "-fi" };
+            cmd.push_str(&format!(" {} {}", flag, cli_quote(file)));
+        }
+        if let Some(function) = &self.function {
+            let flag = if self.function_is_regex { "-rfu" } else { "-fu" };
+            cmd.push_str(&format!(" {} {}", flag, cli_quote(function)));
+        }
+        cmd
+    }
+}
+
+/// A skip of `Debugger::list_skips()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Skip {
+    pub number: u32,
+    pub enabled: bool,
+    pub spec: SkipSpec,
+}
+
+impl Skip {
+    /// Parse a line of `info skip`:
+    /// `1       y      y /usr/include/c++/*   n <none>`
+    pub fn from_line(line: &str) -> Option<Skip> {
+        let mut rest = line.trim();
+        let mut columns = Vec::new();
+        // the last column, the function, may contain spaces
+        for _ in 0..5 {
+            let (column, tail) = rest.split_once(char::is_whitespace)?;
+            columns.push(column);
+            rest = tail.trim_start();
+        }
+        let text = |s: &str| (s != "<none>").then(|| s.to_string());
+        Some(Skip {
+            number: columns[0].parse().ok()?,
+            enabled: columns[1] == "y",
+            spec: SkipSpec {
+                file: text(columns[3]),
+                file_is_glob: columns[2] == "y",
+                function: text(rest),
+                function_is_regex: columns[4] == "y",
+            },
+        })
+    }
+}
+
+impl Debugger {
+    /// Never step into the functions described by `spec`, e.g. the standard library's.
+    /// Return the new skip
+    pub async fn add_skip(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        spec: &SkipSpec,
+    ) -> Result<Skip> {
+        if spec.file.is_none() && spec.function.is_none() {
+            // gdb would skip the current function
+            let msg = "skip: no file or function".to_string();
+            return Err(Error::InvalidArgument(msg));
+        }
+        self.console(output_channel, &spec.command()).await?;
+        // gdb does not print the number of the skip, it is the highest one
+        let skips = self.list_skips(output_channel).await?;
+        skips
+            .into_iter()
+            .max_by_key(|skip| skip.number)
+            .ok_or(Error::UnexpectedResponse)
+    }
+
+    /// The skips, in creation order (`info skip`)
+    pub async fn list_skips(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<Vec<Skip>> {
+        let output = self.console(output_channel, "info skip").await?;
+        // the first line is the header
+        Ok(output.lines().skip(1).filter_map(Skip::from_line).collect())
+    }
+
+    /// Delete skip `number`
+    pub async fn delete_skip(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        number: u32,
+    ) -> Result<()> {
+        self.console(output_channel, &format!("skip delete {}", number))
+            .await?;
+        Ok(())
+    }
+}
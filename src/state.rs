@@ -0,0 +1,144 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{strip_token, Debugger, Error, Result};
+use crate::msg::{AsyncClass, AsyncRecord, Record, ResultClass, Value};
+use tokio::sync::watch;
+
+/// The state of the debugger, tracked from gdb's output. The transitions are:
+///
+/// ```text
+/// Starting --(any MI output)--> Idle --(run)--> Running <--(continue, step...)--+
+///                                                 |                             |
+///                                                 +--(*stopped)--> Stopped -----+
+///                                                 +--(*stopped,reason="exited*")--> Exited
+/// any state --(^exit or gdb died)--> Dead
+/// ```
+///
+/// `run()` (and `restart()`) is accepted in `Starting`, `Idle`, `Stopped` and `Exited`, once
+/// a program is known to gdb. The other execution commands need a stopped thread: state
+/// `Stopped`, or `Running` with some threads stopped in non-stop mode. Anything else fails
+/// with `Error::InvalidState` (or `Error::NoExecutable`) before reaching gdb
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DebuggerState {
+    /// gdb was launched and did not talk MI yet
+    Starting,
+    /// gdb is ready, no program is running
+    Idle,
+    /// The program is running, gdb only accepts a few commands
+    Running,
+    /// The program is stopped (breakpoint, signal, end of step...)
+    Stopped,
+    /// The program exited
+    Exited,
+    /// gdb exited, nothing can be done with this debugger anymore
+    Dead,
+}
+
+impl DebuggerState {
+    /// The state after gdb emitted `record`
+    pub fn next(self, record: &Record) -> DebuggerState {
+        let current = match (self, record) {
+            (DebuggerState::Dead, _) => return DebuggerState::Dead,
+            // any MI output means gdb is up
+            (DebuggerState::Starting, Record::Raw(_) | Record::Event(_)) => self,
+            (DebuggerState::Starting, _) => DebuggerState::Idle,
+            _ => self,
+        };
+        match record {
+            Record::Result(result) => match result.class {
+                ResultClass::Running => DebuggerState::Running,
+                ResultClass::Exit => DebuggerState::Dead,
+                _ => current,
+            },
+            Record::Async(AsyncRecord::Exec(exec)) => match exec.class {
+                AsyncClass::Running => DebuggerState::Running,
+                AsyncClass::Stopped => {
+                    let reason = exec.get("reason").and_then(Value::as_str);
+                    if reason.is_some_and(|reason| reason.starts_with("exited")) {
+                        DebuggerState::Exited
+                    } else {
+                        DebuggerState::Stopped
+                    }
+                }
+                _ => current,
+            },
+            _ => current,
+        }
+    }
+}
+
+/// Commands that give gdb a program to run or debug
+const PROGRAM_COMMANDS: [&str; 3] = ["-file-exec-and-symbols", "-file-exec-file", "-target-attach"];
+
+/// Does `cmd` load or attach a program?
+pub(crate) fn loads_program(cmd: &str) -> bool {
+    let (_, name) = strip_token(cmd);
+    PROGRAM_COMMANDS.iter().any(|command| name.starts_with(command))
+}
+
+impl Debugger {
+    /// Check that the execution command `cmd` (`-exec-*`) is valid in the current state,
+    /// see `DebuggerState`
+    pub(crate) fn check_exec(&self, cmd: &str) -> Result<()> {
+        let state = self.state();
+        let (_, name) = strip_token(cmd);
+        let operation = name.split_whitespace().next().unwrap_or(name);
+        let valid = match state {
+            DebuggerState::Dead => false,
+            _ if operation == "-exec-run" => state != DebuggerState::Running,
+            DebuggerState::Stopped => true,
+            DebuggerState::Running => self.session.lock().unwrap().running.is_partial(),
+            _ => false,
+        };
+        if !valid {
+            return Err(Error::InvalidState {
+                operation: operation.to_string(),
+                state,
+            });
+        }
+        if operation == "-exec-run" {
+            let session = self.session.lock().unwrap();
+            // remote stubs may know the program on their own
+            if !session.program_loaded && session.remote.is_none() {
+                return Err(Error::NoExecutable);
+            }
+        }
+        Ok(())
+    }
+
+    /// The current state of the debugger
+    pub fn state(&self) -> DebuggerState {
+        *self.state.borrow()
+    }
+
+    /// A receiver notified on every state change, so UIs can react to transitions
+    /// without polling `state()`
+    pub fn watch_state(&self) -> watch::Receiver<DebuggerState> {
+        self.state.subscribe()
+    }
+
+    /// Force the state, for transitions gdb does not report (e.g. loading a core file)
+    pub(crate) fn set_state(&self, state: DebuggerState) {
+        if self.state.send_replace(state) != state {
+            if let Some(timeline) = self.session.lock().unwrap().timeline.as_mut() {
+                timeline.on_state(state);
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Result};
+use crate::events::{Frame, StopEvent};
+use crate::frames::{register_values, RegisterValue};
+use crate::msg::{self, MessageRecord, ResultClass, Value};
+use crate::variables::{variable_values, PrintValues, VariableValue};
+use tokio::sync::mpsc::Receiver;
+
+/// What `wait_for_stop_bundle()` queries after a stop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StopBundleOptions {
+    pub frame: bool,
+    pub registers: bool,
+    /// Values printed for the locals, `None` to skip them
+    pub locals: Option<PrintValues>,
+}
+
+impl Default for StopBundleOptions {
+    fn default() -> Self {
+        StopBundleOptions {
+            frame: true,
+            registers: true,
+            locals: Some(PrintValues::Simple),
+        }
+    }
+}
+
+/// A stop and the state of the innermost frame of the stopped thread. Queries that
+/// failed (e.g. no registers when the program exited) leave their field empty
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StopBundle {
+    pub stop: StopEvent,
+    pub frame: Option<Frame>,
+    pub registers: Vec<RegisterValue>,
+    pub locals: Vec<VariableValue>,
+}
+
+#[derive(Clone, Copy)]
+enum Query {
+    Frame,
+    RegisterNames,
+    Registers,
+    Locals,
+}
+
+impl Debugger {
+    /// Wait for the next stop, then query the frame, registers and locals the stop
+    /// bundle is configured with. The queries are sent together, without waiting for each
+    /// result before sending the next command, which saves a round trip per query on
+    /// remote links
+    pub async fn wait_for_stop_bundle(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        options: &StopBundleOptions,
+    ) -> Result<StopBundle> {
+        let stop = self.wait_for_stop(output_channel).await?;
+        let mut bundle = StopBundle {
+            stop,
+            ..Default::default()
+        };
+        let exited = bundle
+            .stop
+            .reason
+            .as_deref()
+            .is_some_and(|reason| reason.starts_with("exited"));
+        if exited {
+            return Ok(bundle);
+        }
+
+        let context = match &bundle.stop.thread_id {
+            Some(thread) => format!("--thread {} --frame 0", thread),
+            None => String::new(),
+        };
+        let mut queries = Vec::new();
+        if options.frame {
+            queries.push((Query::Frame, format!("-stack-info-frame {}", context)));
+        }
+        if options.registers {
+            queries.push((Query::RegisterNames, "-data-list-register-names".to_string()));
+            queries.push((
+                Query::Registers,
+                format!("-data-list-register-values {} --skip-unavailable N", context),
+            ));
+        }
+        if let Some(values) = options.locals {
+            queries.push((
+                Query::Locals,
+                format!("-stack-list-variables {} {}", context, values.as_option()),
+            ));
+        }
+
+        let results = self.pipeline(output_channel, &queries).await?;
+        let mut register_names = Vec::new();
+        for ((query, cmd), record) in queries.iter().zip(results) {
+            if record.class != ResultClass::Done {
+                tracing::debug!("stop bundle query {} failed: {}", cmd, record);
+                continue;
+            }
+            match query {
+                Query::Frame => bundle.frame = record.get("frame").and_then(Frame::from_value),
+                Query::RegisterNames => {
+                    register_names = match record.get("register-names") {
+                        Some(Value::ValueList(names)) => {
+                            names.iter().filter_map(Value::unquoted).collect()
+                        }
+                        _ => Vec::new(),
+                    }
+                }
+                Query::Registers => {
+                    bundle.registers =
+                        register_values(record.get("register-values"), &register_names)
+                }
+                Query::Locals => bundle.locals = variable_values(record.get("variables"), true),
+            }
+        }
+        Ok(bundle)
+    }
+
+    /// Send all the commands, then read their results, in order
+    async fn pipeline(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        queries: &[(Query, String)],
+    ) -> Result<Vec<MessageRecord<ResultClass>>> {
+        let _guard = self.commands.lock().await;
+        let mut sent = 0;
+        for (_, cmd) in queries {
+            if let Err(e) = self.send_cmd_raw(cmd).await {
+                tracing::debug!("failed to send {}: {}", cmd, e);
+                break;
+            }
+            sent += 1;
+        }
+        let mut results = Vec::with_capacity(sent);
+        for _ in 0..sent {
+            results.push(self.read_result_record(output_channel).await?);
+        }
+        Ok(results)
+    }
+}
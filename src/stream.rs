@@ -0,0 +1,71 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::events::StopEvent;
+use crate::msg::{AsyncClass, AsyncRecord, Record};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::Receiver;
+use tokio_stream::wrappers::ReceiverStream;
+pub use tokio_stream::{Stream, StreamExt};
+
+/// gdb output as a `Stream`, so `StreamExt` combinators (filter, timeout, merge...) can be
+/// used instead of hand written `recv()` loops
+pub struct RecordStream {
+    inner: ReceiverStream<Record>,
+}
+
+impl RecordStream {
+    pub fn new(output_channel: Receiver<Record>) -> Self {
+        RecordStream {
+            inner: ReceiverStream::new(output_channel),
+        }
+    }
+
+    /// Keep only `*stopped` records, as typed `StopEvent`s
+    pub fn events(self) -> impl Stream<Item = StopEvent> {
+        self.filter_map(|record| match record {
+            Record::Async(AsyncRecord::Exec(msg)) if msg.class == AsyncClass::Stopped => {
+                Some(StopEvent::from_record(&msg))
+            }
+            _ => None,
+        })
+    }
+
+    /// Get back the underlying output channel
+    pub fn into_inner(self) -> Receiver<Record> {
+        self.inner.into_inner()
+    }
+}
+
+impl From<Receiver<Record>> for RecordStream {
+    fn from(output_channel: Receiver<Record>) -> Self {
+        Self::new(output_channel)
+    }
+}
+
+impl Stream for RecordStream {
+    type Item = Record;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Record>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,183 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::Breakpoint;
+use crate::dbg::{Debugger, Error, ReaderContext, Result};
+use crate::events::DebuggerEvent;
+use crate::msg::{self, Record};
+use crate::signal;
+use crate::state::DebuggerState;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use sysinfo::Signal;
+use tokio::io::BufWriter;
+use tokio::sync::mpsc::Receiver;
+
+/// How long `restart_gdb()` waits for a live gdb to exit
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many times in a row the supervisor mode tries to restart gdb before giving up
+const MAX_RESTART_ATTEMPTS: usize = 3;
+
+/// Outcome of `Debugger::restart_gdb()`
+#[derive(Debug, Default)]
+pub struct RestartReport {
+    /// The executable loaded again, if one was loaded with `load_executable()`
+    pub executable: Option<PathBuf>,
+    /// The breakpoints re-created in the new gdb
+    pub restored: Vec<Breakpoint>,
+    /// The breakpoints that could not be re-created, with gdb's error
+    pub unresolved: Vec<(Breakpoint, Error)>,
+}
+
+impl Debugger {
+    /// Is gdb still running? `false` once it exited, crashed or was killed
+    pub fn is_alive(&self) -> bool {
+        self.state() != DebuggerState::Dead
+    }
+
+    /// Supervisor mode: when gdb exited unexpectedly, `send_cmd()` restarts it with
+    /// `restart_gdb()` before sending the command. A command that was running when gdb
+    /// exited fails with `Error::ChannelClosed` once gdb is restarted, it is not sent
+    /// again. Off by default, `terminate()` turns it off
+    pub fn set_auto_restart(&self, enabled: bool) {
+        self.auto_restart.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Is the supervisor mode on? See `set_auto_restart()`
+    pub fn auto_restart(&self) -> bool {
+        self.auto_restart.load(Ordering::Relaxed)
+    }
+
+    /// Start a new gdb with the backend the `Debugger` was started with, load the
+    /// executable again and re-create the breakpoints of the breakpoint table. A live gdb
+    /// is killed first. The debuggee is not restarted. A `DebuggerEvent::Restarted` record
+    /// is emitted once done
+    pub async fn restart_gdb(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<RestartReport> {
+        if self.is_alive() {
+            self.kill_gdb().await?;
+        }
+        tracing::debug!("restarting gdb");
+        let breakpoints = self.breakpoints();
+        let executable = {
+            let session = self.session.lock().unwrap();
+            session.executable.as_ref().map(|e| e.path.clone())
+        };
+
+        // the reader of the old gdb must be done before the new one starts
+        let mut closed = self.events.output_closed.subscribe();
+        let _ = tokio::time::timeout(EXIT_TIMEOUT, closed.wait_for(|closed| *closed)).await;
+
+        let io = self.backend.lock().unwrap().spawn()?;
+        // whatever the old gdb printed is stale
+        while output_channel.try_recv().is_ok() {}
+        *self.writer.lock().await = BufWriter::new(io.stdin);
+        self.write_error.lock().unwrap().take();
+        // the old gdb won't reply anymore
+        let sent = self.stats.commands_sent.load(Ordering::Relaxed);
+        self.stats.results_received.store(sent, Ordering::Relaxed);
+        let pid = io.pid.map_or(usize::MAX, |pid| pid as usize);
+        self.gdb_pid.store(pid, Ordering::Relaxed);
+        self.reset_session();
+        {
+            let mut session = self.session.lock().unwrap();
+            session.breakpoints = Default::default();
+            session.executable = None;
+            session.program_loaded = false;
+            session.gdb_version = None;
+        }
+        self.set_state(DebuggerState::Starting);
+        let readers = ReaderContext {
+            sender: self.events.clone(),
+            state: self.state.clone(),
+            debugee_pid: self.debugee_pid.clone(),
+            session: self.session.clone(),
+            parse_errors: self.parse_errors.clone(),
+            lenient_parsing: self.lenient_parsing.clone(),
+            max_nesting_depth: self.max_nesting_depth.clone(),
+            stats: self.stats.clone(),
+        };
+        let stderr_task = Self::spawn_readers(io.stdout, io.stderr, &readers);
+        Self::wait_for_startup(&self.state, stderr_task).await?;
+        if let Err(e) = self.detect_capabilities(output_channel).await {
+            tracing::debug!("failed to detect gdb's capabilities: {}", e);
+        }
+        self.detect_gdb_version(output_channel).await;
+        self.restore_source_mappings(output_channel).await;
+
+        let mut report = RestartReport::default();
+        if let Some(path) = &executable {
+            self.load_executable(output_channel, path).await?;
+            report.executable = executable.clone();
+        }
+        let results = self.restore_breakpoints(output_channel, &breakpoints).await;
+        for (old, result) in breakpoints.iter().zip(results) {
+            match result {
+                Ok(bkpt) => report.restored.push(bkpt),
+                Err(e) => report.unresolved.push((old.clone(), e)),
+            }
+        }
+        let event = DebuggerEvent::Restarted {
+            restored: report.restored.len(),
+            unresolved: report.unresolved.len(),
+        };
+        let _ = self.events.send(Record::Event(event)).await;
+        Ok(report)
+    }
+
+    /// `restart_gdb()` for the supervisor mode, up to `MAX_RESTART_ATTEMPTS` times when
+    /// the new gdb exits too. The commands sent while restarting don't restart gdb again,
+    /// they fail instead
+    pub(crate) async fn supervise_restart(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<()> {
+        if self.restarting.swap(true, Ordering::Relaxed) {
+            return Err(Error::ChannelClosed);
+        }
+        let mut result = Err(Error::ChannelClosed);
+        for attempt in 1..=MAX_RESTART_ATTEMPTS {
+            tracing::debug!("gdb is gone, restarting it (attempt {})", attempt);
+            match Box::pin(self.restart_gdb(output_channel)).await {
+                Ok(_) if self.is_alive() => {
+                    result = Ok(());
+                    break;
+                }
+                Ok(_) => result = Err(Error::ChannelClosed),
+                Err(e) => result = Err(e),
+            }
+        }
+        self.restarting.store(false, Ordering::Relaxed);
+        result
+    }
+
+    /// Kill gdb, or ask it to exit when its pid is unknown, and wait for its output to end
+    async fn kill_gdb(&self) -> Result<()> {
+        let mut state = self.watch_state();
+        let pid = self.gdb_pid.load(Ordering::Relaxed);
+        if pid == usize::MAX || signal(pid, Signal::Kill).is_err() {
+            self.send_cmd_raw("-gdb-exit").await?;
+        }
+        let dead = state.wait_for(|state| *state == DebuggerState::Dead);
+        let result = tokio::time::timeout(EXIT_TIMEOUT, dead).await;
+        result.map(|_| ()).map_err(|_| Error::Timeout)
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg::{self, Value};
+use regex::Regex;
+use tokio::sync::mpsc::Receiver;
+
+/// A source file of the executable, see `Debugger::source_files()`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceFile {
+    /// The name as recorded in the debug info, often relative to the compilation directory
+    pub file: String,
+    pub fullname: Option<String>,
+}
+
+/// A function, variable or type, see `Debugger::functions()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol {
+    pub name: String,
+    /// Missing for types and symbols without debug info
+    pub type_name: Option<String>,
+    /// The declaration, e.g. `static int counter;`
+    pub description: Option<String>,
+    /// Where the symbol is defined. Missing for symbols without debug info
+    pub file: Option<String>,
+    pub fullname: Option<String>,
+    pub line: Option<u32>,
+    /// Set for symbols without debug info only
+    pub address: Option<String>,
+}
+
+impl Symbol {
+    fn from_value(value: &Value, file: Option<&Value>) -> Option<Symbol> {
+        let text = |name| value.get(name).and_then(Value::unquoted);
+        Some(Symbol {
+            name: text("name")?,
+            type_name: text("type"),
+            description: text("description"),
+            file: file.and_then(|file| file.get("filename")).and_then(Value::unquoted),
+            fullname: file.and_then(|file| file.get("fullname")).and_then(Value::unquoted),
+            line: text("line").and_then(|line| line.parse().ok()),
+            address: text("address"),
+        })
+    }
+}
+
+/// Parse the `symbols` field of `-symbol-info-*` results:
+/// `{debug=[{filename="..",fullname="..",symbols=[{line="3",name="f",...}]}],nondebug=[..]}`
+pub(crate) fn parse_symbols(symbols: Option<&Value>) -> Vec<Symbol> {
+    let mut result = Vec::new();
+    let list = |name| symbols.and_then(|s| s.get(name)).and_then(Value::as_list);
+    for file in list("debug").unwrap_or_default() {
+        let entries = file.get("symbols").and_then(Value::as_list).unwrap_or_default();
+        result.extend(entries.iter().filter_map(|s| Symbol::from_value(s, Some(file))));
+    }
+    for symbol in list("nondebug").unwrap_or_default() {
+        result.extend(Symbol::from_value(symbol, None));
+    }
+    result
+}
+
+impl Debugger {
+    /// The source files of the executable (`-file-list-exec-source-files`), optionally
+    /// only those whose full path matches the regular expression `filter`
+    pub async fn source_files(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        filter: Option<&str>,
+    ) -> Result<impl Iterator<Item = SourceFile>> {
+        let filter = filter
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| Error::InvalidArgument(e.to_string()))?;
+        let record = self
+            .send_cmd(output_channel, "-file-list-exec-source-files")
+            .await?;
+        let files = record.get("files").and_then(Value::as_list).unwrap_or_default();
+        let files: Vec<SourceFile> = files
+            .iter()
+            .filter_map(|file| {
+                Some(SourceFile {
+                    file: file.get("file").and_then(Value::unquoted)?,
+                    fullname: file.get("fullname").and_then(Value::unquoted),
+                })
+            })
+            .filter(|file| match &filter {
+                Some(filter) => filter.is_match(file.fullname.as_ref().unwrap_or(&file.file)),
+                None => true,
+            })
+            .collect();
+        Ok(files.into_iter())
+    }
+
+    /// Functions whose name matches the regular expression `name`
+    /// (`-symbol-info-functions`). Functions without debug info are included when
+    /// `include_nondebug` is set
+    pub async fn functions(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        name: Option<&str>,
+        include_nondebug: bool,
+    ) -> Result<impl Iterator<Item = Symbol>> {
+        self.symbol_info(output_channel, "functions", name, include_nondebug)
+            .await
+    }
+
+    /// Global and static variables whose name matches the regular expression `name`
+    /// (`-symbol-info-variables`)
+    pub async fn global_variables(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        name: Option<&str>,
+        include_nondebug: bool,
+    ) -> Result<impl Iterator<Item = Symbol>> {
+        self.symbol_info(output_channel, "variables", name, include_nondebug)
+            .await
+    }
+
+    /// Types whose name matches the regular expression `name` (`-symbol-info-types`)
+    pub async fn types(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        name: Option<&str>,
+    ) -> Result<impl Iterator<Item = Symbol>> {
+        self.symbol_info(output_channel, "types", name, false).await
+    }
+
+    /// The filtering is done by gdb, which is much faster than listing all the symbols
+    async fn symbol_info(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        kind: &str,
+        name: Option<&str>,
+        include_nondebug: bool,
+    ) -> Result<std::vec::IntoIter<Symbol>> {
+        let mut cmd = format!("-symbol-info-{}", kind);
+        if include_nondebug {
+            cmd.push_str(" --include-nondebug");
+        }
+        if let Some(name) = name {
+            cmd.push_str(&format!(" --name {}", mi_quote(name)));
+        }
+        let record = self.send_cmd(output_channel, &cmd).await?;
+        Ok(parse_symbols(record.get("symbols")).into_iter())
+    }
+}
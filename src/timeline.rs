@@ -0,0 +1,237 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Result};
+use crate::msg::{AsyncClass, MessageRecord, ResultClass, Value};
+use crate::state::DebuggerState;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// The track an event is shown on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineCategory {
+    /// From sending an MI command to receiving its result
+    Command,
+    /// How long the debugger stayed in a `DebuggerState`
+    State,
+    /// Stops, with their reason
+    Stop,
+    /// Breakpoints created, modified or deleted
+    Breakpoint,
+}
+
+impl TimelineCategory {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimelineCategory::Command => "command",
+            TimelineCategory::State => "state",
+            TimelineCategory::Stop => "stop",
+            TimelineCategory::Breakpoint => "breakpoint",
+        }
+    }
+
+    /// Each category is a separate thread in the trace viewer
+    fn track(&self) -> u32 {
+        match self {
+            TimelineCategory::Command => 1,
+            TimelineCategory::State => 2,
+            TimelineCategory::Stop | TimelineCategory::Breakpoint => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEvent {
+    pub name: String,
+    pub category: TimelineCategory,
+    /// Since the recording started
+    pub start: Duration,
+    /// `None` for instant events (stops, breakpoint changes)
+    pub duration: Option<Duration>,
+    pub args: Vec<(String, String)>,
+}
+
+/// A recording of a debug session, see `Debugger::start_timeline()`
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    started: Instant,
+    events: Vec<TimelineEvent>,
+    /// Commands waiting for their result. gdb replies in order
+    commands: VecDeque<(String, Instant)>,
+    /// The current state and when it was entered
+    state: (DebuggerState, Instant),
+}
+
+impl Timeline {
+    pub(crate) fn new(state: DebuggerState) -> Timeline {
+        let now = Instant::now();
+        Timeline {
+            started: now,
+            events: Vec::new(),
+            commands: VecDeque::new(),
+            state: (state, now),
+        }
+    }
+
+    /// The recorded events, in the order they completed. The current state is not
+    /// included until it changes
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+
+    pub(crate) fn on_command(&mut self, cmd: &str) {
+        self.commands
+            .push_back((cmd.trim_end().to_string(), Instant::now()));
+    }
+
+    pub(crate) fn on_result(&mut self, record: &MessageRecord<ResultClass>) {
+        let Some((cmd, sent)) = self.commands.pop_front() else {
+            return;
+        };
+        self.events.push(TimelineEvent {
+            name: cmd,
+            category: TimelineCategory::Command,
+            start: sent - self.started,
+            duration: Some(sent.elapsed()),
+            args: vec![("result".to_string(), record.class.as_str().to_string())],
+        });
+    }
+
+    pub(crate) fn on_state(&mut self, state: DebuggerState) {
+        let (previous, since) = std::mem::replace(&mut self.state, (state, Instant::now()));
+        self.events.push(TimelineEvent {
+            name: format!("{:?}", previous),
+            category: TimelineCategory::State,
+            start: since - self.started,
+            duration: Some(since.elapsed()),
+            args: Vec::new(),
+        });
+    }
+
+    pub(crate) fn on_async(&mut self, record: &MessageRecord<AsyncClass>) {
+        let (category, fields): (_, &[&str]) = match record.class {
+            AsyncClass::Stopped => (TimelineCategory::Stop, &["reason", "thread-id"]),
+            AsyncClass::BreakpointCreated | AsyncClass::BreakpointModified => (
+                TimelineCategory::Breakpoint,
+                &["bkpt.number", "bkpt.original-location"],
+            ),
+            AsyncClass::BreakpointDeleted => (TimelineCategory::Breakpoint, &["id"]),
+            _ => return,
+        };
+        let args = fields
+            .iter()
+            .filter_map(|path| {
+                let value = record.lookup(path).and_then(Value::unquoted)?;
+                Some((path.to_string(), value))
+            })
+            .collect();
+        self.events.push(TimelineEvent {
+            name: record.class.as_str().to_string(),
+            category,
+            start: self.started.elapsed(),
+            duration: None,
+            args,
+        });
+    }
+
+    /// Export in the Chrome trace event format, which can be opened with Perfetto
+    /// (ui.perfetto.dev) or chrome://tracing. Commands, states and events are shown on
+    /// separate tracks
+    pub fn to_chrome_trace(&self) -> serde_json::Value {
+        let mut events: Vec<serde_json::Value> = [
+            TimelineCategory::Command,
+            TimelineCategory::State,
+            TimelineCategory::Stop,
+        ]
+        .iter()
+        .map(|category| {
+            let name = match category {
+                TimelineCategory::Command => "commands",
+                TimelineCategory::State => "state",
+                _ => "events",
+            };
+            json!({"name": "thread_name", "ph": "M", "pid": 1, "tid": category.track(),
+                   "args": {"name": name}})
+        })
+        .collect();
+
+        // the current state lasts until now
+        let (state, since) = self.state;
+        let current = TimelineEvent {
+            name: format!("{:?}", state),
+            category: TimelineCategory::State,
+            start: since - self.started,
+            duration: Some(since.elapsed()),
+            args: Vec::new(),
+        };
+        for event in self.events.iter().chain(std::iter::once(&current)) {
+            let args: serde_json::Map<String, serde_json::Value> = event
+                .args
+                .iter()
+                .map(|(name, value)| (name.clone(), json!(value)))
+                .collect();
+            let mut trace_event = json!({
+                "name": event.name,
+                "cat": event.category.as_str(),
+                "ts": event.start.as_micros() as u64,
+                "pid": 1,
+                "tid": event.category.track(),
+                "args": args,
+            });
+            match event.duration {
+                Some(duration) => {
+                    trace_event["ph"] = json!("X");
+                    trace_event["dur"] = json!(duration.as_micros() as u64);
+                }
+                None => {
+                    trace_event["ph"] = json!("i");
+                    trace_event["s"] = json!("t");
+                }
+            }
+            events.push(trace_event);
+        }
+        json!({"traceEvents": events, "displayTimeUnit": "ms"})
+    }
+
+    /// Write `to_chrome_trace()` to `path`
+    pub fn write_chrome_trace(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_chrome_trace().to_string())?;
+        Ok(())
+    }
+}
+
+impl Debugger {
+    /// Start recording a timeline of the commands sent to gdb, state transitions, stops
+    /// and breakpoint changes, discarding the previous recording. Recording is off by
+    /// default
+    pub fn start_timeline(&self) {
+        let state = self.state();
+        self.session.lock().unwrap().timeline = Some(Timeline::new(state));
+    }
+
+    /// Stop recording and return the timeline
+    pub fn stop_timeline(&self) -> Option<Timeline> {
+        self.session.lock().unwrap().timeline.take()
+    }
+
+    /// A copy of the timeline recorded so far, recording goes on
+    pub fn timeline(&self) -> Option<Timeline> {
+        self.session.lock().unwrap().timeline.clone()
+    }
+}
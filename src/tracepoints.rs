@@ -0,0 +1,274 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::Breakpoint;
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::events::Frame;
+use crate::msg::{self, MessageRecord, ResultClass, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// Options for `Debugger::insert_tracepoint()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TracepointOptions {
+    /// Insert a fast tracepoint, a jump instead of a trap. Requires the in-process agent
+    /// of gdbserver
+    pub fast: bool,
+    /// Only collect when `condition` holds, evaluated by the target (`-c`)
+    pub condition: Option<String>,
+    /// Create the tracepoint disabled (`-d`)
+    pub disabled: bool,
+    /// Stop tracing once the tracepoint was hit this many times (`-break-passcount`)
+    pub pass_count: Option<u32>,
+    /// The actions executed when the tracepoint is hit, e.g. `collect $regs` or
+    /// `teval $hits++`
+    pub actions: Vec<String>,
+}
+
+impl TracepointOptions {
+    /// Build the `-break-insert -a` command for `location`
+    pub(crate) fn insert_cmd(&self, location: &str) -> String {
+        let mut cmd = "-break-insert -a".to_string();
+        // gdb reuses the hardware flag to request a fast tracepoint
+        if self.fast {
+            cmd.push_str(" -h");
+        }
+        if self.disabled {
+            cmd.push_str(" -d");
+        }
+        if let Some(condition) = &self.condition {
+            cmd.push_str(&format!(" -c {}", mi_quote(condition)));
+        }
+        cmd.push(' ');
+        cmd.push_str(&mi_quote(location));
+        cmd
+    }
+}
+
+impl From<&Breakpoint> for TracepointOptions {
+    /// The options re-creating tracepoint `bkpt`
+    fn from(bkpt: &Breakpoint) -> Self {
+        TracepointOptions {
+            fast: bkpt.kind.as_deref() == Some("fast tracepoint"),
+            condition: bkpt.condition.clone(),
+            disabled: !bkpt.enabled,
+            pass_count: (bkpt.pass_count > 0).then_some(bkpt.pass_count),
+            actions: bkpt.script.clone(),
+        }
+    }
+}
+
+/// Is breakpoint type `kind` a tracepoint (`tracepoint`, `fast tracepoint` or
+/// `static tracepoint`)?
+pub(crate) fn is_tracepoint(kind: &str) -> bool {
+    kind.ends_with("tracepoint")
+}
+
+/// The state of the trace experiment, see `Debugger::trace_status()`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceStatus {
+    /// The target supports tracing, or the trace was loaded from a file
+    pub supported: bool,
+    /// The trace experiment is collecting
+    pub running: bool,
+    /// Why the last experiment stopped, e.g. `request`, `overflow` or `passcount`
+    pub stop_reason: Option<String>,
+    /// The tracepoint whose pass count stopped the experiment
+    pub stopping_tracepoint: Option<String>,
+    /// The trace frames in the buffer
+    pub frames: u64,
+    /// The trace frames created, including the ones discarded by a circular buffer
+    pub frames_created: u64,
+    pub buffer_size: Option<u64>,
+    pub buffer_free: Option<u64>,
+    /// Old trace frames are discarded when the buffer is full
+    pub circular: bool,
+    /// The experiment keeps running when gdb disconnects
+    pub disconnected: bool,
+    /// The trace file examined instead of a live target
+    pub trace_file: Option<String>,
+}
+
+impl TraceStatus {
+    /// Build from the result of `-trace-status`
+    pub fn from_record(record: &MessageRecord<ResultClass>) -> Self {
+        let text = |name| record.get(name).and_then(Value::unquoted);
+        let number = |name| text(name).and_then(|s| s.parse().ok());
+        let flag = |name| text(name).as_deref() == Some("1");
+        TraceStatus {
+            // `file` for a trace file
+            supported: text("supported").is_some_and(|s| s != "0"),
+            running: flag("running"),
+            stop_reason: text("stop-reason"),
+            stopping_tracepoint: text("stopping-tracepoint"),
+            frames: number("frames").unwrap_or(0),
+            frames_created: number("frames-created").unwrap_or(0),
+            buffer_size: number("buffer-size"),
+            buffer_free: number("buffer-free"),
+            circular: flag("circular"),
+            disconnected: flag("disconnected"),
+            trace_file: text("trace-file"),
+        }
+    }
+}
+
+/// Which trace frame `Debugger::trace_find()` selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceFind {
+    /// Stop examining trace frames and go back to the live target
+    None,
+    /// Trace frame `n`
+    Frame(u64),
+    /// The next trace frame collected by tracepoint `number`
+    Tracepoint(String),
+    /// The next trace frame at address `pc`
+    Pc(String),
+    /// The next trace frame whose pc is in `start..=end`
+    PcInsideRange(String, String),
+    /// The next trace frame whose pc is outside of `start..=end`
+    PcOutsideRange(String, String),
+    /// The next trace frame at source line `location`, e.g. `foo.c:12`
+    Line(String),
+}
+
+impl TraceFind {
+    /// The `-trace-find` command
+    pub(crate) fn cmd(&self) -> String {
+        match self {
+            TraceFind::None => "-trace-find none".to_string(),
+            TraceFind::Frame(n) => format!("-trace-find frame-number {}", n),
+            TraceFind::Tracepoint(number) => format!("-trace-find tracepoint-number {}", number),
+            TraceFind::Pc(pc) => format!("-trace-find pc {}", pc),
+            TraceFind::PcInsideRange(start, end) => {
+                format!("-trace-find pc-inside-range {} {}", start, end)
+            }
+            TraceFind::PcOutsideRange(start, end) => {
+                format!("-trace-find pc-outside-range {} {}", start, end)
+            }
+            TraceFind::Line(location) => format!("-trace-find line {}", mi_quote(location)),
+        }
+    }
+}
+
+/// A trace frame selected with `Debugger::trace_find()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceFrame {
+    /// The trace frame number
+    pub number: u64,
+    /// The tracepoint that collected it
+    pub tracepoint: String,
+    /// Where the tracepoint was hit
+    pub frame: Option<Frame>,
+}
+
+impl TraceFrame {
+    /// Build from the result of `-trace-find`, `None` when no trace frame was found
+    pub fn from_record(record: &MessageRecord<ResultClass>) -> Option<TraceFrame> {
+        let text = |name| record.get(name).and_then(Value::unquoted);
+        if text("found").as_deref() != Some("1") {
+            return None;
+        }
+        Some(TraceFrame {
+            number: text("traceframe")?.parse().ok()?,
+            tracepoint: text("tracepoint")?,
+            frame: record.get("frame").and_then(Frame::from_value),
+        })
+    }
+}
+
+impl Debugger {
+    /// Insert a tracepoint at `location`: the program is not stopped, the actions of
+    /// `options` collect data in the trace buffer while the experiment is running
+    pub async fn insert_tracepoint(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        location: &str,
+        options: &TracepointOptions,
+    ) -> Result<Breakpoint> {
+        let record = self
+            .send_cmd(output_channel, &options.insert_cmd(location))
+            .await?;
+        let mut tpt = Breakpoint::from_results(&record.content).ok_or(Error::UnexpectedResponse)?;
+        if let Some(count) = options.pass_count {
+            self.send_cmd(
+                output_channel,
+                &format!("-break-passcount {} {}", tpt.number, count),
+            )
+            .await?;
+            tpt.pass_count = count;
+        }
+        if !options.actions.is_empty() {
+            self.set_breakpoint_commands(output_channel, &tpt.number, &options.actions)
+                .await?;
+            tpt.script = options.actions.clone();
+        }
+        self.session.lock().unwrap().breakpoints.insert(tpt.clone());
+        Ok(tpt)
+    }
+
+    /// Define trace state variable `name` (with or without the leading `$`), which
+    /// actions can update with `teval`
+    pub async fn define_trace_variable(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        name: &str,
+        initial_value: Option<i64>,
+    ) -> Result<()> {
+        let mut cmd = format!("-trace-define-variable ${}", name.trim_start_matches('$'));
+        if let Some(value) = initial_value {
+            cmd.push_str(&format!(" {}", value));
+        }
+        self.send_cmd(output_channel, &cmd).await?;
+        Ok(())
+    }
+
+    /// Start the trace experiment: the tracepoints are downloaded to the target, which
+    /// collects their data without stopping the program
+    pub async fn trace_start(&self, output_channel: &mut Receiver<msg::Record>) -> Result<()> {
+        self.send_cmd(output_channel, "-trace-start").await?;
+        Ok(())
+    }
+
+    /// Stop the trace experiment and return its final status
+    pub async fn trace_stop(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<TraceStatus> {
+        let record = self.send_cmd(output_channel, "-trace-stop").await?;
+        Ok(TraceStatus::from_record(&record))
+    }
+
+    /// The state of the trace experiment (`-trace-status`)
+    pub async fn trace_status(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+    ) -> Result<TraceStatus> {
+        let record = self.send_cmd(output_channel, "-trace-status").await?;
+        Ok(TraceStatus::from_record(&record))
+    }
+
+    /// Select a trace frame: the registers, memory and variables it collected are
+    /// examined with the usual commands until `TraceFind::None` is selected.
+    /// Return `None` when there is no such frame
+    pub async fn trace_find(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        find: &TraceFind,
+    ) -> Result<Option<TraceFrame>> {
+        let record = self.send_cmd(output_channel, &find.cmd()).await?;
+        Ok(TraceFrame::from_record(&record))
+    }
+}
@@ -0,0 +1,158 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::Debugger;
+use crate::msg::Record;
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A command sent to gdb or a record received from it
+#[derive(Debug, Clone)]
+pub enum TranscriptEntry {
+    Command { at: SystemTime, command: String },
+    Record { at: SystemTime, record: Record },
+}
+
+impl TranscriptEntry {
+    /// `<seconds since the epoch> > <command>` for commands, `... < <MI line>` for
+    /// records. Crate events are written as JSON
+    pub fn to_line(&self) -> String {
+        let (at, direction, text) = match self {
+            TranscriptEntry::Command { at, command } => (at, '>', command.trim_end().to_string()),
+            TranscriptEntry::Record { at, record } => (
+                at,
+                '<',
+                record
+                    .to_mi_string()
+                    .unwrap_or_else(|| record.to_json().to_string()),
+            ),
+        };
+        let at = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        format!("{}.{:03} {} {}", at.as_secs(), at.subsec_millis(), direction, text)
+    }
+}
+
+/// Receives the transcript entries, see `SessionRecorder::with_sink()`
+pub trait TranscriptSink: Send {
+    fn record(&mut self, entry: &TranscriptEntry);
+}
+
+impl<F: FnMut(&TranscriptEntry) + Send> TranscriptSink for F {
+    fn record(&mut self, entry: &TranscriptEntry) {
+        self(entry)
+    }
+}
+
+/// A sink appending entries to a text file, one `TranscriptEntry::to_line()` per line
+pub struct TranscriptFile {
+    pub path: PathBuf,
+}
+
+impl TranscriptSink for TranscriptFile {
+    fn record(&mut self, entry: &TranscriptEntry) {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path);
+        if let Err(e) = file.and_then(|mut file| writeln!(file, "{}", entry.to_line())) {
+            tracing::debug!("failed to write to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Records the commands sent to gdb and the records received, with timestamps.
+/// The latest entries are kept in memory (see `Debugger::dump_transcript()`), and all of
+/// them can be passed on to a sink
+pub struct SessionRecorder {
+    capacity: usize,
+    entries: VecDeque<TranscriptEntry>,
+    sink: Option<Box<dyn TranscriptSink>>,
+}
+
+impl SessionRecorder {
+    /// Keep the last `capacity` entries in memory
+    pub fn new(capacity: usize) -> Self {
+        SessionRecorder {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            sink: None,
+        }
+    }
+
+    /// Also pass every entry to `sink`, e.g. a `TranscriptFile`
+    pub fn with_sink(mut self, sink: Box<dyn TranscriptSink>) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    pub(crate) fn on_command(&mut self, command: &str) {
+        self.push(TranscriptEntry::Command {
+            at: SystemTime::now(),
+            command: command.to_string(),
+        });
+    }
+
+    pub(crate) fn on_record(&mut self, record: &Record) {
+        self.push(TranscriptEntry::Record {
+            at: SystemTime::now(),
+            record: record.clone(),
+        });
+    }
+
+    fn push(&mut self, entry: TranscriptEntry) {
+        if let Some(sink) = self.sink.as_mut() {
+            sink.record(&entry);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// The entries kept in memory, oldest first
+    pub fn entries(&self) -> Vec<TranscriptEntry> {
+        self.entries.iter().cloned().collect()
+    }
+}
+
+pub(crate) type SharedRecorder = Arc<Mutex<Option<SessionRecorder>>>;
+
+impl Debugger {
+    /// Record the commands sent to gdb and every record received with `recorder`.
+    /// Pass `None` to stop recording
+    pub fn set_recorder(&self, recorder: Option<SessionRecorder>) {
+        *self.events.recorder.lock().unwrap() = recorder;
+    }
+
+    /// The entries kept in memory by the recorder, oldest first. Empty when not recording
+    pub fn dump_transcript(&self) -> Vec<TranscriptEntry> {
+        self.events
+            .recorder
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(SessionRecorder::entries)
+            .unwrap_or_default()
+    }
+}
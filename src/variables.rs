@@ -0,0 +1,317 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::frames::FrameContext;
+use crate::msg::{self, find_field, Value, Variable};
+use serde_json::{json, Map};
+use std::future::Future;
+use std::pin::Pin;
+use tokio::sync::mpsc::Receiver;
+
+/// C++ access specifiers show up as children of class varobjs without being members
+const ACCESS_SPECIFIERS: [&str; 3] = ["public", "private", "protected"];
+
+/// Which values `-stack-list-variables` and `-stack-list-arguments` print
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrintValues {
+    /// Names only
+    Names,
+    /// Values of simple types (not arrays, structures or unions), with the types
+    #[default]
+    Simple,
+    /// All values, without the types
+    All,
+}
+
+impl PrintValues {
+    pub(crate) fn as_option(&self) -> &'static str {
+        match self {
+            PrintValues::Names => "--no-values",
+            PrintValues::Simple => "--simple-values",
+            PrintValues::All => "--all-values",
+        }
+    }
+}
+
+/// A local variable or argument of a frame
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VariableValue {
+    pub name: String,
+    /// Reported with `PrintValues::Simple` only
+    pub type_name: Option<String>,
+    /// Missing for `PrintValues::Names`, and for non simple types with
+    /// `PrintValues::Simple`
+    pub value: Option<String>,
+}
+
+impl VariableValue {
+    fn from_vars(vars: &[Variable]) -> Option<VariableValue> {
+        let text = |name| find_field(vars, name).and_then(Value::unquoted);
+        Some(VariableValue {
+            name: text("name")?,
+            type_name: text("type"),
+            value: text("value"),
+        })
+    }
+}
+
+/// Parse a list of variables: `[{name="x",type="int",value="1"},...]`, or `[name="x",...]`
+/// when gdb prints names only
+pub(crate) fn variable_values(list: Option<&Value>, skip_args: bool) -> Vec<VariableValue> {
+    match list {
+        Some(Value::ValueList(values)) => values
+            .iter()
+            .filter_map(|value| match value {
+                Value::VariableList(vars) => Some(vars),
+                _ => None,
+            })
+            .filter(|vars| !skip_args || find_field(vars, "arg").is_none())
+            .filter_map(|vars| VariableValue::from_vars(vars))
+            .collect(),
+        Some(Value::ResultList(vars)) => vars
+            .iter()
+            .filter(|var| var.name == "name")
+            .filter_map(|var| var.value.unquoted())
+            .map(|name| VariableValue {
+                name,
+                ..Default::default()
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+type JsonResult<'a> = Pin<Box<dyn Future<Output = Result<serde_json::Value>> + Send + 'a>>;
+
+impl Debugger {
+    /// The local variables of the frame described by `context`, arguments excluded
+    /// (`-stack-list-variables`)
+    pub async fn locals(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        context: &FrameContext,
+        values: PrintValues,
+    ) -> Result<Vec<VariableValue>> {
+        let options = self.context_options(output_channel, context).await?;
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-stack-list-variables {} {}", options, values.as_option()),
+            )
+            .await?;
+        let mut variables = variable_values(record.get("variables"), true);
+        self.render_variables(output_channel, &options, &mut variables)
+            .await?;
+        Ok(variables)
+    }
+
+    /// The arguments of frame `frame` (0 is the innermost) (`-stack-list-arguments`)
+    pub async fn frame_args(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        frame: u32,
+        values: PrintValues,
+    ) -> Result<Vec<VariableValue>> {
+        let thread = match self.snapshot().selected_thread {
+            Some(thread) => format!("--thread {} ", thread),
+            None => String::new(),
+        };
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!(
+                    "-stack-list-arguments {}{} {} {}",
+                    thread,
+                    values.as_option(),
+                    frame,
+                    frame
+                ),
+            )
+            .await?;
+        let mut args = variable_values(record.lookup("stack-args.frame.args"), false);
+        if !self.renderers().is_empty() {
+            let options = self
+                .context_options(output_channel, &FrameContext::frame(frame))
+                .await?;
+            self.render_variables(output_channel, &options, &mut args)
+                .await?;
+        }
+        Ok(args)
+    }
+
+    /// Replace the values of `variables` that have a renderer. Their types are known with
+    /// `PrintValues::Simple` only
+    async fn render_variables(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        options: &str,
+        variables: &mut [VariableValue],
+    ) -> Result<()> {
+        let renderers = self.renderers();
+        if renderers.is_empty() {
+            return Ok(());
+        }
+        for variable in variables {
+            let Some(kind) = &variable.type_name else {
+                continue;
+            };
+            if renderers.find(kind).is_none() {
+                continue;
+            }
+            let rendered = self
+                .render_expression(output_channel, &renderers, options, &variable.name)
+                .await?;
+            if rendered.is_some() {
+                variable.value = rendered;
+            }
+        }
+        Ok(())
+    }
+
+    /// Export the local variables of frame `frame` (0 is the innermost) as a JSON object,
+    /// expanding structures, arrays and pointers up to `depth_limit` levels:
+    ///
+    /// `{"p": {"type": "struct point", "value": "{...}", "children": {"x": {...}, ...}}}`
+    ///
+    /// Nodes with children beyond the depth limit are marked `"truncated": true`. The values
+    /// that have a renderer are replaced, see `register_renderer()`
+    pub async fn dump_variables(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        frame: u32,
+        depth_limit: usize,
+    ) -> Result<serde_json::Value> {
+        let options = self
+            .context_options(output_channel, &FrameContext::frame(frame))
+            .await?;
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-stack-list-variables {} --no-values", options),
+            )
+            .await?;
+        let names: Vec<String> = match record.get("variables") {
+            Some(Value::ValueList(vars)) => vars
+                .iter()
+                .filter_map(|var| match var {
+                    Value::VariableList(vars) => find_field(vars, "name").and_then(Value::unquoted),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let renderers = self.renderers();
+        let mut result = Map::new();
+        for name in names {
+            let record = self
+                .send_cmd(
+                    output_channel,
+                    &format!("-var-create {} - * {}", options, mi_quote(&name)),
+                )
+                .await?;
+            let varobj = record
+                .get("name")
+                .and_then(Value::unquoted)
+                .ok_or(Error::UnexpectedResponse)?;
+            let node = self
+                .varobj_to_json(output_channel, &varobj, &record.content, depth_limit)
+                .await;
+            // always clean up the varobj, even if expanding it failed
+            self.send_cmd(output_channel, &format!("-var-delete {}", varobj))
+                .await?;
+            let mut node = node?;
+            renderers.apply(&mut node);
+            result.insert(name, node);
+        }
+        Ok(serde_json::Value::Object(result))
+    }
+
+    /// `vars` are the fields describing varobj `varobj` (`type`, `value`, `numchild`...)
+    pub(crate) fn varobj_to_json<'a>(
+        &'a self,
+        output_channel: &'a mut Receiver<msg::Record>,
+        varobj: &'a str,
+        vars: &'a [Variable],
+        depth_limit: usize,
+    ) -> JsonResult<'a> {
+        Box::pin(async move {
+            let text = |name| find_field(vars, name).and_then(Value::unquoted);
+            let mut node = Map::new();
+            if let Some(kind) = text("type") {
+                node.insert("type".to_string(), json!(kind));
+            }
+            if let Some(value) = text("value") {
+                node.insert("value".to_string(), json!(value));
+            }
+            let numchild: usize = text("numchild").and_then(|n| n.parse().ok()).unwrap_or(0);
+            if numchild == 0 {
+                return Ok(serde_json::Value::Object(node));
+            }
+            if depth_limit == 0 {
+                node.insert("truncated".to_string(), json!(true));
+                return Ok(serde_json::Value::Object(node));
+            }
+            let children = self
+                .varobj_children(output_channel, varobj, depth_limit)
+                .await?;
+            node.insert("children".to_string(), serde_json::Value::Object(children));
+            Ok(serde_json::Value::Object(node))
+        })
+    }
+
+    async fn varobj_children(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        varobj: &str,
+        depth_limit: usize,
+    ) -> Result<Map<String, serde_json::Value>> {
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-var-list-children --all-values {}", varobj),
+            )
+            .await?;
+        let mut children = Map::new();
+        let Some(Value::ResultList(list)) = record.get("children") else {
+            return Ok(children);
+        };
+        for child in list {
+            let Value::VariableList(vars) = &child.value else {
+                continue;
+            };
+            let text = |name| find_field(vars, name).and_then(Value::unquoted);
+            let (Some(name), Some(exp)) = (text("name"), text("exp")) else {
+                continue;
+            };
+            if ACCESS_SPECIFIERS.contains(&exp.as_str()) && text("type").is_none() {
+                // not a level of its own, hoist its members
+                children.extend(
+                    Box::pin(self.varobj_children(output_channel, &name, depth_limit)).await?,
+                );
+                continue;
+            }
+            let node = self
+                .varobj_to_json(output_channel, &name, vars, depth_limit - 1)
+                .await?;
+            children.insert(exp, node);
+        }
+        Ok(children)
+    }
+}
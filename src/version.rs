@@ -0,0 +1,110 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::dbg::{Debugger, Error, MiDialect, Result};
+use crate::msg;
+use std::fmt;
+use tokio::sync::mpsc::Receiver;
+
+/// A gdb version, e.g. `13.2`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    /// `-break-insert --qualified`
+    pub const QUALIFIED_BREAKPOINTS: Version = Version::new(8, 1, 0);
+    /// `-catch-throw`, `-catch-catch` and `-catch-rethrow`
+    pub const CATCH_EXCEPTIONS: Version = Version::new(8, 1, 0);
+    /// `--interpreter=mi3`
+    pub const MI3: Version = Version::new(9, 1, 0);
+    /// `--interpreter=mi4`
+    pub const MI4: Version = Version::new(13, 1, 0);
+
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// The version in the first line of gdb's banner (`show version`), e.g.
+    /// `GNU gdb (Ubuntu 12.1-0ubuntu1~22.04) 12.1` or `GNU gdb (GDB) 14.0.50.20230907-git`
+    pub fn from_banner(text: &str) -> Option<Version> {
+        let line = text.lines().next()?;
+        if !line.starts_with("GNU gdb") {
+            return None;
+        }
+        // the package name in parentheses may contain a version too, and distributions
+        // add their own suffixes: `13.2-3.fc39`
+        let version = line.rsplit(' ').next()?;
+        let end = version
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(version.len());
+        let mut numbers = version[..end].split('.').map(|n| n.parse::<u32>().ok());
+        let major = numbers.next()??;
+        let minor = numbers.next().flatten().unwrap_or(0);
+        let patch = numbers.next().flatten().unwrap_or(0);
+        Some(Version::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.patch > 0 {
+            write!(f, ".{}", self.patch)?;
+        }
+        Ok(())
+    }
+}
+
+impl Debugger {
+    /// The version of gdb, from its banner or `-gdb-version`. `None` if unknown, e.g.
+    /// with lldb-mi
+    pub fn gdb_version(&self) -> Option<Version> {
+        self.session.lock().unwrap().gdb_version
+    }
+
+    /// Run `-gdb-version` if the banner did not tell the version, e.g. with `gdb -q`
+    pub(crate) async fn detect_gdb_version(&self, output_channel: &mut Receiver<msg::Record>) {
+        if self.gdb_version().is_some() || self.dialect() != MiDialect::Gdb {
+            return;
+        }
+        // the reader picks the version from the console output
+        if let Err(e) = self.query(output_channel, "-gdb-version").await {
+            tracing::debug!("failed to get gdb's version: {}", e);
+        }
+    }
+
+    /// Fail with `Error::Unsupported` if gdb is older than `required` for `feature`.
+    /// Unknown versions are given the benefit of the doubt
+    pub fn require_gdb(&self, required: Version, feature: &str) -> Result<()> {
+        match self.gdb_version() {
+            Some(found) if found < required => Err(Error::Unsupported {
+                feature: feature.to_string(),
+                required,
+                found,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::breakpoints::Breakpoint;
+use crate::dbg::{mi_quote, Debugger, Error, Result};
+use crate::msg::{self, AsyncClass, MessageRecord, Value};
+use tokio::sync::mpsc::Receiver;
+
+/// The kind of access that triggers a watchpoint
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WatchKind {
+    /// The value is written (`watch`)
+    #[default]
+    Write,
+    /// The value is read (`rwatch`)
+    Read,
+    /// The value is read or written (`awatch`)
+    Access,
+}
+
+impl WatchKind {
+    /// The watchpoint kind of breakpoint type `kind` (e.g. `hw watchpoint`)
+    pub(crate) fn from_breakpoint_type(kind: &str) -> Option<WatchKind> {
+        if !kind.ends_with("watchpoint") {
+            return None;
+        }
+        Some(if kind.starts_with("read") {
+            WatchKind::Read
+        } else if kind.starts_with("acc") {
+            WatchKind::Access
+        } else {
+            WatchKind::Write
+        })
+    }
+}
+
+/// Options for `Debugger::insert_watchpoint()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchpointOptions {
+    pub kind: WatchKind,
+    /// Only stop when this thread accesses the value
+    pub thread: Option<String>,
+    /// Evaluate the expression in this frame (0 is the innermost). A watchpoint on locals
+    /// is deleted by gdb when that frame exits, which is reported with
+    /// `DebuggerEvent::WatchpointOutOfScope`
+    pub frame: Option<u32>,
+}
+
+/// The number of the watchpoint deleted by a `*stopped,reason="watchpoint-scope"` record
+pub(crate) fn out_of_scope_watchpoint(record: &MessageRecord<AsyncClass>) -> Option<String> {
+    if record.class != AsyncClass::Stopped
+        || record.get("reason").and_then(Value::as_str).as_deref() != Some("watchpoint-scope")
+    {
+        return None;
+    }
+    record.get("wpnum").and_then(Value::unquoted)
+}
+
+impl Debugger {
+    /// Stop the program when `expression` is accessed as described by `options`
+    pub async fn insert_watchpoint(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        expression: &str,
+        options: &WatchpointOptions,
+    ) -> Result<Breakpoint> {
+        if let Some(frame) = options.frame {
+            self.send_cmd(output_channel, &format!("-stack-select-frame {}", frame))
+                .await?;
+        }
+        if let Some(thread) = &options.thread {
+            return self
+                .insert_thread_watchpoint(output_channel, expression, options.kind, thread)
+                .await;
+        }
+        let flag = match options.kind {
+            WatchKind::Write => "",
+            WatchKind::Read => " -r",
+            WatchKind::Access => " -a",
+        };
+        let record = self
+            .send_cmd(
+                output_channel,
+                &format!("-break-watch{} {}", flag, mi_quote(expression)),
+            )
+            .await?;
+        // `wpt`, `hw-rwpt` or `hw-awpt` depending on the kind
+        let (kind, value) = [
+            ("watchpoint", "wpt"),
+            ("read watchpoint", "hw-rwpt"),
+            ("acc watchpoint", "hw-awpt"),
+        ]
+        .into_iter()
+        .find_map(|(kind, field)| record.get(field).map(|value| (kind, value)))
+        .ok_or(Error::UnexpectedResponse)?;
+        let number = value
+            .get("number")
+            .and_then(Value::unquoted)
+            .ok_or(Error::UnexpectedResponse)?;
+        let wpt = Breakpoint {
+            number,
+            kind: Some(kind.to_string()),
+            enabled: true,
+            original_location: Some(expression.to_string()),
+            ..Default::default()
+        };
+        // gdb does not notify about breakpoints created by MI commands
+        self.session.lock().unwrap().breakpoints.insert(wpt.clone());
+        Ok(wpt)
+    }
+
+    async fn insert_thread_watchpoint(
+        &self,
+        output_channel: &mut Receiver<msg::Record>,
+        expression: &str,
+        kind: WatchKind,
+        thread: &str,
+    ) -> Result<Breakpoint> {
+        // `-break-watch` has no thread option, the CLI command does
+        let command = match kind {
+            WatchKind::Write => "watch",
+            WatchKind::Read => "rwatch",
+            WatchKind::Access => "awatch",
+        };
+        let cmd = format!("{} {} thread {}", command, expression, thread);
+        self.insert_with_console(output_channel, &cmd).await
+    }
+}
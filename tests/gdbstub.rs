@@ -0,0 +1,153 @@
+/*
+ * This file is part of rust-gdb.
+ *
+ * rust-gdb is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * rust-gdb is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with rust-gdb.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Drive the debugger against a pure-Rust `gdbstub` server: a single threaded x86_64
+//! "machine" with a few bytes of memory and no execution support.
+//! Requires a gdb binary, run with `cargo test -- --ignored`
+
+use gdbstub::common::Signal;
+use gdbstub::conn::ConnectionExt;
+use gdbstub::stub::{run_blocking, DisconnectReason, GdbStub, SingleThreadStopReason};
+use gdbstub::target::ext::base::singlethread::SingleThreadBase;
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::{Target, TargetResult};
+use gdbstub_arch::x86::reg::X86_64CoreRegs;
+use gdbstub_arch::x86::X86_64_SSE;
+use std::net::{TcpListener, TcpStream};
+
+const PC: u64 = 0x1000;
+
+struct Machine {
+    regs: X86_64CoreRegs,
+    mem: Vec<u8>,
+}
+
+impl Target for Machine {
+    type Arch = X86_64_SSE;
+    type Error = ();
+
+    fn base_ops(&mut self) -> BaseOps<'_, Self::Arch, Self::Error> {
+        BaseOps::SingleThread(self)
+    }
+}
+
+impl SingleThreadBase for Machine {
+    fn read_registers(&mut self, regs: &mut X86_64CoreRegs) -> TargetResult<(), Self> {
+        *regs = self.regs.clone();
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &X86_64CoreRegs) -> TargetResult<(), Self> {
+        self.regs = regs.clone();
+        Ok(())
+    }
+
+    fn read_addrs(&mut self, start_addr: u64, data: &mut [u8]) -> TargetResult<usize, Self> {
+        for (addr, byte) in (start_addr..).zip(data.iter_mut()) {
+            *byte = self
+                .mem
+                .get(addr.wrapping_sub(PC) as usize)
+                .copied()
+                .unwrap_or(0);
+        }
+        Ok(data.len())
+    }
+
+    fn write_addrs(&mut self, _start_addr: u64, _data: &[u8]) -> TargetResult<(), Self> {
+        Ok(())
+    }
+}
+
+enum MachineEventLoop {}
+
+impl run_blocking::BlockingEventLoop for MachineEventLoop {
+    type Target = Machine;
+    type Connection = TcpStream;
+    type StopReason = SingleThreadStopReason<u64>;
+
+    #[allow(clippy::type_complexity)]
+    fn wait_for_stop_reason(
+        _target: &mut Machine,
+        conn: &mut TcpStream,
+    ) -> Result<
+        run_blocking::Event<Self::StopReason>,
+        run_blocking::WaitForStopReasonError<(), std::io::Error>,
+    > {
+        // the machine can not execute, so resuming only waits for gdb's next packet
+        let byte = conn
+            .read()
+            .map_err(run_blocking::WaitForStopReasonError::Connection)?;
+        Ok(run_blocking::Event::IncomingData(byte))
+    }
+
+    fn on_interrupt(_target: &mut Machine) -> Result<Option<Self::StopReason>, ()> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
+
+/// Serve a single gdb connection on a random port. Return the port
+fn spawn_stub() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut machine = Machine {
+            regs: X86_64CoreRegs {
+                rip: PC,
+                ..Default::default()
+            },
+            mem: vec![0x90, 0x90, 0xcc],
+        };
+        match GdbStub::new(stream).run_blocking::<MachineEventLoop>(&mut machine) {
+            Ok(DisconnectReason::Disconnect) | Ok(DisconnectReason::Kill) => {}
+            Ok(reason) => panic!("unexpected disconnect: {:?}", reason),
+            Err(e) => panic!("stub failed: {:?}", e),
+        }
+    });
+    port
+}
+
+#[test]
+#[ignore = "requires a gdb binary"]
+fn gdbstub_remote_session() {
+    let port = spawn_stub();
+    gdb::run_async(async move {
+        let (dbg, mut rx) = gdb::Debugger::start().await.unwrap();
+        dbg.connect_remote(&mut rx, &format!("localhost:{port}"))
+            .await
+            .unwrap();
+        assert!(dbg.is_remote_connected());
+
+        // gdbstub targets without the thread extensions show up as a single synthetic thread
+        let capabilities = dbg.remote_capabilities().unwrap();
+        assert!(!capabilities.threads);
+        assert!(!capabilities.process_info);
+        assert!(dbg.get_debuggee_pid(None).is_none());
+
+        let resp = dbg
+            .send_cmd(&mut rx, "-data-evaluate-expression $pc")
+            .await
+            .unwrap();
+        assert!(format!("{:?}", resp).contains("0x1000"), "unexpected pc: {:?}", resp);
+
+        let resp = dbg
+            .send_cmd(&mut rx, "-data-read-memory-bytes 0x1000 3")
+            .await
+            .unwrap();
+        assert!(format!("{:?}", resp).contains("9090cc"));
+    });
+}